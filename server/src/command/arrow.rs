@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    ipc::writer::StreamWriter,
+    record_batch::RecordBatch,
+};
+use serde_json::Value;
+
+use crate::db::connection::ColumnMeta;
+
+/// Map a database type name onto the Arrow type used to carry it.
+fn arrow_type(type_name: &str) -> DataType {
+    let type_name = type_name.to_uppercase();
+    let base = type_name.split_whitespace().next().unwrap_or_default();
+    match base {
+        "BOOL" | "BOOLEAN" => DataType::Boolean,
+        // 超过 i64::MAX 的值放不进 Int64
+        "BIGINT" if type_name.contains("UNSIGNED") => DataType::UInt64,
+        "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "INTEGER" | "BIGINT" | "INT2" | "INT4"
+        | "INT8" => DataType::Int64,
+        "FLOAT" | "FLOAT4" | "FLOAT8" | "REAL" | "DOUBLE" | "NUMERIC" | "DECIMAL" => {
+            DataType::Float64
+        }
+        _ => DataType::Utf8,
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn build_column(name: &str, data_type: &DataType, rows: &[Value]) -> ArrayRef {
+    let values = rows.iter().map(|row| row.get(name).unwrap_or(&Value::Null));
+    match data_type {
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for value in values {
+                builder.append_option(match value {
+                    Value::Bool(b) => Some(*b),
+                    Value::Number(n) => n.as_i64().map(|n| n != 0),
+                    Value::String(s) => match s.as_str() {
+                        "1" | "t" | "true" => Some(true),
+                        "0" | "f" | "false" => Some(false),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                builder.append_option(as_text(value).and_then(|s| s.parse().ok()));
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::UInt64 => {
+            let mut builder = UInt64Builder::new();
+            for value in values {
+                builder.append_option(as_text(value).and_then(|s| s.parse().ok()));
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                builder.append_option(as_text(value).and_then(|s| s.parse().ok()));
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                builder.append_option(as_text(value));
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Serialize JSON row objects to an Arrow IPC stream with one record batch.
+pub fn to_ipc(columns: &[ColumnMeta], rows: &Value) -> anyhow::Result<Vec<u8>> {
+    let rows = rows.as_array().map(Vec::as_slice).unwrap_or_default();
+
+    let mut fields = Vec::new();
+    let mut arrays = Vec::new();
+    for column in columns {
+        let data_type = arrow_type(&column.type_name);
+        arrays.push(build_column(&column.name, &data_type, rows));
+        fields.push(Field::new(&column.name, data_type, true));
+    }
+
+    let schema = Arc::new(Schema::new(fields));
+    // A batch needs at least one column, which we don't know without rows
+    let batch = if arrays.is_empty() {
+        RecordBatch::new_empty(Arc::clone(&schema))
+    } else {
+        RecordBatch::try_new(Arc::clone(&schema), arrays)?
+    };
+
+    let mut buffer = Vec::new();
+    {
+        let mut writer = StreamWriter::try_new(&mut buffer, &schema)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+    }
+    Ok(buffer)
+}
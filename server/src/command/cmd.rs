@@ -1,56 +1,534 @@
+use std::{collections::HashMap, sync::Arc};
+
+#[cfg(feature = "arrow")]
+use base64::Engine;
+
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tower_lsp::lsp_types::{ExecuteCommandParams, MessageType};
+use sqlparser::ast::Statement;
+use tokio::sync::mpsc;
+use tower_lsp::{
+    Client,
+    lsp_types::{
+        ExecuteCommandParams, MessageType, Position, Range, Url, notification::Notification,
+    },
+};
 
 use crate::{
-    constant::{SERVER_CHECK_CONNECTION, SERVER_EXECUTE_COMMAND},
-    db::connection::DBConnectionOptions,
+    constant::{
+        SERVER_BEGIN_SESSION, SERVER_CHECK_CONNECTION, SERVER_CHECK_CONNECTIONS,
+        SERVER_CLEAR_RESULT_CACHE, SERVER_END_SESSION, SERVER_EXECUTE_COMMAND,
+        SERVER_EXECUTE_TRANSACTION, SERVER_RUN_RANGE, SERVER_RUN_STATEMENT_AT,
+        SERVER_VALIDATE_CONNECTION,
+    },
+    db::{
+        DatabaseType,
+        cache::{self, CacheKey},
+        connection::{
+            BindValue, DBConnectionOptions, QueryOutput, QueryParam, QueryTiming, StreamItem,
+            validate_connection_string,
+        },
+        schema, session,
+        value::FormatOptions,
+    },
     logger::log,
+    parser::{self, DocumentMap, ResultKind, SqlParser, affected_objects, count_query, is_ddl},
+    settings,
 };
 
-use super::{Command, CommandResult};
+use super::{
+    Command, CommandResult, ConnectionParams, DocumentConnections, Timing, parse_arguments,
+};
 
 // 定义SQL查询请求参数结构
 #[derive(Debug, Deserialize)]
 struct ExecuteQueryParams {
     query: String,
+    /// Values for the query's positional placeholders
     #[serde(default)]
-    connection_id: String,
+    params: Vec<QueryParam>,
+    #[serde(flatten)]
+    connection: ConnectionParams,
     #[serde(default)]
-    connection_string: String,
+    format: ResultFormat,
+    #[serde(default)]
+    layout: ResultLayout,
+    /// Run in this session's transaction instead of on the pool
+    #[serde(default)]
+    session_id: Option<String>,
+    /// Run the query even if a cached result is available
+    #[serde(default)]
+    bypass_cache: bool,
+    /// Run a query made of several statements one after another, returning
+    /// a `results` array like [`RunRangeCommand`]. Without it such a query
+    /// is rejected.
+    #[serde(default)]
+    multi_statement: bool,
+    /// Also run a `COUNT(*)` of a SELECT without its LIMIT and return it as
+    /// `total_rows`, so the grid can page through the result. Costs a second
+    /// query.
+    #[serde(default)]
+    count_total: bool,
+    /// Whether the query returns rows, when the client already knows it
+    /// e.g. from the lens the query came from. When set it decides between
+    /// the rows and the affected-rows path and the query is neither parsed
+    /// nor split into statements. When absent the statement is parsed,
+    /// falling back to a keyword check if that fails.
+    #[serde(default)]
+    is_read: Option<bool>,
+    /// Run a single INSERT, UPDATE or DELETE in a transaction that is rolled
+    /// back, returning the rows it would affect (or its RETURNING rows)
+    /// without keeping the changes.
+    #[serde(default)]
+    preview: bool,
+    /// When a query fails after some rows arrived, e.g. on a cast error
+    /// deep into the result, return those rows flagged `partial` with the
+    /// `error` instead of failing. Rows are read over the streaming path,
+    /// bypassing the result cache; ignored in a session.
+    #[serde(default)]
+    partial_results: bool,
+    /// Document the query came from, see `tag_queries`
+    #[serde(default)]
+    uri: Option<String>,
+}
+
+/// Encoding of the returned rows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ResultFormat {
+    /// An array of row objects
+    #[default]
+    Json,
+    /// A base64 encoded Arrow IPC stream, needs the `arrow` feature
+    Arrow,
+}
+
+/// Shape of JSON rows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ResultLayout {
+    /// An array of row objects
+    #[default]
+    Rows,
+    /// One array of values per column, keyed by column name
+    Columns,
+}
+
+/// Requested encoding and shape of a result.
+#[derive(Debug, Clone, Copy)]
+struct ResultShape {
+    format: ResultFormat,
+    layout: ResultLayout,
 }
 
 // 定义SQL查询结果结构
 #[derive(Debug, Serialize)]
 struct QueryResult {
+    /// `rows` for result sets, `affected` for row counts
+    kind: ResultKind,
+    format: ResultFormat,
+    layout: ResultLayout,
     columns: Vec<String>,
+    /// Null when `kind` is `affected`, a base64 string for the arrow format
     rows: serde_json::Value,
+    /// Number of returned rows or of affected rows, depending on `kind`
     affected_rows: usize,
+    /// Served from the result cache instead of the database
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cached: bool,
+    /// Rows of the whole query ignoring its LIMIT, see `count_total`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_rows: Option<u64>,
+    /// The statement ran in a rolled back transaction, see `preview`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    preview: bool,
+    /// The query failed part way, `rows` are those read before `error`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    partial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// `db/schemaChanged` notification sent after DDL ran, so the client can
+/// refetch the affected explorer nodes.
+enum SchemaChanged {}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SchemaChangedParams {
+    connection_id: String,
+    objects: Vec<String>,
+}
+
+impl Notification for SchemaChanged {
+    type Params = SchemaChangedParams;
+    const METHOD: &'static str = "db/schemaChanged";
+}
+
+/// Forget the cached schema of a connection and notify the client, when a
+/// query created, altered or dropped objects.
+async fn schema_changed(client: &Client, connection_id: &str, objects: &[String]) {
+    if objects.is_empty() {
+        return;
+    }
+    if !connection_id.is_empty() {
+        schema::invalidate(connection_id).await;
+    }
+    client
+        .send_notification::<SchemaChanged>(SchemaChangedParams {
+            connection_id: connection_id.to_string(),
+            objects: objects.to_vec(),
+        })
+        .await;
+}
+
+/// Prefix a query with a comment naming the document it came from when
+/// `tag_queries` is on and the connection is PostgreSQL.
+async fn tag_query(query: &str, uri: Option<&str>, connection: &ConnectionParams) -> String {
+    if let Some(uri) = uri
+        && settings::get().tag_queries
+        && let Ok(pool) = connection.pool().await
+        && pool.database_type() == DatabaseType::PostgreSQL
+    {
+        // URI 里的 */ 会提前结束注释
+        return format!("/* {} */ {}", uri.replace("*/", "*%2F"), query);
+    }
+    query.to_string()
 }
 
 #[derive(Debug)]
-pub struct ExecuteCommand;
+pub struct ExecuteCommand {
+    pub client: Arc<Client>,
+}
 
 impl ExecuteCommand {
     // 执行SQL查询的实现
     async fn execute_sql_query(
         &self,
         query: &str,
-        connection_id: &str,
-        options: DBConnectionOptions,
-    ) -> anyhow::Result<QueryResult> {
-        let connect = crate::db::from_cache(connection_id, options).await;
-        let pool = connect
-            .get_pool()
+        kind: ResultKind,
+        params: &[BindValue],
+        connection: &ConnectionParams,
+        shape: ResultShape,
+        bypass_cache: bool,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let connection_id = connection.connection_id.as_str();
+        let settings = settings::get().result_cache;
+        // 缓存的是按 format 设置格式化的行
+        let key = (settings.enabled
+            && kind == ResultKind::Rows
+            && shape.format == ResultFormat::Json
+            && parser::is_cacheable(query))
+        .then(|| CacheKey::new(connection_id, query, params));
+        if let Some(key) = &key
+            && !bypass_cache
+            && let Some(output) = cache::get(key, &settings)
+        {
+            let (mut result, timing, warnings) =
+                Self::query_result(output, kind, shape.format, shape.layout)?;
+            result.cached = true;
+            return Ok((result, timing, warnings));
+        }
+
+        let pool = connection.pool_for(query).await?;
+        let output = pool
+            .execute_query_as(query, params, kind, &Self::row_format(shape.format))
+            .await?;
+        match key {
+            Some(key) => cache::put(key, &output, &settings),
+            // 写操作之后该连接缓存的结果可能已过期
+            None if !parser::is_read_only(query) => {
+                cache::clear(Some(connection_id));
+            }
+            None => {}
+        }
+        Self::query_result(output, kind, shape.format, shape.layout)
+    }
+
+    /// Run one statement of an [`ExecuteQueryParams`] request, in its
+    /// session if it has one.
+    async fn run(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        req: &ExecuteQueryParams,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let query = &tag_query(query, req.uri.as_deref(), &req.connection).await;
+        let kind = match req.is_read {
+            Some(true) => ResultKind::Rows,
+            Some(false) => ResultKind::Affected,
+            None => SqlParser::new().result_kind(query),
+        };
+        if req.preview {
+            return self.execute_preview(query, kind, params, req).await;
+        }
+        if req.partial_results && kind == ResultKind::Rows && req.session_id.is_none() {
+            return self.execute_partial(query, params, req).await;
+        }
+        match &req.session_id {
+            Some(session_id) => {
+                self.execute_in_session(query, kind, params, session_id, req.format, req.layout)
+                    .await
+            }
+            None => {
+                self.execute_sql_query(
+                    query,
+                    kind,
+                    params,
+                    &req.connection,
+                    ResultShape {
+                        format: req.format,
+                        layout: req.layout,
+                    },
+                    req.bypass_cache,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Run a write in a transaction that is always rolled back, reporting
+    /// what it would have changed.
+    async fn execute_preview(
+        &self,
+        query: &str,
+        kind: ResultKind,
+        params: &[BindValue],
+        req: &ExecuteQueryParams,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let pool = req.connection.pool().await?;
+        let mut session = pool.begin_session().await?;
+        let output = session
+            .execute_as(query, params, kind, &Self::row_format(req.format))
+            .await;
+        // 语句出错时同样回滚
+        let rolled_back = session.rollback().await;
+        let output = output?;
+        rolled_back?;
+        let (mut result, timing, warnings) =
+            Self::query_result(output, kind, req.format, req.layout)?;
+        result.preview = true;
+        Ok((result, timing, warnings))
+    }
+
+    /// Read a query's rows as they arrive, keeping those read before an
+    /// error. Fails as usual when the error comes before the first row.
+    async fn execute_partial(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        req: &ExecuteQueryParams,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let pool = req.connection.pool_for(query).await?;
+        let started = std::time::Instant::now();
+        let (tx, mut rx) = mpsc::channel(1024);
+        let collect = async {
+            let (mut columns, mut rows) = (Vec::new(), Vec::new());
+            while let Some(item) = rx.recv().await {
+                match item {
+                    StreamItem::Columns(meta) => columns = meta,
+                    StreamItem::Row(row) => rows.push(serde_json::Value::Object(row)),
+                }
+            }
+            (columns, rows)
+        };
+        let (streamed, (columns, rows)) = tokio::join!(
+            pool.stream_query(query, params, None, &Self::row_format(req.format), tx),
+            collect
+        );
+        let error = match streamed {
+            Ok(_) => None,
+            Err(e) if rows.is_empty() => return Err(e),
+            Err(e) => Some(e.to_string()),
+        };
+
+        let output = QueryOutput {
+            columns,
+            total: rows.len(),
+            rows: serde_json::Value::Array(rows),
+            timing: QueryTiming {
+                execute: started.elapsed(),
+                ..Default::default()
+            },
+            warnings: Vec::new(),
+        };
+        let (mut result, timing, warnings) =
+            Self::query_result(output, ResultKind::Rows, req.format, req.layout)?;
+        result.partial = error.is_some();
+        result.error = error;
+        Ok((result, timing, warnings))
+    }
+
+    /// Whether a query is a single statement `preview` can run.
+    fn previewable(query: &str) -> bool {
+        SqlParser::new()
+            .with_recovery(false)
+            .parse(query)
+            .is_ok_and(|ast| {
+                matches!(
+                    ast.statements.as_slice(),
+                    [Statement::Insert(_) | Statement::Update { .. } | Statement::Delete(_)]
+                )
+            })
+    }
+
+    /// Run the [`count_query`] of a request's query, on its session or pool.
+    /// None when the query can't be counted.
+    async fn count_rows(
+        params: &[BindValue],
+        req: &ExecuteQueryParams,
+    ) -> anyhow::Result<Option<u64>> {
+        let db_type = req.connection.pool().await?.database_type();
+        let Some(count_sql) = count_query(&req.query, &db_type) else {
+            return Ok(None);
+        };
+        let count_sql = count_sql.as_str();
+        let output = match &req.session_id {
+            Some(session_id) => {
+                let session = session::get(session_id)?;
+                let mut session = session.lock().await;
+                session.execute(count_sql, params, ResultKind::Rows).await?
+            }
+            None => {
+                let pool = req.connection.pool_for(count_sql).await?;
+                pool.execute_query(count_sql, params, ResultKind::Rows)
+                    .await?
+            }
+        };
+        // 数字格式化后可能是字符串
+        let count = output
+            .rows
+            .get(0)
+            .and_then(|row| row.as_object())
+            .and_then(|row| row.values().next())
+            .and_then(|value| match value {
+                serde_json::Value::Number(n) => n.as_u64(),
+                serde_json::Value::String(s) => s.parse().ok(),
+                _ => None,
+            });
+        count
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("Count query returned no number"))
+    }
+
+    /// Split a query into its statements, each sliced from the query as
+    /// written. A single statement is returned unchanged.
+    fn split_statements(query: &str) -> Vec<String> {
+        let chunks = parser::split_statements(query);
+        if chunks.len() < 2 {
+            return vec![query.to_string()];
+        }
+        chunks.into_iter().map(|chunk| chunk.sql).collect()
+    }
+
+    /// Run a query in an open session, see [`BeginSessionCommand`].
+    async fn execute_in_session(
+        &self,
+        query: &str,
+        kind: ResultKind,
+        params: &[BindValue],
+        session_id: &str,
+        format: ResultFormat,
+        layout: ResultLayout,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let session = session::get(session_id)?;
+        let output = session
+            .lock()
             .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to get pool from connection"))?;
-        let (res, total) = pool.execute_query(query).await?;
+            .execute_as(query, params, kind, &Self::row_format(format))
+            .await?;
+        Self::query_result(output, kind, format, layout)
+    }
 
-        Ok(QueryResult {
-            columns: Vec::new(),
-            rows: res,
-            affected_rows: total,
-        })
+    /// How rows are formatted for a result format. Arrow columns are parsed
+    /// back from the values, so they get plain values whatever the `format`
+    /// init option says.
+    fn row_format(format: ResultFormat) -> FormatOptions {
+        let options = settings::get().format;
+        match format {
+            ResultFormat::Json => options,
+            ResultFormat::Arrow => options.for_arrow(),
+        }
+    }
+
+    fn query_result(
+        output: QueryOutput,
+        kind: ResultKind,
+        format: ResultFormat,
+        layout: ResultLayout,
+    ) -> anyhow::Result<(QueryResult, Timing, Vec<String>)> {
+        let columns: Vec<String> = output.columns.iter().map(|c| c.name.clone()).collect();
+        let rows = match format {
+            ResultFormat::Arrow if kind == ResultKind::Rows => Self::encode_arrow(&output)?,
+            _ if layout == ResultLayout::Columns => Self::to_columns(&columns, output.rows),
+            _ => output.rows,
+        };
+        let result = QueryResult {
+            kind,
+            format,
+            layout,
+            columns,
+            rows,
+            affected_rows: output.total,
+            cached: false,
+            total_rows: None,
+            preview: false,
+            partial: false,
+            error: None,
+        };
+        Ok((result, output.timing.into(), output.warnings))
+    }
+
+    /// Objects touched by the DDL statements of a query, so the client can
+    /// refresh just those tree nodes.
+    fn ddl_objects(query: &str) -> Vec<String> {
+        let Ok(ast) = SqlParser::new().parse(query) else {
+            return Vec::new();
+        };
+        ast.statements
+            .iter()
+            .filter(|statement| is_ddl(statement))
+            .flat_map(affected_objects)
+            .collect()
+    }
+
+    /// Transpose row objects into one array of values per column.
+    fn to_columns(columns: &[String], rows: serde_json::Value) -> serde_json::Value {
+        let serde_json::Value::Array(rows) = rows else {
+            return rows;
+        };
+        let mut data: Vec<(String, Vec<serde_json::Value>)> = columns
+            .iter()
+            .map(|c| (c.clone(), Vec::with_capacity(rows.len())))
+            .collect();
+        for mut row in rows {
+            for (name, values) in data.iter_mut() {
+                let value = row
+                    .get_mut(name.as_str())
+                    .map(serde_json::Value::take)
+                    .unwrap_or_default();
+                values.push(value);
+            }
+        }
+        serde_json::Value::Object(
+            data.into_iter()
+                .map(|(name, values)| (name, serde_json::Value::Array(values)))
+                .collect(),
+        )
+    }
+
+    #[cfg(feature = "arrow")]
+    fn encode_arrow(output: &QueryOutput) -> anyhow::Result<serde_json::Value> {
+        let bytes = super::arrow::to_ipc(&output.columns, &output.rows)?;
+        Ok(serde_json::Value::String(
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+        ))
+    }
+
+    #[cfg(not(feature = "arrow"))]
+    fn encode_arrow(_: &QueryOutput) -> anyhow::Result<serde_json::Value> {
+        Err(anyhow::anyhow!(
+            "Arrow output is not available, the server was built without the `arrow` feature"
+        ))
     }
 }
 
@@ -72,19 +550,360 @@ impl Command for ExecuteCommand {
         // 记录开始时间
         let start_time = std::time::Instant::now();
 
+        let params = query_params
+            .params
+            .iter()
+            .map(BindValue::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if query_params.preview {
+            if query_params.session_id.is_some() {
+                return Err(anyhow::anyhow!("Preview can't run inside a session"));
+            }
+            if !Self::previewable(&query_params.query) {
+                return Err(anyhow::anyhow!(
+                    "Preview only supports a single INSERT, UPDATE or DELETE statement"
+                ));
+            }
+        }
+
+        let statements = match query_params.is_read {
+            // 客户端已经知道语句类型，不再解析
+            Some(_) => vec![query_params.query.clone()],
+            None => Self::split_statements(&query_params.query),
+        };
+        if statements.len() > 1 {
+            if !query_params.multi_statement {
+                return Err(anyhow::anyhow!(
+                    "Query contains {} statements, set `multi_statement` to run them in order",
+                    statements.len()
+                ));
+            }
+            if !params.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Parameters can't be bound to a query with multiple statements"
+                ));
+            }
+            let mut results = Vec::new();
+            let mut warnings = Vec::new();
+            for statement in statements {
+                match self.run(&statement, &[], &query_params).await {
+                    Ok((result, _, statement_warnings)) => {
+                        warnings.extend(statement_warnings);
+                        results.push(StatementResult {
+                            statement,
+                            result: Some(result),
+                            error: None,
+                        });
+                    }
+                    Err(e) => {
+                        results.push(StatementResult {
+                            statement,
+                            result: None,
+                            error: Some(e.to_string()),
+                        });
+                        break;
+                    }
+                }
+            }
+            let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+            let affected_objects = Self::ddl_objects(&query_params.query);
+            schema_changed(
+                &self.client,
+                &query_params.connection.connection_id,
+                &affected_objects,
+            )
+            .await;
+            return Ok(Some(
+                CommandResult::try_create(json!({ "results": results }), execution_time)?
+                    .with_warnings(warnings)
+                    .with_affected_objects(affected_objects),
+            ));
+        }
+
         // 执行SQL查询
-        let result = self
+        let (mut result, timing, mut warnings) = self
+            .run(&query_params.query, &params, &query_params)
+            .await?;
+        if query_params.count_total && result.kind == ResultKind::Rows {
+            match Self::count_rows(&params, &query_params).await {
+                Ok(Some(total)) => result.total_rows = Some(total),
+                Ok(None) => warnings.push("Total rows can't be counted for this query".to_string()),
+                Err(e) => warnings.push(format!("Failed to count the total rows: {}", e)),
+            }
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        let affected_objects = match query_params.is_read {
+            Some(true) => Vec::new(),
+            _ => Self::ddl_objects(&query_params.query),
+        };
+        schema_changed(
+            &self.client,
+            &query_params.connection.connection_id,
+            &affected_objects,
+        )
+        .await;
+
+        Ok(Some(
+            CommandResult::try_create(result, execution_time)?
+                .with_timing(timing)
+                .with_warnings(warnings)
+                .with_affected_objects(affected_objects),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RunStatementAtParams {
+    uri: Url,
+    position: Position,
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    #[serde(default)]
+    format: ResultFormat,
+    #[serde(default)]
+    layout: ResultLayout,
+}
+
+/// Runs the statement under the cursor, for keybindings.
+pub struct RunStatementAtCommand {
+    pub client: Arc<Client>,
+    pub document_map: DocumentMap,
+    pub document_connections: DocumentConnections,
+}
+
+#[tower_lsp::async_trait]
+impl Command for RunStatementAtCommand {
+    fn command(&self) -> &'static str {
+        SERVER_RUN_STATEMENT_AT
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<RunStatementAtParams>(&params)?;
+        if req.connection.connection_id.is_empty() {
+            // 未指定连接时使用文档关联的连接
+            if let Some(id) = self.document_connections.read().await.get(req.uri.as_str()) {
+                req.connection.connection_id = id.clone();
+            }
+        }
+        let query = {
+            let document_map = self.document_map.read().await;
+            let document = document_map
+                .get(req.uri.as_str())
+                .ok_or_else(|| anyhow::anyhow!("Document is not open: {}", req.uri))?;
+            document
+                .statement_at(req.position)
+                .map(|statement| statement.to_string())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No statement at line {}, column {}",
+                        req.position.line + 1,
+                        req.position.character + 1
+                    )
+                })?
+        };
+
+        log(MessageType::INFO, format!("Executing SQL query: {}", query));
+
+        let start_time = std::time::Instant::now();
+        let execute = ExecuteCommand {
+            client: Arc::clone(&self.client),
+        };
+        let tagged = tag_query(&query, Some(req.uri.as_str()), &req.connection).await;
+        let (result, timing, warnings) = execute
             .execute_sql_query(
-                &query_params.query,
-                &query_params.connection_id,
-                DBConnectionOptions {
-                    connection_string: query_params.connection_string,
+                &tagged,
+                SqlParser::new().result_kind(&query),
+                &[],
+                &req.connection,
+                ResultShape {
+                    format: req.format,
+                    layout: req.layout,
                 },
+                // 在编辑器里运行总是取最新结果
+                true,
             )
             .await?;
         let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        let affected_objects = ExecuteCommand::ddl_objects(&query);
+        schema_changed(
+            &self.client,
+            &req.connection.connection_id,
+            &affected_objects,
+        )
+        .await;
 
-        Ok(Some(CommandResult::try_create(result, execution_time)?))
+        Ok(Some(
+            CommandResult::try_create(result, execution_time)?
+                .with_timing(timing)
+                .with_warnings(warnings)
+                .with_affected_objects(affected_objects),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RunRangeParams {
+    uri: Url,
+    range: Range,
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    #[serde(default)]
+    format: ResultFormat,
+    #[serde(default)]
+    layout: ResultLayout,
+}
+
+/// Outcome of one statement run by [`RunRangeCommand`].
+#[derive(Debug, Serialize)]
+struct StatementResult {
+    statement: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<QueryResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Runs every statement touched by a selection, for "run selected SQL".
+/// Stops at the first failing statement.
+pub struct RunRangeCommand {
+    pub client: Arc<Client>,
+    pub document_map: DocumentMap,
+    pub document_connections: DocumentConnections,
+}
+
+#[tower_lsp::async_trait]
+impl Command for RunRangeCommand {
+    fn command(&self) -> &'static str {
+        SERVER_RUN_RANGE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<RunRangeParams>(&params)?;
+        if req.connection.connection_id.is_empty() {
+            // 未指定连接时使用文档关联的连接
+            if let Some(id) = self.document_connections.read().await.get(req.uri.as_str()) {
+                req.connection.connection_id = id.clone();
+            }
+        }
+        let statements: Vec<String> = {
+            let document_map = self.document_map.read().await;
+            let document = document_map
+                .get(req.uri.as_str())
+                .ok_or_else(|| anyhow::anyhow!("Document is not open: {}", req.uri))?;
+            document
+                .statements_in(req.range)
+                .into_iter()
+                .map(|statement| statement.to_string())
+                .collect()
+        };
+        if statements.is_empty() {
+            return Err(anyhow::anyhow!("No statement in the selection"));
+        }
+
+        let start_time = std::time::Instant::now();
+        let execute = ExecuteCommand {
+            client: Arc::clone(&self.client),
+        };
+        let mut results = Vec::new();
+        let mut warnings = Vec::new();
+        let mut affected_objects = Vec::new();
+        for statement in statements {
+            log(
+                MessageType::INFO,
+                format!("Executing SQL query: {}", statement),
+            );
+            let tagged = tag_query(&statement, Some(req.uri.as_str()), &req.connection).await;
+            let outcome = execute
+                .execute_sql_query(
+                    &tagged,
+                    SqlParser::new().result_kind(&statement),
+                    &[],
+                    &req.connection,
+                    ResultShape {
+                        format: req.format,
+                        layout: req.layout,
+                    },
+                    // 在编辑器里运行总是取最新结果
+                    true,
+                )
+                .await;
+            match outcome {
+                Ok((result, _, statement_warnings)) => {
+                    warnings.extend(statement_warnings);
+                    affected_objects.extend(ExecuteCommand::ddl_objects(&statement));
+                    results.push(StatementResult {
+                        statement,
+                        result: Some(result),
+                        error: None,
+                    });
+                }
+                Err(e) => {
+                    results.push(StatementResult {
+                        statement,
+                        result: None,
+                        error: Some(e.to_string()),
+                    });
+                    break;
+                }
+            }
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        schema_changed(
+            &self.client,
+            &req.connection.connection_id,
+            &affected_objects,
+        )
+        .await;
+
+        Ok(Some(
+            CommandResult::try_create(json!({ "results": results }), execution_time)?
+                .with_warnings(warnings)
+                .with_affected_objects(affected_objects),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecuteTransactionParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    statements: Vec<String>,
+    /// Skip failing statements instead of rolling everything back
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+/// Runs a batch of statements in a single transaction.
+pub struct ExecuteTransactionCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ExecuteTransactionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_EXECUTE_TRANSACTION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ExecuteTransactionParams>(&params)?;
+        log(
+            MessageType::INFO,
+            format!(
+                "Executing {} statements in a transaction",
+                req.statements.len()
+            ),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let output = pool
+            .execute_transaction(&req.statements, req.continue_on_error)
+            .await?;
+        if output.committed {
+            cache::clear(Some(&req.connection.connection_id));
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(output, execution_time)?))
     }
 }
 
@@ -96,6 +915,10 @@ struct CheckConnectionParams {
     connection_id: String,
     #[serde(default)]
     connection_string: String,
+    #[serde(default)]
+    db_type_hint: Option<DatabaseType>,
+    #[serde(default)]
+    key: Option<String>,
 }
 
 #[tower_lsp::async_trait]
@@ -110,6 +933,9 @@ impl Command for CheckConnectionCommand {
             &req.connection_id,
             DBConnectionOptions {
                 connection_string: req.connection_string,
+                db_type_hint: req.db_type_hint,
+                replica_connection_string: None,
+                key: req.key,
             },
         )
         .await;
@@ -123,3 +949,278 @@ impl Command for CheckConnectionCommand {
         )?))
     }
 }
+
+#[derive(Debug, Deserialize)]
+struct ValidateConnectionParams {
+    connection_string: String,
+}
+
+/// Checks that a connection string is well formed without connecting, for
+/// feedback while it is typed.
+pub struct ValidateConnectionCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ValidateConnectionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_VALIDATE_CONNECTION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ValidateConnectionParams>(&params)?;
+        let result = match validate_connection_string(&req.connection_string) {
+            Ok(db_type) => json!({ "valid": true, "db_type": db_type }),
+            Err(error) => json!({ "valid": false, "error": error }),
+        };
+        Ok(Some(CommandResult::try_create(result, 0.0)?))
+    }
+}
+
+fn default_check_timeout_ms() -> u64 {
+    5000
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckConnectionsParams {
+    connections: Vec<ConnectionEntry>,
+    /// Limit for each check, a dead host only fails its own entry
+    #[serde(default = "default_check_timeout_ms")]
+    timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectionEntry {
+    id: String,
+    #[serde(default)]
+    connection_string: String,
+    #[serde(default)]
+    db_type_hint: Option<DatabaseType>,
+    #[serde(default)]
+    key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConnectionHealth {
+    healthy: bool,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Checks several connections concurrently, for the connection manager.
+pub struct CheckConnectionsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for CheckConnectionsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_CHECK_CONNECTIONS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<CheckConnectionsParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let timeout = std::time::Duration::from_millis(req.timeout_ms);
+        let checks = req.connections.into_iter().map(|entry| async move {
+            let ConnectionEntry {
+                id,
+                connection_string,
+                db_type_hint,
+                key,
+            } = entry;
+            let started = std::time::Instant::now();
+            let check = async {
+                let connect = crate::db::from_cache(
+                    &id,
+                    DBConnectionOptions {
+                        connection_string,
+                        db_type_hint,
+                        replica_connection_string: None,
+                        key,
+                    },
+                )
+                .await;
+                let pool = connect
+                    .get_pool()
+                    .await
+                    .ok_or_else(|| anyhow::anyhow!("Failed to get pool from connection"))?;
+                pool.check_connection().await
+            };
+            let result = match tokio::time::timeout(timeout, check).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!(
+                    "Timed out after {} ms",
+                    timeout.as_millis()
+                )),
+            };
+            let health = ConnectionHealth {
+                healthy: matches!(result, Ok(true)),
+                latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+                error: result.err().map(|e| e.to_string()),
+            };
+            (id, health)
+        });
+        let results: HashMap<String, ConnectionHealth> = futures::future::join_all(checks)
+            .await
+            .into_iter()
+            .collect();
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        Ok(Some(CommandResult::try_create(results, execution_time)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BeginSessionParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+/// Opens a transaction on a dedicated connection. Queries sent to
+/// [`ExecuteCommand`] with the returned `session_id` run inside it until
+/// [`EndSessionCommand`] commits or rolls it back.
+pub struct BeginSessionCommand;
+
+#[tower_lsp::async_trait]
+impl Command for BeginSessionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_BEGIN_SESSION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<BeginSessionParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let session_id =
+            session::register(&req.connection.connection_id, pool.begin_session().await?);
+        log(
+            MessageType::INFO,
+            format!(
+                "Started session {} on {}",
+                session_id, req.connection.connection_id
+            ),
+        );
+        Ok(Some(CommandResult::try_create(
+            json!({ "session_id": session_id }),
+            0.0,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EndSessionParams {
+    session_id: String,
+    /// Roll back when false
+    #[serde(default)]
+    commit: bool,
+}
+
+/// Commits or rolls back a session opened by [`BeginSessionCommand`].
+pub struct EndSessionCommand;
+
+#[tower_lsp::async_trait]
+impl Command for EndSessionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_END_SESSION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<EndSessionParams>(&params)?;
+        session::end(&req.session_id, req.commit).await?;
+        log(
+            MessageType::INFO,
+            format!(
+                "Session {} {}",
+                req.session_id,
+                if req.commit {
+                    "committed"
+                } else {
+                    "rolled back"
+                }
+            ),
+        );
+        Ok(Some(CommandResult::try_create(
+            json!({ "session_id": req.session_id, "committed": req.commit }),
+            0.0,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearResultCacheParams {
+    /// Only clear this connection's results, all when missing
+    #[serde(default)]
+    connection_id: Option<String>,
+}
+
+/// Drops cached query results, see the `result_cache` setting.
+pub struct ClearResultCacheCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ClearResultCacheCommand {
+    fn command(&self) -> &'static str {
+        SERVER_CLEAR_RESULT_CACHE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ClearResultCacheParams>(&params)?;
+        let cleared = cache::clear(req.connection_id.as_deref());
+        Ok(Some(CommandResult::try_create(
+            json!({ "cleared": cleared }),
+            0.0,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_previewable() {
+        assert!(ExecuteCommand::previewable(
+            "DELETE FROM orders WHERE created_at < '2020-01-01'"
+        ));
+        assert!(ExecuteCommand::previewable(
+            "UPDATE users SET active = false RETURNING id"
+        ));
+        assert!(!ExecuteCommand::previewable("DROP TABLE orders"));
+        assert!(!ExecuteCommand::previewable("DELETE FROM a; DELETE FROM b"));
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            ExecuteCommand::split_statements("SELECT 1; SELECT 2"),
+            vec!["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(
+            ExecuteCommand::split_statements("SELECT 1;"),
+            vec!["SELECT 1;"]
+        );
+        // 保留原文，无法解析的语句由数据库报告错误
+        assert_eq!(
+            ExecuteCommand::split_statements("select \"Id\" from t where x = 'a;b';\nSELEC 2"),
+            vec!["select \"Id\" from t where x = 'a;b'", "SELEC 2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_rows() {
+        let req: ExecuteQueryParams = serde_json::from_value(json!({
+            "query": "SELECT name FROM items WHERE id > ? ORDER BY name LIMIT 1",
+            "connection_id": "test_count_rows",
+            "connection_string": "sqlite::memory:",
+        }))
+        .unwrap();
+        let pool = req.connection.pool().await.unwrap();
+        for sql in [
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+            "INSERT INTO items (name) VALUES ('a'), ('b'), ('c')",
+        ] {
+            pool.execute_query(sql, &[], ResultKind::Affected)
+                .await
+                .unwrap();
+        }
+        let total = ExecuteCommand::count_rows(&[BindValue::Int(1)], &req)
+            .await
+            .unwrap();
+        assert_eq!(total, Some(2));
+    }
+}
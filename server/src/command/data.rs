@@ -0,0 +1,1382 @@
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::json;
+use tower_lsp::lsp_types::ExecuteCommandParams;
+
+use crate::{
+    constant::{
+        SERVER_COLUMN_PROFILE, SERVER_FETCH_BLOB, SERVER_FETCH_CELL, SERVER_GET_RECENT_ROWS,
+        SERVER_GET_RELATED_ROWS, SERVER_GET_ROWS_BY_KEYS, SERVER_PIVOT, SERVER_UPDATE_PREVIEW,
+    },
+    db::{
+        DatabaseType, blob,
+        connection::{BindValue, ColumnInfo, ConnectionPool, ForeignKey, ParamType, QueryParam},
+        dialect::Dialect,
+    },
+    parser::ResultKind,
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments, resolve_column};
+
+/// Placeholder for a primary key value. PostgreSQL binds JSON strings as
+/// `text`, which doesn't compare with `uuid`, `date` or numeric keys, so the
+/// placeholder is cast to the column's type there.
+fn key_placeholder(db_type: &DatabaseType, index: usize, column: Option<&ColumnInfo>) -> String {
+    let placeholder = db_type.placeholder(index);
+    match column {
+        Some(column)
+            if *db_type == DatabaseType::PostgreSQL
+                && !matches!(column.data_type.as_str(), "USER-DEFINED" | "ARRAY") =>
+        {
+            format!("{}::{}", placeholder, column.data_type)
+        }
+        _ => placeholder,
+    }
+}
+
+/// Declared types of the primary key columns, only needed for PostgreSQL.
+async fn key_columns(
+    pool: &ConnectionPool,
+    table: &str,
+    primary_keys: &[String],
+) -> anyhow::Result<Vec<Option<ColumnInfo>>> {
+    let mut columns = if pool.database_type() == DatabaseType::PostgreSQL {
+        pool.get_column_info(table).await?
+    } else {
+        Vec::new()
+    };
+    Ok(primary_keys
+        .iter()
+        .map(|key| {
+            columns
+                .iter()
+                .position(|c| &c.name == key)
+                .map(|i| columns.swap_remove(i))
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct GetRowsByKeysParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Primary key values, one array per row in key column order. A bare
+    /// value is accepted for single column keys.
+    keys: Vec<serde_json::Value>,
+}
+
+/// Reads the rows matching a set of primary key values.
+pub struct GetRowsByKeysCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetRowsByKeysCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_ROWS_BY_KEYS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<GetRowsByKeysParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let primary_keys = pool.get_primary_keys(&req.table).await?;
+        if primary_keys.is_empty() {
+            return Err(anyhow::anyhow!("Table has no primary key: {}", req.table));
+        }
+
+        let mut values = Vec::new();
+        for key in req.keys {
+            let tuple = match key {
+                serde_json::Value::Array(tuple) => tuple,
+                value => vec![value],
+            };
+            if tuple.len() != primary_keys.len() {
+                return Err(anyhow::anyhow!(
+                    "Expected {} key values per row, got {}",
+                    primary_keys.len(),
+                    tuple.len()
+                ));
+            }
+            values.push(tuple);
+        }
+        if values.is_empty() {
+            return Ok(Some(CommandResult::try_create(
+                json!({
+                    "columns": [],
+                    "rows": [],
+                }),
+                0.0,
+            )?));
+        }
+
+        let dialect = pool.native_dialect();
+        let db_type = pool.database_type();
+        let key_columns = key_columns(&pool, &req.table, &primary_keys).await?;
+        let mut binds = Vec::new();
+        let mut tuples = Vec::new();
+        for tuple in values {
+            let mut placeholders = Vec::new();
+            for (value, column) in tuple.into_iter().zip(&key_columns) {
+                binds.push(BindValue::try_from(&QueryParam::Plain(value))?);
+                placeholders.push(key_placeholder(&db_type, binds.len(), column.as_ref()));
+            }
+            tuples.push(placeholders);
+        }
+        let sql = if primary_keys.len() == 1 {
+            format!(
+                "SELECT * FROM {} WHERE {} IN ({})",
+                dialect.quote_ident(&req.table),
+                dialect.quote_ident(&primary_keys[0]),
+                tuples.concat().join(", ")
+            )
+        } else {
+            format!(
+                "SELECT * FROM {} WHERE ({}) IN ({})",
+                dialect.quote_ident(&req.table),
+                primary_keys
+                    .iter()
+                    .map(|k| dialect.quote_ident(k))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                tuples
+                    .iter()
+                    .map(|t| format!("({})", t.join(", ")))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+
+        let start_time = std::time::Instant::now();
+        let output = pool.execute_query(&sql, &binds, ResultKind::Rows).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "columns": output.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                "rows": output.rows,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FetchCellParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Primary key values of the row in key column order, a bare value for
+    /// single column keys
+    key: serde_json::Value,
+    column: String,
+}
+
+/// Reads a single cell by primary key, for loading the full value of a cell
+/// the grid shows shortened. Large binary values still come back as a
+/// `blob_ref`.
+pub struct FetchCellCommand;
+
+#[tower_lsp::async_trait]
+impl Command for FetchCellCommand {
+    fn command(&self) -> &'static str {
+        SERVER_FETCH_CELL
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<FetchCellParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let columns = pool.get_columns(&req.table).await?;
+        req.column = resolve_column(&pool.database_type(), &req.table, &req.column, &columns)?;
+        let primary_keys = pool.get_primary_keys(&req.table).await?;
+        if primary_keys.is_empty() {
+            return Err(anyhow::anyhow!("Table has no primary key: {}", req.table));
+        }
+        let key = match req.key {
+            serde_json::Value::Array(key) => key,
+            value => vec![value],
+        };
+        if key.len() != primary_keys.len() {
+            return Err(anyhow::anyhow!(
+                "Expected {} key values, got {}",
+                primary_keys.len(),
+                key.len()
+            ));
+        }
+
+        let dialect = pool.native_dialect();
+        let db_type = pool.database_type();
+        let key_columns = key_columns(&pool, &req.table, &primary_keys).await?;
+        let mut binds = Vec::new();
+        let mut conditions = Vec::new();
+        for ((name, value), column) in primary_keys.iter().zip(key).zip(&key_columns) {
+            binds.push(BindValue::try_from(&QueryParam::Plain(value))?);
+            conditions.push(format!(
+                "{} = {}",
+                dialect.quote_ident(name),
+                key_placeholder(&db_type, binds.len(), column.as_ref())
+            ));
+        }
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}",
+            dialect.quote_ident(&req.column),
+            dialect.quote_ident(&req.table),
+            conditions.join(" AND ")
+        );
+
+        let start_time = std::time::Instant::now();
+        let output = pool.execute_query(&sql, &binds, ResultKind::Rows).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        let value = output
+            .rows
+            .get(0)
+            .and_then(|row| row.get(&req.column))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Row not found in {}", req.table))?;
+
+        Ok(Some(CommandResult::try_create(
+            json!({ "value": value }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FetchBlobParams {
+    blob_ref: String,
+    /// Write the bytes to this file instead of returning them
+    #[serde(default)]
+    path: Option<String>,
+}
+
+/// Returns a binary value left out of a query result, see `blob_threshold`.
+pub struct FetchBlobCommand;
+
+#[tower_lsp::async_trait]
+impl Command for FetchBlobCommand {
+    fn command(&self) -> &'static str {
+        SERVER_FETCH_BLOB
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<FetchBlobParams>(&params)?;
+        let bytes = blob::get(&req.blob_ref).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown or expired blob {}, run the query again",
+                req.blob_ref
+            )
+        })?;
+
+        let data = match &req.path {
+            Some(path) => {
+                tokio::fs::write(path, &bytes).await?;
+                json!({ "path": path, "size": bytes.len() })
+            }
+            None => json!({
+                "data": base64::engine::general_purpose::STANDARD.encode(&bytes),
+                "size": bytes.len(),
+            }),
+        };
+        Ok(Some(CommandResult::try_create(data, 0.0)?))
+    }
+}
+
+fn default_recent_limit() -> i64 {
+    100
+}
+
+#[derive(Debug, Deserialize)]
+struct GetRecentRowsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Date or timestamp column the rows are filtered and ordered by
+    column: String,
+    /// Earliest value to include. A plain string is parsed according to
+    /// the column's type.
+    since: QueryParam,
+    #[serde(default = "default_recent_limit")]
+    limit: i64,
+}
+
+/// Returns the most recently changed rows of a table, newest first, for
+/// audit and log tables.
+pub struct GetRecentRowsCommand;
+
+impl GetRecentRowsCommand {
+    /// Parameter type for comparing against a column, None if the column
+    /// doesn't hold dates or timestamps.
+    fn temporal_type(data_type: &str) -> Option<ParamType> {
+        let data_type = data_type.to_lowercase();
+        if data_type.contains("with time zone") || data_type.starts_with("timestamptz") {
+            Some(ParamType::Timestamptz)
+        } else if data_type.starts_with("timestamp") || data_type.starts_with("datetime") {
+            Some(ParamType::Timestamp)
+        } else if data_type == "date" {
+            Some(ParamType::Date)
+        } else {
+            None
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for GetRecentRowsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_RECENT_ROWS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<GetRecentRowsParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let columns = pool.get_column_info(&req.table).await?;
+        let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+        req.column = resolve_column(&pool.database_type(), &req.table, &req.column, &names)?;
+        let column = columns
+            .iter()
+            .find(|c| c.name == req.column)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unknown column {} in table {}", req.column, req.table)
+            })?;
+        let param_type = Self::temporal_type(&column.data_type).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Column {} is not a date or timestamp column ({})",
+                column.name,
+                column.data_type
+            )
+        })?;
+        let since = match req.since {
+            QueryParam::Plain(serde_json::Value::String(value)) => QueryParam::Typed {
+                value: serde_json::Value::String(value),
+                param_type,
+            },
+            other => other,
+        };
+
+        let dialect = pool.native_dialect();
+        let db_type = pool.database_type();
+        let column = dialect.quote_ident(&req.column);
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} >= {} ORDER BY {} DESC LIMIT {}",
+            dialect.quote_ident(&req.table),
+            column,
+            db_type.placeholder(1),
+            column,
+            db_type.placeholder(2)
+        );
+        let binds = vec![BindValue::try_from(&since)?, BindValue::Int(req.limit)];
+
+        let start_time = std::time::Instant::now();
+        let output = pool.execute_query(&sql, &binds, ResultKind::Rows).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "columns": output.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                "rows": output.rows,
+                "total": output.total,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+/// Aggregate computed for each cell of a pivot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PivotAggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Deserialize)]
+struct PivotParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Column whose values become the rows
+    group_by: String,
+    /// Column whose distinct values become the result columns
+    pivot: String,
+    aggregate: PivotAggregate,
+    /// Column aggregated in each cell, only optional for `count`
+    #[serde(default)]
+    value: Option<String>,
+}
+
+/// Most distinct pivot values turned into columns.
+const MAX_PIVOT_COLUMNS: usize = 100;
+
+/// Pivots a table: one row per `group_by` value and one column per distinct
+/// `pivot` value, each cell aggregating `value`. Built from `CASE WHEN`
+/// aggregates since only PostgreSQL has `crosstab`, and only as an
+/// extension. NULL pivot values are left out.
+pub struct PivotCommand;
+
+impl PivotCommand {
+    /// A column cast to text, so values of any type compare equal to the
+    /// text they were read back as.
+    fn as_text(column: &str, db_type: &DatabaseType) -> String {
+        match db_type {
+            DatabaseType::MySQL => format!("CAST({} AS CHAR)", column),
+            DatabaseType::PostgreSQL | DatabaseType::SQLite => format!("CAST({} AS TEXT)", column),
+        }
+    }
+
+    /// The pivot query, binding the pivot values in order.
+    fn pivot_sql(
+        req: &PivotParams,
+        pivot_values: &[String],
+        db_type: &DatabaseType,
+        dialect: Dialect,
+    ) -> String {
+        let group = dialect.quote_ident(&req.group_by);
+        let pivot = Self::as_text(&dialect.quote_ident(&req.pivot), db_type);
+        let value = match &req.value {
+            Some(value) => dialect.quote_ident(value),
+            None => "1".to_string(),
+        };
+        let function = match req.aggregate {
+            PivotAggregate::Count => "COUNT",
+            PivotAggregate::Sum => "SUM",
+            PivotAggregate::Avg => "AVG",
+            PivotAggregate::Min => "MIN",
+            PivotAggregate::Max => "MAX",
+        };
+        let mut select = vec![group.clone()];
+        for (i, pivot_value) in pivot_values.iter().enumerate() {
+            select.push(format!(
+                "{}(CASE WHEN {} = {} THEN {} END) AS {}",
+                function,
+                pivot,
+                db_type.placeholder(i + 1),
+                value,
+                dialect.quote_ident(pivot_value)
+            ));
+        }
+        format!(
+            "SELECT {} FROM {} GROUP BY {} ORDER BY {}",
+            select.join(", "),
+            dialect.quote_ident(&req.table),
+            group,
+            group
+        )
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for PivotCommand {
+    fn command(&self) -> &'static str {
+        SERVER_PIVOT
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<PivotParams>(&params)?;
+        if req.value.is_none() && req.aggregate != PivotAggregate::Count {
+            return Err(anyhow::anyhow!(
+                "A value column is needed for {:?}",
+                req.aggregate
+            ));
+        }
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let columns = pool.get_columns(&req.table).await?;
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("Unknown table: {}", req.table));
+        }
+        let db_type = pool.database_type();
+        req.group_by = resolve_column(&db_type, &req.table, &req.group_by, &columns)?;
+        req.pivot = resolve_column(&db_type, &req.table, &req.pivot, &columns)?;
+        req.value = req
+            .value
+            .as_deref()
+            .map(|value| resolve_column(&db_type, &req.table, value, &columns))
+            .transpose()?;
+
+        let start_time = std::time::Instant::now();
+        let dialect = pool.native_dialect();
+        let pivot = dialect.quote_ident(&req.pivot);
+        let sql = format!(
+            "SELECT DISTINCT {} AS pivot_value FROM {} WHERE {} IS NOT NULL ORDER BY 1 LIMIT {}",
+            Self::as_text(&pivot, &db_type),
+            dialect.quote_ident(&req.table),
+            pivot,
+            MAX_PIVOT_COLUMNS + 1
+        );
+        let output = pool.execute_query(&sql, &[], ResultKind::Rows).await?;
+        let pivot_values: Vec<String> = output
+            .rows
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|row| row["pivot_value"].as_str().map(str::to_string))
+            .collect();
+        if pivot_values.len() > MAX_PIVOT_COLUMNS {
+            return Err(anyhow::anyhow!(
+                "Column {} has more than {} distinct values to pivot on",
+                req.pivot,
+                MAX_PIVOT_COLUMNS
+            ));
+        }
+
+        let sql = Self::pivot_sql(&req, &pivot_values, &db_type, dialect);
+        let binds: Vec<BindValue> = pivot_values
+            .iter()
+            .map(|value| BindValue::Text(value.clone()))
+            .collect();
+        let output = pool.execute_query(&sql, &binds, ResultKind::Rows).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "columns": output.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                "rows": output.rows,
+                "pivot_values": pivot_values,
+                "total": output.total,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+fn default_profile_buckets() -> usize {
+    10
+}
+
+fn default_profile_sample() -> Option<u64> {
+    Some(100_000)
+}
+
+#[derive(Debug, Deserialize)]
+struct ColumnProfileParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    column: String,
+    /// Histogram buckets for numeric columns
+    #[serde(default = "default_profile_buckets")]
+    buckets: usize,
+    /// Only profile this many rows of the table, null for all of them
+    #[serde(default = "default_profile_sample")]
+    sample_rows: Option<u64>,
+}
+
+/// Most histogram buckets of a profile.
+const MAX_PROFILE_BUCKETS: usize = 100;
+
+/// Profiles a column for a data-quality panel: row, null and distinct
+/// counts, the smallest and largest value, and a histogram for numeric
+/// columns. Large tables are profiled on a sample of their first rows.
+pub struct ColumnProfileCommand;
+
+impl ColumnProfileCommand {
+    fn is_numeric(data_type: &str) -> bool {
+        let data_type = data_type.to_lowercase();
+        !data_type.starts_with("interval")
+            && [
+                "int",
+                "tinyint",
+                "smallint",
+                "mediumint",
+                "bigint",
+                "serial",
+                "smallserial",
+                "bigserial",
+                "numeric",
+                "decimal",
+                "real",
+                "double",
+                "float",
+            ]
+            .iter()
+            .any(|prefix| data_type.starts_with(prefix))
+    }
+
+    /// Zero based bucket of `column` for `buckets` equal ranges from `min` to
+    /// `max`, the maximum falling into the last bucket.
+    fn bucket_expr(
+        column: &str,
+        min: f64,
+        max: f64,
+        buckets: usize,
+        db_type: &DatabaseType,
+    ) -> String {
+        let scaled = format!("({} - {}) * {} / ({})", column, min, buckets, max - min);
+        let floor = match db_type {
+            // SQLite 的 FLOOR 需要编译时启用数学函数，值非负时截断即向下取整
+            DatabaseType::SQLite => format!("CAST({} AS INTEGER)", scaled),
+            DatabaseType::MySQL | DatabaseType::PostgreSQL => format!("FLOOR({})", scaled),
+        };
+        format!(
+            "CASE WHEN {} >= {} THEN {} ELSE {} END",
+            column,
+            max,
+            buckets - 1,
+            floor
+        )
+    }
+
+    /// A number from a result value, which may come back as a string for
+    /// decimals.
+    fn number(value: &serde_json::Value) -> Option<f64> {
+        match value {
+            serde_json::Value::String(s) => s.parse().ok(),
+            other => other.as_f64(),
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for ColumnProfileCommand {
+    fn command(&self) -> &'static str {
+        SERVER_COLUMN_PROFILE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<ColumnProfileParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let columns = pool.get_column_info(&req.table).await?;
+        let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+        req.column = resolve_column(&pool.database_type(), &req.table, &req.column, &names)?;
+        let data_type = columns
+            .iter()
+            .find(|c| c.name == req.column)
+            .map(|c| c.data_type.clone())
+            .unwrap_or_default();
+
+        let start_time = std::time::Instant::now();
+        let dialect = pool.native_dialect();
+        let db_type = pool.database_type();
+        let column = dialect.quote_ident(&req.column);
+        let source = match req.sample_rows {
+            Some(limit) => format!(
+                "(SELECT {} FROM {} LIMIT {}) AS sampled",
+                column,
+                dialect.quote_ident(&req.table),
+                limit
+            ),
+            None => dialect.quote_ident(&req.table),
+        };
+        let numeric = Self::is_numeric(&data_type);
+        let temporal = GetRecentRowsCommand::temporal_type(&data_type).is_some();
+        // 其他类型不一定支持 MIN/MAX 和 DISTINCT（如 json），按文本比较
+        let value = if numeric || temporal {
+            column.clone()
+        } else {
+            PivotCommand::as_text(&column, &db_type)
+        };
+        // 行转换只解码文本，数字结果都转成文本再用 number 解析
+        let text = |expr: String| PivotCommand::as_text(&expr, &db_type);
+        let extreme = |function: &str| {
+            let expr = format!("{}({})", function, value);
+            if temporal { expr } else { text(expr) }
+        };
+        let sql = format!(
+            "SELECT {} AS row_count, {} AS non_null_count, {} AS distinct_count, \
+             {} AS min_value, {} AS max_value FROM {source}",
+            text("COUNT(*)".to_string()),
+            text(format!("COUNT({})", column)),
+            text(format!("COUNT(DISTINCT {})", value)),
+            extreme("MIN"),
+            extreme("MAX"),
+        );
+        let output = pool.execute_query(&sql, &[], ResultKind::Rows).await?;
+        let stats = output.rows.get(0).cloned().unwrap_or_default();
+        let stat = |key: &str| stats.get(key).cloned().unwrap_or_default();
+        let count = |key: &str| Self::number(&stat(key)).unwrap_or_default() as u64;
+        let row_count = count("row_count");
+
+        let mut histogram = Vec::new();
+        let min = Self::number(&stat("min_value"));
+        let max = Self::number(&stat("max_value"));
+        if numeric && let (Some(min), Some(max)) = (min, max) {
+            let buckets = req.buckets.clamp(1, MAX_PROFILE_BUCKETS);
+            let counts = if min < max {
+                let sql = format!(
+                    "SELECT {} AS bucket, {} AS bucket_count FROM {} \
+                     WHERE {} IS NOT NULL GROUP BY 1",
+                    text(Self::bucket_expr(&column, min, max, buckets, &db_type)),
+                    text("COUNT(*)".to_string()),
+                    source,
+                    column
+                );
+                let output = pool.execute_query(&sql, &[], ResultKind::Rows).await?;
+                let mut counts = vec![0; buckets];
+                for row in output.rows.as_array().into_iter().flatten() {
+                    let bucket = row.get("bucket").and_then(Self::number);
+                    let count = row.get("bucket_count").and_then(Self::number);
+                    if let (Some(bucket), Some(count)) = (bucket, count)
+                        && let Some(slot) = counts.get_mut(bucket as usize)
+                    {
+                        *slot = count as u64;
+                    }
+                }
+                counts
+            } else {
+                // 所有值相同时只有一个桶
+                vec![count("non_null_count")]
+            };
+            let width = (max - min) / counts.len() as f64;
+            for (i, count) in counts.into_iter().enumerate() {
+                histogram.push(json!({
+                    "lower": min + width * i as f64,
+                    "upper": min + width * (i + 1) as f64,
+                    "count": count,
+                }));
+            }
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "column": req.column,
+                "data_type": data_type,
+                "row_count": row_count,
+                "null_count": row_count.saturating_sub(count("non_null_count")),
+                "distinct_count": count("distinct_count"),
+                "min": stat("min_value"),
+                "max": stat("max_value"),
+                "histogram": histogram,
+                "sampled": req.sample_rows.is_some_and(|limit| row_count >= limit),
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+fn default_related_rows() -> usize {
+    100
+}
+
+/// Which side of a foreign key [`GetRelatedRowsCommand`] follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RelatedDirection {
+    /// The row the source row's foreign key points to
+    Parent,
+    /// Rows of the target table whose foreign key points to the source row
+    Children,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetRelatedRowsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Primary key values of the source row in key column order, a bare
+    /// value for single column keys
+    key: serde_json::Value,
+    /// Table on the other side of the foreign key
+    target_table: String,
+    direction: RelatedDirection,
+    /// Foreign key constraint to follow when the tables are linked by more
+    /// than one
+    #[serde(default)]
+    constraint: Option<String>,
+    /// Column of the foreign key to follow, for picking one of several
+    /// unnamed SQLite keys
+    #[serde(default)]
+    column: Option<String>,
+    #[serde(default = "default_related_rows")]
+    max_rows: usize,
+}
+
+/// Follows a foreign key from one row to the rows it references or that
+/// reference it, for drilling through related records in the grid.
+pub struct GetRelatedRowsCommand;
+
+impl GetRelatedRowsCommand {
+    /// Rows of `target` linked to the source row with the given primary
+    /// key. `pairs` holds the (source column, target column) pairs of the
+    /// foreign key, `keys` the primary key columns with their declared type
+    /// on PostgreSQL.
+    fn related_sql(
+        dialect: Dialect,
+        db_type: &DatabaseType,
+        source: &str,
+        target: &str,
+        pairs: &[(String, String)],
+        keys: &[(String, Option<ColumnInfo>)],
+        limit: usize,
+    ) -> String {
+        let joins = pairs.iter().map(|(source_column, target_column)| {
+            format!(
+                "s.{} = t.{}",
+                dialect.quote_ident(source_column),
+                dialect.quote_ident(target_column)
+            )
+        });
+        let keys = keys.iter().enumerate().map(|(i, (key, column))| {
+            format!(
+                "s.{} = {}",
+                dialect.quote_ident(key),
+                key_placeholder(db_type, i + 1, column.as_ref())
+            )
+        });
+        format!(
+            "SELECT t.* FROM {} t WHERE EXISTS (SELECT 1 FROM {} s WHERE {}) LIMIT {}",
+            dialect.quote_ident(target),
+            dialect.quote_ident(source),
+            joins.chain(keys).collect::<Vec<_>>().join(" AND "),
+            limit
+        )
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for GetRelatedRowsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_RELATED_ROWS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<GetRelatedRowsParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        req.target_table = req
+            .connection
+            .resolve_table(&pool, &req.target_table)
+            .await?;
+        let primary_keys = pool.get_primary_keys(&req.table).await?;
+        if primary_keys.is_empty() {
+            return Err(anyhow::anyhow!("Table has no primary key: {}", req.table));
+        }
+        let key = match req.key {
+            serde_json::Value::Array(key) => key,
+            value => vec![value],
+        };
+        if key.len() != primary_keys.len() {
+            return Err(anyhow::anyhow!(
+                "Expected {} key values, got {}",
+                primary_keys.len(),
+                key.len()
+            ));
+        }
+
+        // 外键定义在子表上
+        let (child, parent) = match req.direction {
+            RelatedDirection::Parent => (&req.table, &req.target_table),
+            RelatedDirection::Children => (&req.target_table, &req.table),
+        };
+        // SQLite 的外键没有名字，按 foreign_key_list 的 id 区分
+        let mut groups: Vec<Vec<ForeignKey>> = Vec::new();
+        for fk in pool.get_foreign_keys(child).await? {
+            if !fk.referenced_table.eq_ignore_ascii_case(parent) {
+                continue;
+            }
+            match groups
+                .iter_mut()
+                .find(|group| group[0].name == fk.name && group[0].id == fk.id)
+            {
+                Some(group) => group.push(fk),
+                None => groups.push(vec![fk]),
+            }
+        }
+        groups.retain(|group| {
+            req.constraint
+                .as_ref()
+                .is_none_or(|constraint| group[0].name.as_ref() == Some(constraint))
+                && req.column.as_ref().is_none_or(|column| {
+                    group
+                        .iter()
+                        .any(|fk| fk.column.eq_ignore_ascii_case(column))
+                })
+        });
+        let foreign_keys = match groups.len() {
+            0 => {
+                return Err(anyhow::anyhow!(
+                    "No foreign key from {} references {}",
+                    child,
+                    parent
+                ));
+            }
+            1 => groups.remove(0),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "{} references {} through several foreign keys, pick one with `constraint` \
+                     or `column`: {}",
+                    child,
+                    parent,
+                    groups
+                        .iter()
+                        .map(|group| {
+                            let columns = group
+                                .iter()
+                                .map(|fk| fk.column.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            match &group[0].name {
+                                Some(name) => format!("{} ({})", name, columns),
+                                None => format!("({})", columns),
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        };
+        let pairs: Vec<(String, String)> = foreign_keys
+            .iter()
+            .map(|fk| match req.direction {
+                RelatedDirection::Parent => (fk.column.clone(), fk.referenced_column.clone()),
+                RelatedDirection::Children => (fk.referenced_column.clone(), fk.column.clone()),
+            })
+            .collect();
+
+        let binds = key
+            .into_iter()
+            .map(|value| BindValue::try_from(&QueryParam::Plain(value)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let key_columns = key_columns(&pool, &req.table, &primary_keys).await?;
+        let keys: Vec<_> = primary_keys.into_iter().zip(key_columns).collect();
+        let sql = Self::related_sql(
+            pool.native_dialect(),
+            &pool.database_type(),
+            &req.table,
+            &req.target_table,
+            &pairs,
+            &keys,
+            req.max_rows + 1,
+        );
+        let output = pool.execute_query(&sql, &binds, ResultKind::Rows).await?;
+        let mut rows = match output.rows {
+            serde_json::Value::Array(rows) => rows,
+            _ => Vec::new(),
+        };
+        let truncated = rows.len() > req.max_rows;
+        rows.truncate(req.max_rows);
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "table": req.target_table,
+                "columns": output.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                "rows": rows,
+                "truncated": truncated,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+fn default_preview_rows() -> usize {
+    100
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdatePreviewParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// New values by column name
+    set: serde_json::Map<String, serde_json::Value>,
+    /// Primary key values of a single row in key column order, a bare value
+    /// for single column keys
+    #[serde(default)]
+    key: Option<serde_json::Value>,
+    /// Condition selecting the rows when no `key` is given, without the
+    /// `WHERE`
+    #[serde(default)]
+    filter: Option<String>,
+    /// Rows diffed at most, the update itself still covers every match
+    #[serde(default = "default_preview_rows")]
+    max_rows: usize,
+}
+
+/// Shows what an UPDATE would change before it is committed: the matching
+/// rows with their current and new value of every column that changes.
+/// The update runs in a transaction that is always rolled back, so
+/// constraint errors surface without anything being written. The rows are
+/// read again after the update, so casts, defaults and triggers show up in
+/// the new values.
+pub struct UpdatePreviewCommand;
+
+impl UpdatePreviewCommand {
+    /// `column = placeholder` terms of a primary key, numbered from `start`.
+    fn key_condition(
+        primary_keys: &[String],
+        key_columns: &[Option<ColumnInfo>],
+        dialect: Dialect,
+        db_type: &DatabaseType,
+        start: usize,
+    ) -> String {
+        primary_keys
+            .iter()
+            .zip(key_columns)
+            .enumerate()
+            .map(|(i, (name, column))| {
+                format!(
+                    "{} = {}",
+                    dialect.quote_ident(name),
+                    key_placeholder(db_type, start + i, column.as_ref())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Columns whose value differs between a row before and after the
+    /// update, with both values.
+    fn row_changes(
+        before: &serde_json::Map<String, serde_json::Value>,
+        after: &serde_json::Map<String, serde_json::Value>,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        after
+            .iter()
+            .filter_map(|(column, after)| {
+                let before = before.get(column).cloned().unwrap_or_default();
+                (before != *after)
+                    .then(|| (column.clone(), json!({ "before": before, "after": after })))
+            })
+            .collect()
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for UpdatePreviewCommand {
+    fn command(&self) -> &'static str {
+        SERVER_UPDATE_PREVIEW
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<UpdatePreviewParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        if req.set.is_empty() {
+            return Err(anyhow::anyhow!("No columns to update"));
+        }
+        let dialect = pool.native_dialect();
+        let db_type = pool.database_type();
+        let columns = pool.get_columns(&req.table).await?;
+        let mut set = Vec::new();
+        for (column, value) in std::mem::take(&mut req.set) {
+            set.push((
+                resolve_column(&db_type, &req.table, &column, &columns)?,
+                value,
+            ));
+        }
+        let primary_keys = pool.get_primary_keys(&req.table).await?;
+        let key_columns = key_columns(&pool, &req.table, &primary_keys).await?;
+
+        // 主键条件的占位符在 UPDATE 中排在 SET 之后
+        let mut key_binds = Vec::new();
+        let (select_where, update_where) = match (req.key.take(), &req.filter) {
+            (Some(key), _) => {
+                if primary_keys.is_empty() {
+                    return Err(anyhow::anyhow!("Table has no primary key: {}", req.table));
+                }
+                let key = match key {
+                    serde_json::Value::Array(key) => key,
+                    value => vec![value],
+                };
+                if key.len() != primary_keys.len() {
+                    return Err(anyhow::anyhow!(
+                        "Expected {} key values, got {}",
+                        primary_keys.len(),
+                        key.len()
+                    ));
+                }
+                for value in key {
+                    key_binds.push(BindValue::try_from(&QueryParam::Plain(value))?);
+                }
+                (
+                    Self::key_condition(&primary_keys, &key_columns, dialect, &db_type, 1),
+                    Self::key_condition(
+                        &primary_keys,
+                        &key_columns,
+                        dialect,
+                        &db_type,
+                        set.len() + 1,
+                    ),
+                )
+            }
+            (None, Some(filter)) => (format!("({})", filter), format!("({})", filter)),
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Give a key or a filter to select the rows to update"
+                ));
+            }
+        };
+
+        let mut update_binds = Vec::new();
+        let mut assignments = Vec::new();
+        for (column, value) in &set {
+            update_binds.push(BindValue::try_from(&QueryParam::Plain(value.clone()))?);
+            assignments.push(format!(
+                "{} = {}",
+                dialect.quote_ident(column),
+                db_type.placeholder(update_binds.len())
+            ));
+        }
+        update_binds.extend(key_binds.iter().cloned());
+        let select_sql = format!(
+            "SELECT * FROM {} WHERE {} LIMIT {}",
+            dialect.quote_ident(&req.table),
+            select_where,
+            req.max_rows
+        );
+        let update_sql = format!(
+            "UPDATE {} SET {} WHERE {}",
+            dialect.quote_ident(&req.table),
+            assignments.join(", "),
+            update_where
+        );
+
+        let reselect_sql = format!(
+            "SELECT * FROM {} WHERE {}",
+            dialect.quote_ident(&req.table),
+            Self::key_condition(&primary_keys, &key_columns, dialect, &db_type, 1)
+        );
+
+        let mut session = pool.begin_session().await?;
+        let outcome = async {
+            let before = session
+                .execute(&select_sql, &key_binds, ResultKind::Rows)
+                .await?;
+            let updated = session
+                .execute(&update_sql, &update_binds, ResultKind::Affected)
+                .await?;
+
+            // 按主键重新读取每一行，主键本身被修改时使用新值
+            let mut rows = Vec::new();
+            let before_rows = before.rows.as_array().cloned().unwrap_or_default();
+            for row in before_rows.iter().filter_map(serde_json::Value::as_object) {
+                if primary_keys.is_empty() {
+                    break;
+                }
+                let key: serde_json::Map<_, _> = primary_keys
+                    .iter()
+                    .map(|k| (k.clone(), row.get(k).cloned().unwrap_or_default()))
+                    .collect();
+                let mut binds = Vec::new();
+                for name in &primary_keys {
+                    let value = set
+                        .iter()
+                        .find(|(column, _)| column == name)
+                        .map(|(_, value)| value)
+                        .unwrap_or(&key[name]);
+                    binds.push(BindValue::try_from(&QueryParam::Plain(value.clone()))?);
+                }
+                let after = session
+                    .execute(&reselect_sql, &binds, ResultKind::Rows)
+                    .await?;
+                let changes = match after.rows.get(0).and_then(serde_json::Value::as_object) {
+                    Some(after) => Self::row_changes(row, after),
+                    None => serde_json::Map::new(),
+                };
+                rows.push(json!({
+                    "key": key,
+                    "changes": changes,
+                }));
+            }
+            anyhow::Ok((updated, rows))
+        }
+        .await;
+        // 无论成功与否都回滚，预览不能留下修改
+        let rolled_back = session.rollback().await;
+        let (updated, rows) = outcome?;
+        rolled_back?;
+
+        let mut warnings = Vec::new();
+        if primary_keys.is_empty() {
+            warnings.push(format!(
+                "{} has no primary key, the changed rows can't be read back",
+                req.table
+            ));
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(
+            CommandResult::try_create(
+                json!({
+                    "sql": update_sql,
+                    "affected": updated.total,
+                    "truncated": updated.total > rows.len(),
+                    "rows": rows,
+                }),
+                execution_time,
+            )?
+            .with_warnings(warnings),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_placeholder() {
+        let column = |data_type: &str| ColumnInfo {
+            name: "id".to_string(),
+            data_type: data_type.to_string(),
+            nullable: false,
+            is_generated: false,
+            generation_expr: None,
+        };
+        assert_eq!(
+            key_placeholder(&DatabaseType::PostgreSQL, 2, Some(&column("uuid"))),
+            "$2::uuid"
+        );
+        assert_eq!(
+            key_placeholder(&DatabaseType::PostgreSQL, 1, Some(&column("USER-DEFINED"))),
+            "$1"
+        );
+        assert_eq!(
+            key_placeholder(&DatabaseType::MySQL, 1, Some(&column("int"))),
+            "?"
+        );
+    }
+
+    #[test]
+    fn test_related_sql() {
+        assert_eq!(
+            GetRelatedRowsCommand::related_sql(
+                Dialect::Postgres,
+                &DatabaseType::PostgreSQL,
+                "users",
+                "orders",
+                &[("id".to_string(), "user_id".to_string())],
+                &[(
+                    "id".to_string(),
+                    Some(ColumnInfo {
+                        name: "id".to_string(),
+                        data_type: "uuid".to_string(),
+                        nullable: false,
+                        is_generated: false,
+                        generation_expr: None,
+                    })
+                )],
+                101
+            ),
+            "SELECT t.* FROM \"orders\" t WHERE EXISTS (SELECT 1 FROM \"users\" s \
+             WHERE s.\"id\" = t.\"user_id\" AND s.\"id\" = $1::uuid) LIMIT 101"
+        );
+    }
+
+    #[test]
+    fn test_update_row_changes() {
+        let before = json!({ "id": 1, "name": "a", "active": true, "updated_at": null });
+        let after = json!({ "id": 1, "name": "b", "active": true, "updated_at": "2024-01-02" });
+        let changes = UpdatePreviewCommand::row_changes(
+            before.as_object().unwrap(),
+            after.as_object().unwrap(),
+        );
+        assert_eq!(
+            serde_json::Value::Object(changes),
+            json!({
+                "name": { "before": "a", "after": "b" },
+                "updated_at": { "before": null, "after": "2024-01-02" },
+            })
+        );
+        assert_eq!(
+            UpdatePreviewCommand::key_condition(
+                &["a".to_string(), "b".to_string()],
+                &[None, None],
+                Dialect::Postgres,
+                &DatabaseType::PostgreSQL,
+                3
+            ),
+            "\"a\" = $3 AND \"b\" = $4"
+        );
+    }
+
+    #[test]
+    fn test_bucket_expr() {
+        assert_eq!(
+            ColumnProfileCommand::bucket_expr("\"price\"", 0.0, 50.0, 5, &DatabaseType::PostgreSQL),
+            "CASE WHEN \"price\" >= 50 THEN 4 ELSE FLOOR((\"price\" - 0) * 5 / (50)) END"
+        );
+        assert!(
+            ColumnProfileCommand::bucket_expr("x", 1.5, 2.5, 2, &DatabaseType::SQLite)
+                .contains("CAST((x - 1.5) * 2 / (1) AS INTEGER)")
+        );
+        assert!(ColumnProfileCommand::is_numeric("double precision"));
+        assert!(ColumnProfileCommand::is_numeric("INTEGER"));
+        assert!(!ColumnProfileCommand::is_numeric("interval"));
+        assert!(!ColumnProfileCommand::is_numeric("varchar(20)"));
+    }
+
+    #[test]
+    fn test_pivot_sql() {
+        let req = PivotParams {
+            connection: ConnectionParams {
+                connection_id: String::new(),
+                connection_string: String::new(),
+                db_type_hint: None,
+            },
+            table: "sales".to_string(),
+            group_by: "region".to_string(),
+            pivot: "year".to_string(),
+            aggregate: PivotAggregate::Sum,
+            value: Some("amount".to_string()),
+        };
+        let values = vec!["2023".to_string(), "2024".to_string()];
+        assert_eq!(
+            PivotCommand::pivot_sql(&req, &values, &DatabaseType::PostgreSQL, Dialect::Postgres),
+            "SELECT \"region\", \
+             SUM(CASE WHEN CAST(\"year\" AS TEXT) = $1 THEN \"amount\" END) AS \"2023\", \
+             SUM(CASE WHEN CAST(\"year\" AS TEXT) = $2 THEN \"amount\" END) AS \"2024\" \
+             FROM \"sales\" GROUP BY \"region\" ORDER BY \"region\""
+        );
+
+        let req = PivotParams {
+            aggregate: PivotAggregate::Count,
+            value: None,
+            ..req
+        };
+        assert_eq!(
+            PivotCommand::pivot_sql(&req, &values[..1], &DatabaseType::MySQL, Dialect::MySql),
+            "SELECT `region`, COUNT(CASE WHEN CAST(`year` AS CHAR) = ? THEN 1 END) AS `2023` \
+             FROM `sales` GROUP BY `region` ORDER BY `region`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_column_profile() {
+        let connection = json!({
+            "connection_id": "test_column_profile",
+            "connection_string": "sqlite::memory:",
+        });
+        let pool = serde_json::from_value::<ConnectionParams>(connection.clone())
+            .unwrap()
+            .pool()
+            .await
+            .unwrap();
+        for sql in [
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, price INTEGER)",
+            "INSERT INTO items (price) VALUES (1), (1), (5), (NULL)",
+        ] {
+            pool.execute_query(sql, &[], ResultKind::Affected)
+                .await
+                .unwrap();
+        }
+        let mut arguments = connection;
+        arguments["table"] = json!("items");
+        arguments["column"] = json!("price");
+        arguments["buckets"] = json!(2);
+        let params = ExecuteCommandParams {
+            command: SERVER_COLUMN_PROFILE.to_string(),
+            arguments: vec![arguments],
+            work_done_progress_params: Default::default(),
+        };
+        let result = ColumnProfileCommand.handler(params).await.unwrap().unwrap();
+        assert_eq!(result.data["row_count"], json!(4));
+        assert_eq!(result.data["null_count"], json!(1));
+        assert_eq!(result.data["distinct_count"], json!(2));
+        let counts: Vec<_> = result.data["histogram"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| bucket["count"].clone())
+            .collect();
+        assert_eq!(counts, vec![json!(2), json!(1)]);
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Deserialize;
+use serde_json::json;
+use tower_lsp::lsp_types::{ExecuteCommandParams, MessageType, Url};
+
+use crate::{constant::SERVER_SET_DOCUMENT_CONNECTION, logger::log};
+
+use super::{Command, CommandResult, ConnectionParams, DocumentConnections, parse_arguments};
+
+#[derive(Debug, Deserialize)]
+struct SetDocumentConnectionParams {
+    uri: Url,
+    /// An empty id clears the association. With a connection string the
+    /// connection is cached right away, so later calls only need the id.
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+/// Associates a default connection with a document, used by its run lenses.
+pub struct SetDocumentConnectionCommand {
+    pub document_connections: DocumentConnections,
+}
+
+#[tower_lsp::async_trait]
+impl Command for SetDocumentConnectionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_SET_DOCUMENT_CONNECTION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<SetDocumentConnectionParams>(&params)?;
+        let mut document_connections = self.document_connections.write().await;
+        let connection_id = req.connection.connection_id.clone();
+        if connection_id.is_empty() {
+            document_connections.remove(req.uri.as_str());
+        } else {
+            if !req.connection.connection_string.is_empty() {
+                crate::db::from_cache(&connection_id, req.connection.options()).await;
+            }
+            log(
+                MessageType::INFO,
+                format!("Document {} uses connection {}", req.uri, connection_id),
+            );
+            document_connections.insert(req.uri.to_string(), connection_id);
+        }
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "result": true,
+            }),
+            0.0,
+        )?))
+    }
+}
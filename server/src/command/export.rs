@@ -0,0 +1,382 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    sync::mpsc,
+};
+use tower_lsp::{
+    Client,
+    lsp_types::{
+        ExecuteCommandParams, MessageType, NumberOrString, ProgressParams, ProgressParamsValue,
+        WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+        WorkDoneProgressReport,
+        notification::{Notification, Progress},
+        request::WorkDoneProgressCreate,
+    },
+};
+
+use crate::{
+    constant::{SERVER_EXPORT_STREAM, SERVER_EXPORT_TO_FILE},
+    db::connection::{CancelGuard, StreamItem},
+    logger::log,
+    settings,
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments};
+
+/// Rows written between two progress reports.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportToFileParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    query: String,
+    path: String,
+    #[serde(default)]
+    format: ExportFormat,
+    /// Stop after this many rows, even when the query has no LIMIT
+    #[serde(default)]
+    max_rows: Option<u64>,
+}
+
+/// Streams a query's rows into a file without holding the result in memory.
+/// The rows go to a temp file next to it, renamed over `path` once the
+/// export succeeds, so a failed or cancelled export leaves `path` as it was.
+pub struct ExportToFileCommand {
+    pub client: Arc<Client>,
+}
+
+#[tower_lsp::async_trait]
+impl Command for ExportToFileCommand {
+    fn command(&self) -> &'static str {
+        SERVER_EXPORT_TO_FILE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ExportToFileParams>(&params)?;
+        log(
+            MessageType::INFO,
+            format!("Exporting query to {}: {}", req.path, req.query),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool_for(&req.query).await?;
+        let progress = WorkDone::begin(
+            &self.client,
+            params.work_done_progress_params.work_done_token,
+            format!("Exporting to {}", req.path),
+        )
+        .await;
+
+        let temp_path = format!(
+            "{}.{}-{}.part",
+            req.path,
+            std::process::id(),
+            NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+        );
+        // 失败或请求被取消时只删除临时文件
+        let cleanup = CancelGuard::new(|| {
+            let _ = std::fs::remove_file(&temp_path);
+        });
+        let reporter = Reporter::WorkDone(&progress);
+        let format = settings::get().format.for_export();
+        let (tx, rx) = mpsc::channel(1024);
+        let (queried, written) = tokio::join!(
+            pool.stream_query(&req.query, &[], req.max_rows, &format, tx),
+            write_rows(&temp_path, req.format, rx, &reporter),
+        );
+        let exported = match (queried, written) {
+            (Ok(truncated), Ok(written)) => tokio::fs::rename(&temp_path, &req.path)
+                .await
+                .map(|_| (truncated, written))
+                .map_err(anyhow::Error::from),
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        let (truncated, (rows, _)) = match exported {
+            Ok(result) => result,
+            Err(e) => {
+                progress.end(format!("Export failed: {}", e)).await;
+                return Err(e);
+            }
+        };
+        cleanup.disarm();
+        progress.end(format!("{} rows written", rows)).await;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "path": req.path,
+                "rows": rows,
+                "truncated": truncated,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportStreamParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    query: String,
+    /// Stop after this many rows, even when the query has no LIMIT
+    #[serde(default)]
+    max_rows: Option<u64>,
+}
+
+/// `db/exportProgress` notification sent while [`ExportStreamCommand`]
+/// writes its file.
+enum ExportProgress {}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportProgressParams {
+    path: String,
+    rows: u64,
+    bytes: u64,
+}
+
+impl Notification for ExportProgress {
+    type Params = ExportProgressParams;
+    const METHOD: &'static str = "db/exportProgress";
+}
+
+/// Streams a query's rows as NDJSON into a temp file, sending
+/// `db/exportProgress` notifications on the way so the webview can show a
+/// progress bar. Returns the path of the file, which is removed when the
+/// export fails or the request is cancelled.
+pub struct ExportStreamCommand {
+    pub client: Arc<Client>,
+}
+
+#[tower_lsp::async_trait]
+impl Command for ExportStreamCommand {
+    fn command(&self) -> &'static str {
+        SERVER_EXPORT_STREAM
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ExportStreamParams>(&params)?;
+        let path = std::env::temp_dir()
+            .join(format!(
+                "dbviewer-export-{}-{}.ndjson",
+                std::process::id(),
+                NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+            ))
+            .to_string_lossy()
+            .to_string();
+        log(
+            MessageType::INFO,
+            format!("Streaming query to {}: {}", path, req.query),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool_for(&req.query).await?;
+        // 请求被取消时 future 会被丢弃，由 guard 删除写了一半的文件
+        let cleanup = CancelGuard::new(|| {
+            let _ = std::fs::remove_file(&path);
+        });
+        let reporter = Reporter::Notification {
+            client: &self.client,
+            path: &path,
+        };
+        let format = settings::get().format.for_export();
+        let (tx, rx) = mpsc::channel(1024);
+        let (queried, written) = tokio::join!(
+            pool.stream_query(&req.query, &[], req.max_rows, &format, tx),
+            write_rows(&path, ExportFormat::Ndjson, rx, &reporter),
+        );
+        let truncated = queried?;
+        let (rows, bytes) = written?;
+        cleanup.disarm();
+        reporter.report(rows, bytes).await;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "path": path,
+                "rows": rows,
+                "bytes": bytes,
+                "truncated": truncated,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+/// Where [`write_rows`] reports how far it got.
+enum Reporter<'a> {
+    WorkDone(&'a WorkDone<'a>),
+    Notification { client: &'a Client, path: &'a str },
+}
+
+impl Reporter<'_> {
+    async fn report(&self, rows: u64, bytes: u64) {
+        match self {
+            Reporter::WorkDone(progress) => progress.report(format!("{} rows written", rows)).await,
+            Reporter::Notification { client, path } => {
+                client
+                    .send_notification::<ExportProgress>(ExportProgressParams {
+                        path: path.to_string(),
+                        rows,
+                        bytes,
+                    })
+                    .await
+            }
+        }
+    }
+}
+
+/// Write the streamed rows to `path`, returning the rows and bytes written.
+async fn write_rows(
+    path: &str,
+    format: ExportFormat,
+    mut rx: mpsc::Receiver<StreamItem>,
+    reporter: &Reporter<'_>,
+) -> anyhow::Result<(u64, u64)> {
+    let mut file = BufWriter::new(tokio::fs::File::create(path).await?);
+    let mut columns = Vec::new();
+    let (mut rows, mut bytes) = (0, 0);
+    while let Some(item) = rx.recv().await {
+        let line = match item {
+            StreamItem::Columns(meta) => {
+                columns = meta.into_iter().map(|c| c.name).collect();
+                if format != ExportFormat::Csv {
+                    continue;
+                }
+                csv_line(columns.iter().map(|c| csv_field(&Value::String(c.clone()))))
+            }
+            StreamItem::Row(row) => {
+                rows += 1;
+                if rows % PROGRESS_INTERVAL == 0 {
+                    reporter.report(rows, bytes).await;
+                }
+                match format {
+                    ExportFormat::Csv => csv_line(
+                        columns
+                            .iter()
+                            .map(|c| row.get(c).map(csv_field).unwrap_or_default()),
+                    ),
+                    ExportFormat::Ndjson => format!("{}\n", Value::Object(row)),
+                }
+            }
+        };
+        file.write_all(line.as_bytes()).await?;
+        bytes += line.len() as u64;
+    }
+    file.flush().await?;
+    Ok((rows, bytes))
+}
+
+fn csv_line(fields: impl Iterator<Item = String>) -> String {
+    let mut line = fields.collect::<Vec<_>>().join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// A value as a CSV field, quoted when it contains a separator, quote or
+/// line break. NULL becomes an empty field.
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Work done progress shown by the client, silently skipped when the client
+/// doesn't support it.
+struct WorkDone<'a> {
+    client: &'a Client,
+    token: Option<NumberOrString>,
+}
+
+impl<'a> WorkDone<'a> {
+    async fn begin(client: &'a Client, token: Option<NumberOrString>, title: String) -> Self {
+        let token = match token {
+            Some(token) => Some(token),
+            None => {
+                let token = NumberOrString::String(format!(
+                    "dbviewer/export/{}",
+                    NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+                ));
+                client
+                    .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                        token: token.clone(),
+                    })
+                    .await
+                    .ok()
+                    .map(|_| token)
+            }
+        };
+        let progress = WorkDone { client, token };
+        progress
+            .send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title,
+                ..Default::default()
+            }))
+            .await;
+        progress
+    }
+
+    async fn report(&self, message: String) {
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            message: Some(message),
+            ..Default::default()
+        }))
+        .await;
+    }
+
+    async fn end(&self, message: String) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(message),
+        }))
+        .await;
+    }
+
+    async fn send(&self, value: WorkDoneProgress) {
+        if let Some(token) = &self.token {
+            self.client
+                .send_notification::<Progress>(ProgressParams {
+                    token: token.clone(),
+                    value: ProgressParamsValue::WorkDone(value),
+                })
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field(&json!("plain")), "plain");
+        assert_eq!(csv_field(&json!("a,b")), "\"a,b\"");
+        assert_eq!(csv_field(&json!("say \"hi\"")), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(&json!(null)), "");
+        assert_eq!(csv_field(&json!(12)), "12");
+    }
+}
@@ -0,0 +1,405 @@
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use sqlx::types::Uuid;
+use tower_lsp::lsp_types::ExecuteCommandParams;
+
+use crate::{
+    constant::{SERVER_GENERATE_INSERTS, SERVER_GENERATE_SELECT},
+    db::{DatabaseType, dialect::Dialect, value::FormatOptions},
+    parser::ResultKind,
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments, resolve_column};
+
+#[derive(Debug, Deserialize)]
+struct GenerateSelectParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Add a `WHERE` on the primary key with parameter placeholders
+    #[serde(default)]
+    where_primary_key: bool,
+    /// Columns to sort by, in order
+    #[serde(default)]
+    order_by: Vec<OrderBy>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderBy {
+    column: String,
+    #[serde(default)]
+    descending: bool,
+    /// Where NULLs sort, the backend's own order when unset: PostgreSQL
+    /// puts them last ascending and first descending, MySQL and SQLite
+    /// treat them as the lowest value.
+    #[serde(default)]
+    nulls: Option<NullsOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum NullsOrder {
+    First,
+    Last,
+}
+
+/// One `ORDER BY` term. MySQL has no `NULLS FIRST/LAST`, so it first sorts
+/// on whether the value is NULL.
+fn order_term(order: &OrderBy, db_type: &DatabaseType, dialect: Dialect) -> String {
+    let column = dialect.quote_ident(&order.column);
+    let direction = if order.descending { "DESC" } else { "ASC" };
+    match (order.nulls, db_type) {
+        (None, _) => format!("{} {}", column, direction),
+        (Some(nulls), DatabaseType::MySQL) => format!(
+            "ISNULL({}) {}, {} {}",
+            column,
+            if nulls == NullsOrder::First {
+                "DESC"
+            } else {
+                "ASC"
+            },
+            column,
+            direction
+        ),
+        (Some(NullsOrder::First), _) => format!("{} {} NULLS FIRST", column, direction),
+        (Some(NullsOrder::Last), _) => format!("{} {} NULLS LAST", column, direction),
+    }
+}
+
+/// Generates a `SELECT` listing every column of a table.
+pub struct GenerateSelectCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GenerateSelectCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GENERATE_SELECT
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<GenerateSelectParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let dialect = pool.dialect();
+
+        let columns = pool.get_columns(&req.table).await?;
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("Table has no columns: {}", req.table));
+        }
+        let mut sql = format!(
+            "SELECT {}\nFROM {}",
+            columns
+                .iter()
+                .map(|c| dialect.quote_ident(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            dialect.quote_ident(&req.table)
+        );
+        if req.where_primary_key {
+            let keys = pool.get_primary_keys(&req.table).await?;
+            if !keys.is_empty() {
+                let db_type = pool.database_type();
+                let conditions: Vec<String> = keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, key)| {
+                        format!(
+                            "{} = {}",
+                            dialect.quote_ident(key),
+                            db_type.placeholder(i + 1)
+                        )
+                    })
+                    .collect();
+                sql.push_str(&format!("\nWHERE {}", conditions.join(" AND ")));
+            }
+        }
+        if !req.order_by.is_empty() {
+            let db_type = pool.database_type();
+            for order in &mut req.order_by {
+                order.column = resolve_column(&db_type, &req.table, &order.column, &columns)?;
+            }
+            let terms: Vec<String> = req
+                .order_by
+                .iter()
+                .map(|order| order_term(order, &db_type, dialect))
+                .collect();
+            sql.push_str(&format!("\nORDER BY {}", terms.join(", ")));
+        }
+        sql.push_str("\nLIMIT 100");
+
+        Ok(Some(CommandResult::try_create(json!({ "sql": sql }), 0.0)?))
+    }
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateInsertsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Optional condition selecting the rows, without the `WHERE`
+    #[serde(default)]
+    filter: Option<String>,
+    /// Stop after this many rows
+    #[serde(default)]
+    max_rows: Option<u64>,
+    /// Rows per `INSERT`. The default stays under MySQL's
+    /// `max_allowed_packet` for typical rows and under SQLite's 500 row
+    /// limit on multi-row `VALUES`.
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+/// A JSON value as a SQL literal.
+fn sql_literal(value: &Value, dialect: Dialect) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        // SQLite has no boolean literals before 3.23
+        Value::Bool(b) if dialect == Dialect::Sqlite => i32::from(*b).to_string(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => dialect.quote_literal(s),
+        other => dialect.quote_literal(&other.to_string()),
+    }
+}
+
+fn is_binary_type(data_type: &str) -> bool {
+    let data_type = data_type.to_lowercase();
+    data_type == "bytea"
+        || data_type.contains("blob")
+        || data_type.starts_with("binary")
+        || data_type.starts_with("varbinary")
+}
+
+/// A binary column's value as a hex literal. Values come back as
+/// `(binary) <base64>`, or as a UUID for MySQL `BINARY(16)` with
+/// `binary_uuid` set.
+fn binary_literal(value: &Value, dialect: Dialect) -> String {
+    let Value::String(text) = value else {
+        return sql_literal(value, dialect);
+    };
+    let bytes = match text.strip_prefix("(binary) ") {
+        Some(encoded) => base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok(),
+        None => Uuid::parse_str(text)
+            .ok()
+            .map(|uuid| uuid.as_bytes().to_vec()),
+    };
+    let Some(bytes) = bytes else {
+        return sql_literal(value, dialect);
+    };
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    match dialect {
+        Dialect::Postgres => format!("'\\x{}'::bytea", hex),
+        _ => format!("X'{}'", hex),
+    }
+}
+
+/// Multi-row `INSERT` statements of at most `batch_size` rows each, every
+/// one complete on its own. Columns in `binary` are written as hex literals.
+fn insert_statements(
+    dialect: Dialect,
+    table: &str,
+    columns: &[String],
+    binary: &[String],
+    rows: &[Map<String, Value>],
+    batch_size: usize,
+) -> Vec<String> {
+    let head = format!(
+        "INSERT INTO {} ({}) VALUES\n",
+        dialect.quote_ident(table),
+        columns
+            .iter()
+            .map(|c| dialect.quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    rows.chunks(batch_size.max(1))
+        .map(|batch| {
+            let values: Vec<String> = batch
+                .iter()
+                .map(|row| {
+                    let fields: Vec<String> = columns
+                        .iter()
+                        .map(|c| {
+                            let value = row.get(c).unwrap_or(&Value::Null);
+                            if binary.contains(c) {
+                                binary_literal(value, dialect)
+                            } else {
+                                sql_literal(value, dialect)
+                            }
+                        })
+                        .collect();
+                    format!("({})", fields.join(", "))
+                })
+                .collect();
+            format!("{}{};", head, values.join(",\n"))
+        })
+        .collect()
+}
+
+/// Generates `INSERT` statements reproducing a table's rows, batched so
+/// large tables give a seed script the server accepts. Generated columns
+/// are left out.
+pub struct GenerateInsertsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GenerateInsertsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GENERATE_INSERTS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<GenerateInsertsParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let dialect = pool.dialect();
+
+        let info: Vec<_> = pool
+            .get_column_info(&req.table)
+            .await?
+            .into_iter()
+            .filter(|c| !c.is_generated)
+            .collect();
+        let columns: Vec<String> = info.iter().map(|c| c.name.clone()).collect();
+        let binary: Vec<String> = info
+            .iter()
+            .filter(|c| is_binary_type(&c.data_type))
+            .map(|c| c.name.clone())
+            .collect();
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Table has no writable columns: {}",
+                req.table
+            ));
+        }
+        let native = pool.native_dialect();
+        let mut sql = format!(
+            "SELECT {} FROM {}",
+            columns
+                .iter()
+                .map(|c| native.quote_ident(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            native.quote_ident(&req.table)
+        );
+        if let Some(filter) = &req.filter {
+            sql.push_str(&format!(" WHERE {}", filter));
+        }
+        if let Some(max_rows) = req.max_rows {
+            sql.push_str(&format!(" LIMIT {}", max_rows));
+        }
+        // 使用默认格式：ISO 日期、JSON null、二进制不转成 blob 引用
+        let output = pool
+            .execute_query_as(&sql, &[], ResultKind::Rows, &FormatOptions::default())
+            .await?;
+        let rows: Vec<Map<String, Value>> = output
+            .rows
+            .as_array()
+            .map(|rows| {
+                rows.iter()
+                    .filter_map(|row| row.as_object().cloned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let statements = insert_statements(
+            dialect,
+            &req.table,
+            &columns,
+            &binary,
+            &rows,
+            req.batch_size,
+        );
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "statements": statements,
+                "rows": rows.len(),
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_statements() {
+        let columns = vec!["id".to_string(), "name".to_string(), "active".to_string()];
+        let rows: Vec<Map<String, Value>> = (1..=3)
+            .map(|id| {
+                json!({ "id": id, "name": format!("it's {}", id), "active": id != 2 })
+                    .as_object()
+                    .cloned()
+                    .unwrap()
+            })
+            .collect();
+
+        let statements = insert_statements(Dialect::Sqlite, "users", &columns, &[], &rows, 2);
+        assert_eq!(
+            statements,
+            vec![
+                "INSERT INTO \"users\" (\"id\", \"name\", \"active\") VALUES\n(1, 'it''s 1', 1),\n(2, 'it''s 2', 0);",
+                "INSERT INTO \"users\" (\"id\", \"name\", \"active\") VALUES\n(3, 'it''s 3', 1);",
+            ]
+        );
+        assert_eq!(
+            insert_statements(Dialect::MySql, "users", &columns, &[], &rows, 500).len(),
+            1
+        );
+        assert!(insert_statements(Dialect::MySql, "users", &columns, &[], &[], 500).is_empty());
+    }
+
+    #[test]
+    fn test_binary_literal() {
+        let value = json!("(binary) AQL/");
+        assert_eq!(binary_literal(&value, Dialect::MySql), "X'0102ff'");
+        assert_eq!(
+            binary_literal(&value, Dialect::Postgres),
+            "'\\x0102ff'::bytea"
+        );
+        assert_eq!(
+            binary_literal(
+                &json!("00000000-0000-0000-0000-0000000000ff"),
+                Dialect::Sqlite
+            ),
+            "X'000000000000000000000000000000ff'"
+        );
+        assert_eq!(binary_literal(&Value::Null, Dialect::MySql), "NULL");
+    }
+
+    #[test]
+    fn test_order_term() {
+        let order = OrderBy {
+            column: "name".to_string(),
+            descending: true,
+            nulls: Some(NullsOrder::Last),
+        };
+        assert_eq!(
+            order_term(&order, &DatabaseType::PostgreSQL, Dialect::Postgres),
+            "\"name\" DESC NULLS LAST"
+        );
+        assert_eq!(
+            order_term(&order, &DatabaseType::MySQL, Dialect::MySql),
+            "ISNULL(`name`) ASC, `name` DESC"
+        );
+
+        let order = OrderBy {
+            nulls: None,
+            ..order
+        };
+        assert_eq!(
+            order_term(&order, &DatabaseType::SQLite, Dialect::Sqlite),
+            "\"name\" DESC"
+        );
+    }
+}
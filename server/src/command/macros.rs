@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tower_lsp::lsp_types::{ExecuteCommandParams, MessageType};
+
+use crate::{
+    constant::SERVER_RUN_MACRO,
+    db::{
+        DatabaseType, cache,
+        connection::{BindValue, QueryParam},
+        dialect::Dialect,
+    },
+    logger::log,
+    parser::{ResultKind, SqlParser},
+    settings,
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments};
+
+#[derive(Debug, Deserialize)]
+struct RunMacroParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    name: String,
+    #[serde(default)]
+    params: HashMap<String, QueryParam>,
+}
+
+/// Runs a query template from the `macros` setting. `${name}` is bound as a
+/// parameter, `${name:ident}` is inserted as a quoted identifier.
+pub struct RunMacroCommand;
+
+#[tower_lsp::async_trait]
+impl Command for RunMacroCommand {
+    fn command(&self) -> &'static str {
+        SERVER_RUN_MACRO
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<RunMacroParams>(&params)?;
+        let template = settings::get()
+            .macros
+            .remove(&req.name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown macro: {}", req.name))?;
+        let pool = req.connection.pool().await?;
+        let (sql, binds) = expand(
+            &template,
+            &req.params,
+            &pool.database_type(),
+            pool.native_dialect(),
+        )?;
+        log(
+            MessageType::INFO,
+            format!("Running macro {}: {}", req.name, sql),
+        );
+
+        let start_time = std::time::Instant::now();
+        let kind = SqlParser::new().result_kind(&sql);
+        let output = pool.execute_query(&sql, &binds, kind).await?;
+        if kind == ResultKind::Affected {
+            cache::clear(Some(&req.connection.connection_id));
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(
+            CommandResult::try_create(
+                json!({
+                    "kind": kind,
+                    "columns": output.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                    "rows": output.rows,
+                    "affected_rows": output.total,
+                }),
+                execution_time,
+            )?
+            .with_warnings(output.warnings),
+        ))
+    }
+}
+
+/// Replace the placeholders of a template, returning the SQL and the values
+/// to bind in order. Every placeholder needs a value and every value must
+/// be used.
+fn expand(
+    template: &str,
+    params: &HashMap<String, QueryParam>,
+    db_type: &DatabaseType,
+    dialect: Dialect,
+) -> anyhow::Result<(String, Vec<BindValue>)> {
+    let mut sql = String::with_capacity(template.len());
+    let mut binds = Vec::new();
+    let mut used = HashSet::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        sql.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .ok_or_else(|| anyhow::anyhow!("Unterminated placeholder in macro"))?;
+        let placeholder = &rest[start + 2..end];
+        let (name, modifier) = match placeholder.split_once(':') {
+            Some((name, modifier)) => (name.trim(), Some(modifier.trim())),
+            None => (placeholder.trim(), None),
+        };
+        let value = params
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Missing macro parameter: {}", name))?;
+        used.insert(name);
+        match modifier {
+            None => {
+                binds.push(BindValue::try_from(value)?);
+                sql.push_str(&db_type.placeholder(binds.len()));
+            }
+            Some("ident") => {
+                let QueryParam::Plain(Value::String(ident)) = value else {
+                    return Err(anyhow::anyhow!("Parameter {} must be a string", name));
+                };
+                sql.push_str(&dialect.quote_ident(ident));
+            }
+            Some(other) => {
+                return Err(anyhow::anyhow!("Unknown placeholder type: {}", other));
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    sql.push_str(rest);
+
+    let mut unknown: Vec<&str> = params
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !used.contains(name))
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort();
+        return Err(anyhow::anyhow!(
+            "Unknown macro parameters: {}",
+            unknown.join(", ")
+        ));
+    }
+    Ok((sql, binds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let params = HashMap::from([
+            ("table".to_string(), QueryParam::Plain(json!("orders"))),
+            ("min".to_string(), QueryParam::Plain(json!(10))),
+        ]);
+        let (sql, binds) = expand(
+            "SELECT * FROM ${table:ident} WHERE total > ${min} OR id = ${min}",
+            &params,
+            &DatabaseType::PostgreSQL,
+            Dialect::Ansi,
+        )
+        .unwrap();
+        assert_eq!(sql, "SELECT * FROM \"orders\" WHERE total > $1 OR id = $2");
+        assert_eq!(binds, vec![BindValue::Int(10), BindValue::Int(10)]);
+
+        let missing = expand(
+            "SELECT ${x}",
+            &HashMap::new(),
+            &DatabaseType::MySQL,
+            Dialect::Ansi,
+        );
+        assert!(missing.is_err());
+        let unknown = expand("SELECT 1", &params, &DatabaseType::MySQL, Dialect::Ansi);
+        assert!(unknown.is_err());
+    }
+}
@@ -1,12 +1,144 @@
-use cmd::{CheckConnectionCommand, ExecuteCommand};
-use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+use cmd::{
+    BeginSessionCommand, CheckConnectionCommand, CheckConnectionsCommand, ClearResultCacheCommand,
+    EndSessionCommand, ExecuteCommand, ExecuteTransactionCommand, RunRangeCommand,
+    RunStatementAtCommand, ValidateConnectionCommand,
+};
+use data::{
+    ColumnProfileCommand, FetchBlobCommand, FetchCellCommand, GetRecentRowsCommand,
+    GetRelatedRowsCommand, GetRowsByKeysCommand, PivotCommand, UpdatePreviewCommand,
+};
+use database::{BuildConnectionStringCommand, CreateDatabaseCommand, RenameSchemaCommand};
+use document::SetDocumentConnectionCommand;
+use export::{ExportStreamCommand, ExportToFileCommand};
+use generate::{GenerateInsertsCommand, GenerateSelectCommand};
+use macros::RunMacroCommand;
+use schema::{
+    DiffSchemaCommand, DumpSchemaCommand, GetCheckConstraintsCommand, GetColumnInfoCommand,
+    GetPartitionsCommand, GetPrivilegesCommand, GetTableConstraintsDdlCommand, GetTriggersCommand,
+    GetTypesCommand, GetViewDefinitionCommand, ListSequencesCommand, ListViewsCommand,
+    ObjectExistsCommand,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
-use tower_lsp::lsp_types::ExecuteCommandParams;
+use stats::{
+    AdvisoryLockStatusCommand, DryRunCommand, ExplainPlanCommand, GetServerVariablesCommand,
+    NormalizeQueryCommand, SchemaOverviewCommand, ServerStateCommand, SlowQueriesCommand,
+    TransactionInfoCommand,
+};
+use table::{
+    CloneTableStructureCommand, CreateTableAsCommand, DropTableCommand, IdentityInfoCommand,
+    MaintenanceCommand, RenameTableCommand,
+};
+use tokio::sync::RwLock;
+use tower_lsp::{
+    Client,
+    lsp_types::{ExecuteCommandParams, MessageType},
+};
+
+use crate::{
+    db::{
+        ConnectionPool, DatabaseType,
+        connection::{DBConnectionOptions, QueryTiming},
+        schema,
+    },
+    logger::log,
+    parser::DocumentMap,
+};
 
+#[cfg(feature = "arrow")]
+mod arrow;
 pub mod cmd;
+pub mod data;
+pub mod database;
+pub mod document;
+pub mod export;
+pub mod generate;
+pub mod macros;
+pub mod schema;
+pub mod stats;
+pub mod table;
+
+/// Connection ids associated with documents, keyed by URI.
+pub type DocumentConnections = Arc<RwLock<HashMap<String, String>>>;
 
-pub fn commands() -> Vec<Box<dyn Command + Send + Sync>> {
-    vec![Box::new(ExecuteCommand), Box::new(CheckConnectionCommand)]
+pub fn commands(
+    client: Arc<Client>,
+    document_map: DocumentMap,
+    document_connections: DocumentConnections,
+) -> Vec<Box<dyn Command + Send + Sync>> {
+    vec![
+        Box::new(ExecuteCommand {
+            client: client.clone(),
+        }),
+        Box::new(RunStatementAtCommand {
+            client: client.clone(),
+            document_map: document_map.clone(),
+            document_connections: document_connections.clone(),
+        }),
+        Box::new(RunRangeCommand {
+            client: client.clone(),
+            document_map,
+            document_connections: document_connections.clone(),
+        }),
+        Box::new(SetDocumentConnectionCommand {
+            document_connections,
+        }),
+        Box::new(CheckConnectionCommand),
+        Box::new(CheckConnectionsCommand),
+        Box::new(ValidateConnectionCommand),
+        Box::new(ExecuteTransactionCommand),
+        Box::new(BeginSessionCommand),
+        Box::new(EndSessionCommand),
+        Box::new(ClearResultCacheCommand),
+        Box::new(GetTypesCommand),
+        Box::new(MaintenanceCommand),
+        Box::new(GetTriggersCommand),
+        Box::new(GetPartitionsCommand),
+        Box::new(GetCheckConstraintsCommand),
+        Box::new(GetTableConstraintsDdlCommand),
+        Box::new(GetColumnInfoCommand),
+        Box::new(ListViewsCommand),
+        Box::new(ListSequencesCommand),
+        Box::new(GetViewDefinitionCommand),
+        Box::new(CreateDatabaseCommand),
+        Box::new(RenameSchemaCommand),
+        Box::new(DropTableCommand),
+        Box::new(CloneTableStructureCommand),
+        Box::new(CreateTableAsCommand),
+        Box::new(IdentityInfoCommand),
+        Box::new(RenameTableCommand),
+        Box::new(ObjectExistsCommand),
+        Box::new(DumpSchemaCommand),
+        Box::new(DiffSchemaCommand),
+        Box::new(GenerateSelectCommand),
+        Box::new(GenerateInsertsCommand),
+        Box::new(GetRowsByKeysCommand),
+        Box::new(FetchBlobCommand),
+        Box::new(FetchCellCommand),
+        Box::new(GetRecentRowsCommand),
+        Box::new(PivotCommand),
+        Box::new(ColumnProfileCommand),
+        Box::new(UpdatePreviewCommand),
+        Box::new(GetRelatedRowsCommand),
+        Box::new(BuildConnectionStringCommand),
+        Box::new(SlowQueriesCommand),
+        Box::new(DryRunCommand),
+        Box::new(ExplainPlanCommand),
+        Box::new(TransactionInfoCommand),
+        Box::new(ServerStateCommand),
+        Box::new(GetServerVariablesCommand),
+        Box::new(AdvisoryLockStatusCommand),
+        Box::new(NormalizeQueryCommand),
+        Box::new(SchemaOverviewCommand),
+        Box::new(RunMacroCommand),
+        Box::new(GetPrivilegesCommand),
+        Box::new(ExportToFileCommand {
+            client: client.clone(),
+        }),
+        Box::new(ExportStreamCommand { client }),
+    ]
 }
 
 #[tower_lsp::async_trait]
@@ -21,6 +153,38 @@ pub struct CommandResult {
     data: Value,
     // 执行时间（毫秒）
     execution_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timing: Option<Timing>,
+    /// Non-fatal warnings or notices raised while running the query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// Correlation id of the command, also prefixed to its log lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Objects created, altered or dropped by a DDL statement
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    affected_objects: Vec<String>,
+    /// `data` is a base64 string of its gzipped JSON
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    compressed: bool,
+}
+
+/// Breakdown of `execution_time` by phase, in milliseconds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Timing {
+    acquire_ms: f64,
+    execute_ms: f64,
+    serialize_ms: f64,
+}
+
+impl From<QueryTiming> for Timing {
+    fn from(timing: QueryTiming) -> Self {
+        Timing {
+            acquire_ms: timing.acquire.as_secs_f64() * 1000.0,
+            execute_ms: timing.execute.as_secs_f64() * 1000.0,
+            serialize_ms: timing.serialize.as_secs_f64() * 1000.0,
+        }
+    }
 }
 
 impl CommandResult {
@@ -28,6 +192,163 @@ impl CommandResult {
         Ok(CommandResult {
             data: serde_json::to_value(data)?,
             execution_time,
+            timing: None,
+            warnings: Vec::new(),
+            request_id: None,
+            affected_objects: Vec::new(),
+            compressed: false,
         })
     }
+
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = Some(timing);
+        self
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: String) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    pub fn with_affected_objects(mut self, affected_objects: Vec<String>) -> Self {
+        self.affected_objects = affected_objects;
+        self
+    }
+
+    /// Compress `data` when its JSON reaches `threshold` bytes, so large
+    /// results cross a slow LSP channel faster. Sent as is when that fails.
+    pub fn compress(mut self, threshold: usize) -> Self {
+        let compressed = serde_json::to_vec(&self.data)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                if json.len() >= threshold {
+                    gzip_base64(&json).map(Some)
+                } else {
+                    Ok(None)
+                }
+            });
+        match compressed {
+            Ok(Some(data)) => {
+                self.data = Value::String(data);
+                self.compressed = true;
+            }
+            Ok(None) => {}
+            Err(e) => log(
+                MessageType::WARNING,
+                format!("Sending result uncompressed: {}", e),
+            ),
+        }
+        self
+    }
+}
+
+#[cfg(feature = "compress")]
+fn gzip_base64(bytes: &[u8]) -> anyhow::Result<String> {
+    use base64::Engine;
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(bytes)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encoder.finish()?))
+}
+
+#[cfg(not(feature = "compress"))]
+fn gzip_base64(_: &[u8]) -> anyhow::Result<String> {
+    Err(anyhow::anyhow!(
+        "Compressed results are not available, the server was built without the `compress` feature"
+    ))
+}
+
+/// Connection fields shared by every command that talks to a database.
+#[derive(Debug, Deserialize)]
+pub struct ConnectionParams {
+    #[serde(default)]
+    pub connection_id: String,
+    #[serde(default)]
+    pub connection_string: String,
+    /// Overrides detecting the database type from `connection_string`
+    #[serde(default)]
+    pub db_type_hint: Option<DatabaseType>,
+    /// Read replica for read-only queries, see
+    /// [`DBConnectionOptions::replica_connection_string`]
+    #[serde(default)]
+    pub replica_connection_string: Option<String>,
+    /// SQLCipher key, see [`DBConnectionOptions::key`]
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl ConnectionParams {
+    pub fn options(&self) -> DBConnectionOptions {
+        DBConnectionOptions {
+            connection_string: self.connection_string.clone(),
+            db_type_hint: self.db_type_hint.clone(),
+            replica_connection_string: self.replica_connection_string.clone(),
+            key: self.key.clone(),
+        }
+    }
+
+    /// Pool to run `query` on: the replica when the query only reads and
+    /// one is configured, the primary otherwise.
+    pub async fn pool_for(&self, query: &str) -> anyhow::Result<Arc<ConnectionPool>> {
+        let connection = crate::db::from_cache(&self.connection_id, self.options()).await;
+        if connection.has_replica()
+            && crate::parser::is_read_only(query)
+            && let Some(replica) = connection.get_replica_pool().await
+        {
+            return Ok(replica);
+        }
+        connection
+            .get_pool()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Failed to get pool from connection"))
+    }
+
+    pub async fn pool(&self) -> anyhow::Result<Arc<ConnectionPool>> {
+        crate::db::from_cache(&self.connection_id, self.options())
+            .await
+            .get_pool()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Failed to get pool from connection"))
+    }
+
+    /// Catalog name of the table or view the user typed as `name`. Looked up
+    /// in the connection's cached schema when it is loaded, the catalog is
+    /// only queried on a miss.
+    pub async fn resolve_table(&self, pool: &ConnectionPool, name: &str) -> anyhow::Result<String> {
+        if let Some(schema) = schema::cached(&self.connection_id).await {
+            let tables: Vec<String> = schema.tables.keys().cloned().collect();
+            if let Some(table) = schema::resolve_name(&pool.database_type(), name, &tables) {
+                return Ok(table);
+            }
+        }
+        pool.resolve_table(name).await
+    }
+}
+
+/// Catalog name of the column of `table` the user typed as `column`, see
+/// [`schema::resolve_name`].
+pub fn resolve_column(
+    db_type: &DatabaseType,
+    table: &str,
+    column: &str,
+    columns: &[String],
+) -> anyhow::Result<String> {
+    schema::resolve_name(db_type, column, columns)
+        .ok_or_else(|| anyhow::anyhow!("Unknown column {} in table {}", column, table))
+}
+
+/// Deserialize the first command argument.
+pub fn parse_arguments<T: DeserializeOwned>(params: &ExecuteCommandParams) -> anyhow::Result<T> {
+    let argument = params
+        .arguments
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Missing command arguments"))?;
+    Ok(serde_json::from_value::<T>(argument)?)
 }
@@ -0,0 +1,721 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tower_lsp::lsp_types::ExecuteCommandParams;
+
+use crate::{
+    constant::{
+        SERVER_DIFF_SCHEMA, SERVER_DUMP_SCHEMA, SERVER_GET_CHECK_CONSTRAINTS,
+        SERVER_GET_COLUMN_INFO, SERVER_GET_PARTITIONS, SERVER_GET_PRIVILEGES,
+        SERVER_GET_TABLE_CONSTRAINTS_DDL, SERVER_GET_TRIGGERS, SERVER_GET_TYPES,
+        SERVER_GET_VIEW_DEFINITION, SERVER_LIST_SEQUENCES, SERVER_LIST_VIEWS, SERVER_OBJECT_EXISTS,
+    },
+    db::{
+        DatabaseType,
+        connection::{CheckConstraint, ColumnInfo, ForeignKey, IndexInfo, TablePrivileges},
+        dialect::Dialect,
+    },
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments};
+
+#[derive(Debug, Deserialize)]
+struct GetTypesParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+/// Lists user-defined types (enums, composites, domains, ranges).
+pub struct GetTypesCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetTypesCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_TYPES
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<GetTypesParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let types = pool.get_types().await?;
+        Ok(Some(CommandResult::try_create(types, 0.0)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TableParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+}
+
+/// Lists the triggers defined on a table.
+pub struct GetTriggersCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetTriggersCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_TRIGGERS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let triggers = pool.get_triggers(&req.table).await?;
+        Ok(Some(CommandResult::try_create(triggers, 0.0)?))
+    }
+}
+
+/// Lists the partitions of a table for the tree view, empty when the table
+/// isn't partitioned.
+pub struct GetPartitionsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetPartitionsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_PARTITIONS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let partitions = pool.get_partitions(&req.table).await?;
+        Ok(Some(CommandResult::try_create(partitions, 0.0)?))
+    }
+}
+
+/// Lists the CHECK constraints defined on a table.
+pub struct GetCheckConstraintsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetCheckConstraintsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_CHECK_CONSTRAINTS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let constraints = pool.get_check_constraints(&req.table).await?;
+        let mut warnings = Vec::new();
+        if constraints.iter().any(|constraint| constraint.approximate) {
+            warnings.push(format!(
+                "Could not parse the definition of {}, constraints were extracted from its text and may be incomplete",
+                req.table
+            ));
+        }
+        Ok(Some(
+            CommandResult::try_create(constraints, 0.0)?.with_warnings(warnings),
+        ))
+    }
+}
+
+/// Returns the statements recreating a table's primary key, indexes and
+/// constraints outside of its CREATE TABLE, in an order they can be run in.
+pub struct GetTableConstraintsDdlCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetTableConstraintsDdlCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_TABLE_CONSTRAINTS_DDL
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let primary_key = pool.get_primary_keys(&req.table).await?;
+        let indexes = pool.get_indexes(&req.table).await?;
+        let foreign_keys = pool.get_foreign_keys(&req.table).await?;
+        let checks = pool.get_check_constraints(&req.table).await?;
+
+        let mut warnings = Vec::new();
+        if pool.database_type() == DatabaseType::SQLite {
+            warnings.push(
+                "SQLite can't add constraints to an existing table, only the CREATE INDEX \
+                 statements can be run"
+                    .to_string(),
+            );
+        }
+        if checks.iter().any(|check| check.approximate) {
+            warnings.push(
+                "Some CHECK constraints were extracted from text and may be incomplete".to_string(),
+            );
+        }
+        let statements = constraints_ddl(
+            &pool.dialect(),
+            &req.table,
+            &primary_key,
+            &indexes,
+            &foreign_keys,
+            &checks,
+        );
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(
+            CommandResult::try_create(statements, execution_time)?.with_warnings(warnings),
+        ))
+    }
+}
+
+/// Primary key first, foreign keys last so the keys they reference exist.
+fn constraints_ddl(
+    dialect: &Dialect,
+    table: &str,
+    primary_key: &[String],
+    indexes: &[IndexInfo],
+    foreign_keys: &[ForeignKey],
+    checks: &[CheckConstraint],
+) -> Vec<String> {
+    let table = dialect.quote_ident(table);
+    let column_list = |columns: &[String]| {
+        columns
+            .iter()
+            .map(|column| {
+                // 表达式不加引号
+                if column.starts_with('(') {
+                    column.clone()
+                } else {
+                    dialect.quote_ident(column)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let constraint = |name: &Option<String>| match name {
+        Some(name) => format!("CONSTRAINT {} ", dialect.quote_ident(name)),
+        None => String::new(),
+    };
+
+    let mut statements = Vec::new();
+    if !primary_key.is_empty() {
+        statements.push(format!(
+            "ALTER TABLE {} ADD PRIMARY KEY ({});",
+            table,
+            column_list(primary_key)
+        ));
+    }
+    for index in indexes {
+        // PostgreSQL 的定义包含访问方法、部分索引条件和 INCLUDE 列
+        if let Some(definition) = &index.definition {
+            statements.push(format!("{};", definition));
+            continue;
+        }
+        statements.push(format!(
+            "CREATE {}INDEX {} ON {} ({});",
+            if index.unique { "UNIQUE " } else { "" },
+            dialect.quote_ident(&index.name),
+            table,
+            column_list(&index.columns)
+        ));
+    }
+    for check in checks {
+        statements.push(format!(
+            "ALTER TABLE {} ADD {}CHECK ({});",
+            table,
+            constraint(&check.name),
+            check.expression
+        ));
+    }
+
+    // 外键按列对返回，同名或同 id 的合并为一个复合外键
+    let mut groups: Vec<(&ForeignKey, Vec<String>, Vec<String>)> = Vec::new();
+    for fk in foreign_keys {
+        match groups.last_mut() {
+            Some((first, columns, referenced))
+                if (fk.name.is_some() || fk.id.is_some())
+                    && first.name == fk.name
+                    && first.id == fk.id
+                    && first.referenced_table == fk.referenced_table =>
+            {
+                columns.push(fk.column.clone());
+                referenced.push(fk.referenced_column.clone());
+            }
+            _ => groups.push((
+                fk,
+                vec![fk.column.clone()],
+                vec![fk.referenced_column.clone()],
+            )),
+        }
+    }
+    for (fk, columns, referenced) in groups {
+        statements.push(format!(
+            "ALTER TABLE {} ADD {}FOREIGN KEY ({}) REFERENCES {} ({});",
+            table,
+            constraint(&fk.name),
+            column_list(&columns),
+            dialect.quote_ident(&fk.referenced_table),
+            column_list(&referenced)
+        ));
+    }
+    statements
+}
+
+/// Lists a table's columns. Generated columns are flagged so the grid can
+/// keep them read-only.
+pub struct GetColumnInfoCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetColumnInfoCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_COLUMN_INFO
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let columns = pool.get_column_info(&req.table).await?;
+        Ok(Some(CommandResult::try_create(columns, 0.0)?))
+    }
+}
+
+/// The current user's SELECT/INSERT/UPDATE/DELETE privileges on a table.
+/// When they can't be read everything is reported as granted, unverified,
+/// so the client doesn't block actions that may be allowed.
+pub struct GetPrivilegesCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetPrivilegesCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_PRIVILEGES
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<TableParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let (privileges, warnings) = match pool.get_privileges(&req.table).await {
+            Ok(privileges) => (privileges, Vec::new()),
+            Err(e) => (
+                TablePrivileges {
+                    verified: false,
+                    ..TablePrivileges::all()
+                },
+                vec![format!("Could not read privileges: {}", e)],
+            ),
+        };
+        Ok(Some(
+            CommandResult::try_create(privileges, 0.0)?.with_warnings(warnings),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListViewsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+/// Lists the views, which the table listing leaves out.
+pub struct ListViewsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ListViewsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_LIST_VIEWS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ListViewsParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let views = pool.get_views().await?;
+        Ok(Some(CommandResult::try_create(views, 0.0)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListSequencesParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+/// Lists sequences, or auto-increment counters on MySQL and SQLite.
+pub struct ListSequencesCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ListSequencesCommand {
+    fn command(&self) -> &'static str {
+        SERVER_LIST_SEQUENCES
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ListSequencesParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let sequences = pool.get_sequences().await?;
+        Ok(Some(CommandResult::try_create(sequences, 0.0)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ViewParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    view: String,
+}
+
+/// Returns the query a view is defined with.
+pub struct GetViewDefinitionCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetViewDefinitionCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_VIEW_DEFINITION
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ViewParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let definition = pool.get_view_definition(&req.view).await?;
+        Ok(Some(CommandResult::try_create(
+            json!({ "definition": definition }),
+            0.0,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectExistsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    #[serde(default)]
+    column: Option<String>,
+}
+
+/// Checks whether a table, and optionally one of its columns, exists.
+pub struct ObjectExistsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ObjectExistsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_OBJECT_EXISTS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ObjectExistsParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let table = pool.table_exists(&req.table).await?;
+        let column = match &req.column {
+            Some(column) if table => Some(pool.column_exists(&req.table, column).await?),
+            Some(_) => Some(false),
+            None => None,
+        };
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "table": table,
+                "column": column,
+            }),
+            0.0,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DumpSchemaParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    /// Only dump these tables, all tables when empty
+    #[serde(default)]
+    tables: Vec<String>,
+}
+
+/// Builds a DDL script for the schema, without any data.
+pub struct DumpSchemaCommand;
+
+#[tower_lsp::async_trait]
+impl Command for DumpSchemaCommand {
+    fn command(&self) -> &'static str {
+        SERVER_DUMP_SCHEMA
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<DumpSchemaParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+
+        let mut tables = pool.get_tables().await?;
+        if !req.tables.is_empty() {
+            tables.retain(|table| req.tables.contains(table));
+        }
+        let mut references = HashMap::new();
+        for table in &tables {
+            let foreign_keys = pool.get_foreign_keys(table).await.unwrap_or_default();
+            references.insert(
+                table.clone(),
+                foreign_keys
+                    .into_iter()
+                    .map(|fk| fk.referenced_table)
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let mut script = String::new();
+        for table in dependency_order(&tables, &references) {
+            script.push_str(&format!("-- Table: {}\n", table));
+            match pool.get_table_ddl(&table).await {
+                Ok(ddl) => script.push_str(&ddl),
+                Err(e) => script.push_str(&format!("-- DDL not available: {}", e)),
+            }
+            script.push_str("\n\n");
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "ddl": script,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffSchemaParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table_a: String,
+    table_b: String,
+    /// Connection of `table_b` when it lives elsewhere, e.g. dev vs. prod
+    #[serde(default)]
+    connection_b: Option<ConnectionParams>,
+}
+
+/// Difference of a column present in both tables.
+#[derive(Debug, PartialEq, Serialize)]
+struct ColumnChange {
+    name: String,
+    /// `[a, b]` when the declared types differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_type: Option<(String, String)>,
+    /// `[a, b]` when only one side allows NULL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nullable: Option<(bool, bool)>,
+}
+
+/// Columns of `table_b` compared to `table_a`.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct SchemaDiff {
+    /// Only in `table_b`
+    added: Vec<String>,
+    /// Only in `table_a`
+    removed: Vec<String>,
+    changed: Vec<ColumnChange>,
+}
+
+/// Compares the columns of two tables, possibly on two connections.
+pub struct DiffSchemaCommand;
+
+#[tower_lsp::async_trait]
+impl Command for DiffSchemaCommand {
+    fn command(&self) -> &'static str {
+        SERVER_DIFF_SCHEMA
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<DiffSchemaParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool_a = req.connection.pool().await?;
+        let pool_b = match &req.connection_b {
+            Some(connection) => connection.pool().await?,
+            None => pool_a.clone(),
+        };
+        let columns_a = pool_a.get_column_info(&req.table_a).await?;
+        let columns_b = pool_b.get_column_info(&req.table_b).await?;
+        if columns_a.is_empty() {
+            return Err(anyhow::anyhow!("Table not found: {}", req.table_a));
+        }
+        if columns_b.is_empty() {
+            return Err(anyhow::anyhow!("Table not found: {}", req.table_b));
+        }
+        let diff = diff_columns(&columns_a, &columns_b);
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        Ok(Some(CommandResult::try_create(diff, execution_time)?))
+    }
+}
+
+/// Match columns by name; type names are compared case-insensitively.
+fn diff_columns(a: &[ColumnInfo], b: &[ColumnInfo]) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+    for column in a {
+        let Some(other) = b.iter().find(|c| c.name == column.name) else {
+            diff.removed.push(column.name.clone());
+            continue;
+        };
+        let data_type = (!column.data_type.eq_ignore_ascii_case(&other.data_type))
+            .then(|| (column.data_type.clone(), other.data_type.clone()));
+        let nullable =
+            (column.nullable != other.nullable).then_some((column.nullable, other.nullable));
+        if data_type.is_some() || nullable.is_some() {
+            diff.changed.push(ColumnChange {
+                name: column.name.clone(),
+                data_type,
+                nullable,
+            });
+        }
+    }
+    diff.added = b
+        .iter()
+        .filter(|column| !a.iter().any(|c| c.name == column.name))
+        .map(|column| column.name.clone())
+        .collect();
+    diff
+}
+
+/// Order tables so referenced tables are created first. Tables in a
+/// reference cycle are appended in their original order.
+fn dependency_order(tables: &[String], references: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut ordered: Vec<String> = Vec::with_capacity(tables.len());
+    let mut placed = HashSet::new();
+    loop {
+        let mut progress = false;
+        for table in tables {
+            if placed.contains(table) {
+                continue;
+            }
+            let ready = references.get(table).is_none_or(|refs| {
+                refs.iter()
+                    .all(|r| r == table || placed.contains(r) || !tables.contains(r))
+            });
+            if ready {
+                placed.insert(table.clone());
+                ordered.push(table.clone());
+                progress = true;
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+    ordered.extend(tables.iter().filter(|t| !placed.contains(*t)).cloned());
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints_ddl() {
+        let fk = |name: &str, column: &str, referenced_column: &str| ForeignKey {
+            name: Some(name.to_string()),
+            id: None,
+            column: column.to_string(),
+            referenced_table: "orders".to_string(),
+            referenced_column: referenced_column.to_string(),
+        };
+        let statements = constraints_ddl(
+            &Dialect::Postgres,
+            "items",
+            &["id".to_string()],
+            &[IndexInfo {
+                name: "items_sku".to_string(),
+                columns: vec!["sku".to_string(), "(lower(name))".to_string()],
+                unique: true,
+                definition: None,
+            }],
+            &[
+                fk("items_order", "order_id", "id"),
+                fk("items_order", "order_rev", "rev"),
+            ],
+            &[CheckConstraint {
+                name: None,
+                column: Some("qty".to_string()),
+                expression: "qty > 0".to_string(),
+                approximate: false,
+            }],
+        );
+        assert_eq!(
+            statements,
+            vec![
+                r#"ALTER TABLE "items" ADD PRIMARY KEY ("id");"#,
+                r#"CREATE UNIQUE INDEX "items_sku" ON "items" ("sku", (lower(name)));"#,
+                r#"ALTER TABLE "items" ADD CHECK (qty > 0);"#,
+                r#"ALTER TABLE "items" ADD CONSTRAINT "items_order" FOREIGN KEY ("order_id", "order_rev") REFERENCES "orders" ("id", "rev");"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constraints_ddl_index_definition() {
+        let statements = constraints_ddl(
+            &Dialect::Postgres,
+            "items",
+            &[],
+            &[IndexInfo {
+                name: "items_tags".to_string(),
+                columns: vec!["tags".to_string()],
+                unique: false,
+                definition: Some(
+                    "CREATE INDEX items_tags ON public.items USING gin (tags) WHERE active"
+                        .to_string(),
+                ),
+            }],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            statements,
+            vec!["CREATE INDEX items_tags ON public.items USING gin (tags) WHERE active;"]
+        );
+    }
+
+    #[test]
+    fn test_diff_columns() {
+        let column = |name: &str, data_type: &str, nullable: bool| ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            is_generated: false,
+            generation_expr: None,
+        };
+        let a = vec![
+            column("id", "int", false),
+            column("name", "varchar", true),
+            column("legacy", "text", true),
+        ];
+        let b = vec![
+            column("id", "INT", false),
+            column("name", "text", false),
+            column("email", "text", true),
+        ];
+        assert_eq!(
+            diff_columns(&a, &b),
+            SchemaDiff {
+                added: vec!["email".to_string()],
+                removed: vec!["legacy".to_string()],
+                changed: vec![ColumnChange {
+                    name: "name".to_string(),
+                    data_type: Some(("varchar".to_string(), "text".to_string())),
+                    nullable: Some((true, false)),
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let tables: Vec<String> = ["order_items", "orders", "users", "a", "b"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let references = HashMap::from([
+            ("order_items".to_string(), vec!["orders".to_string()]),
+            (
+                "orders".to_string(),
+                vec!["users".to_string(), "orders".to_string()],
+            ),
+            ("a".to_string(), vec!["b".to_string()]),
+            ("b".to_string(), vec!["a".to_string()]),
+        ]);
+        assert_eq!(
+            dependency_order(&tables, &references),
+            vec!["users", "orders", "order_items", "a", "b"]
+        );
+    }
+}
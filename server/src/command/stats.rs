@@ -0,0 +1,710 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlparser::ast::Statement;
+use tower_lsp::lsp_types::ExecuteCommandParams;
+
+use crate::{
+    constant::{
+        SERVER_ADVISORY_LOCK, SERVER_DRY_RUN, SERVER_EXPLAIN_PLAN, SERVER_GET_SERVER_VARIABLES,
+        SERVER_NORMALIZE_QUERY, SERVER_SCHEMA_OVERVIEW, SERVER_SERVER_STATE, SERVER_SLOW_QUERIES,
+        SERVER_TRANSACTION_INFO,
+    },
+    db::{
+        ConnectionPool, DatabaseType,
+        connection::{BindValue, QueryOutput, SlowQueryOrder, TableStats},
+        explain,
+        session::{self, SharedSession},
+    },
+    parser::{ResultKind, SqlParser, affected_objects, fingerprint, statement_kind},
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments};
+
+fn default_limit() -> i64 {
+    20
+}
+
+fn default_max_variables() -> usize {
+    1000
+}
+
+#[derive(Debug, Deserialize)]
+struct SlowQueriesParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    order: SlowQueryOrder,
+}
+
+/// Lists the most expensive statements from the server's statistics.
+pub struct SlowQueriesCommand;
+
+#[tower_lsp::async_trait]
+impl Command for SlowQueriesCommand {
+    fn command(&self) -> &'static str {
+        SERVER_SLOW_QUERIES
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<SlowQueriesParams>(&params)?;
+        let pool = req.connection.pool().await?;
+        let queries = pool.get_slow_queries(req.limit, req.order).await?;
+        Ok(Some(CommandResult::try_create(queries, 0.0)?))
+    }
+}
+
+fn default_overview_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_overview_concurrency() -> usize {
+    4
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaOverviewParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    /// Time allowed for each table's statistics
+    #[serde(default = "default_overview_timeout_ms")]
+    timeout_ms: u64,
+    /// Tables queried at the same time
+    #[serde(default = "default_overview_concurrency")]
+    concurrency: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct TableOverview {
+    table: String,
+    #[serde(flatten)]
+    stats: TableStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Row counts and sizes of every table in the schema, biggest first, for a
+/// dashboard. Tables are queried concurrently and one that fails or times
+/// out is reported with an `error` instead of failing the overview.
+pub struct SchemaOverviewCommand;
+
+#[tower_lsp::async_trait]
+impl Command for SchemaOverviewCommand {
+    fn command(&self) -> &'static str {
+        SERVER_SCHEMA_OVERVIEW
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<SchemaOverviewParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let tables = pool.get_tables().await?;
+        let timeout = std::time::Duration::from_millis(req.timeout_ms);
+        let permits = tokio::sync::Semaphore::new(req.concurrency.max(1));
+
+        let overviews = tables.into_iter().map(|table| {
+            let (pool, permits) = (&pool, &permits);
+            async move {
+                let _permit = permits.acquire().await;
+                let stats = match tokio::time::timeout(timeout, pool.get_table_stats(&table)).await
+                {
+                    Ok(stats) => stats,
+                    Err(_) => Err(anyhow::anyhow!(
+                        "Timed out after {} ms",
+                        timeout.as_millis()
+                    )),
+                };
+                match stats {
+                    Ok(stats) => TableOverview {
+                        table,
+                        stats,
+                        error: None,
+                    },
+                    Err(e) => TableOverview {
+                        table,
+                        stats: TableStats::default(),
+                        error: Some(e.to_string()),
+                    },
+                }
+            }
+        });
+        let mut overviews = futures::future::join_all(overviews).await;
+        overviews.sort_by(|a, b| {
+            (b.stats.size_bytes, b.stats.rows).cmp(&(a.stats.size_bytes, a.stats.rows))
+        });
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        Ok(Some(CommandResult::try_create(overviews, execution_time)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DryRunParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    query: String,
+}
+
+/// Reports what a statement would do without running it: planner estimates
+/// for a query, the statement kind and written objects otherwise.
+pub struct DryRunCommand;
+
+#[tower_lsp::async_trait]
+impl Command for DryRunCommand {
+    fn command(&self) -> &'static str {
+        SERVER_DRY_RUN
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<DryRunParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let ast = SqlParser::new().with_recovery(false).parse(&req.query)?;
+        let statement = match ast.statements.as_slice() {
+            [statement] => statement,
+            _ => return Err(anyhow::anyhow!("Dry run expects a single statement")),
+        };
+
+        let result = if let Statement::Query(_) = statement {
+            let pool = req.connection.pool().await?;
+            let estimate = pool.explain_estimate(&req.query).await?;
+            json!({
+                "kind": "SELECT",
+                "estimate": estimate,
+            })
+        } else {
+            json!({
+                "kind": statement_kind(statement),
+                "objects": affected_objects(statement),
+            })
+        };
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        Ok(Some(CommandResult::try_create(result, execution_time)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NormalizeQueryParams {
+    query: String,
+}
+
+/// Returns a query re-rendered in canonical form and its fingerprint, the
+/// same text with literals replaced by `?`, for grouping history entries
+/// and matching them against server statistics.
+pub struct NormalizeQueryCommand;
+
+#[tower_lsp::async_trait]
+impl Command for NormalizeQueryCommand {
+    fn command(&self) -> &'static str {
+        SERVER_NORMALIZE_QUERY
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<NormalizeQueryParams>(&params)?;
+        let ast = SqlParser::new().with_recovery(false).parse(&req.query)?;
+        if ast.statements.is_empty() {
+            return Err(anyhow::anyhow!("Query has no statement"));
+        }
+        let normalized: Vec<String> = ast.statements.iter().map(|s| s.to_string()).collect();
+        let fingerprints: Vec<String> = ast.statements.iter().map(fingerprint).collect();
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "normalized": normalized.join(";\n"),
+                "fingerprint": fingerprints.join(";\n"),
+            }),
+            0.0,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplainPlanParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    query: String,
+    /// Execute the query to measure actual times, rows and buffers. Any
+    /// changes it makes are rolled back.
+    #[serde(default)]
+    analyze: bool,
+}
+
+/// Returns the plan of a query as a flat list of nodes with parent links
+/// and self times, ready to draw as a flamegraph. PostgreSQL only.
+pub struct ExplainPlanCommand;
+
+#[tower_lsp::async_trait]
+impl Command for ExplainPlanCommand {
+    fn command(&self) -> &'static str {
+        SERVER_EXPLAIN_PLAN
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ExplainPlanParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let plan = pool.explain_plan(&req.query, req.analyze).await?;
+        let entry = plan.get(0);
+        let timing = |key: &str| entry.and_then(|entry| entry.get(key)).cloned();
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "analyzed": req.analyze,
+                "planning_time": timing("Planning Time"),
+                "execution_time": timing("Execution Time"),
+                "nodes": explain::flatten_postgres_plan(&plan),
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionInfoParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    /// Report on this session's transaction instead of a pooled connection
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+const POSTGRES_LOCKS: &str = "SELECT l.pid::text AS pid, l.locktype, l.mode, l.granted, \
+     l.relation::regclass::text AS relation, a.usename::text AS username, a.state, a.query \
+     FROM pg_locks l LEFT JOIN pg_stat_activity a ON a.pid = l.pid \
+     WHERE l.pid <> pg_backend_pid() \
+     ORDER BY l.granted, l.pid";
+
+/// Reports the isolation level of a session and, on PostgreSQL, the locks
+/// currently held or awaited, to diagnose blocking.
+pub struct TransactionInfoCommand;
+
+/// Run a query in a session when there is one, on the pool otherwise.
+async fn query_on(
+    session: Option<&SharedSession>,
+    pool: &ConnectionPool,
+    sql: &str,
+    params: &[BindValue],
+) -> anyhow::Result<QueryOutput> {
+    match session {
+        Some(session) => {
+            session
+                .lock()
+                .await
+                .execute(sql, params, ResultKind::Rows)
+                .await
+        }
+        None => pool.execute_query(sql, params, ResultKind::Rows).await,
+    }
+}
+
+impl TransactionInfoCommand {
+    async fn query(
+        session: Option<&SharedSession>,
+        pool: &ConnectionPool,
+        sql: &str,
+    ) -> anyhow::Result<QueryOutput> {
+        query_on(session, pool, sql, &[]).await
+    }
+
+    fn first_value(output: &QueryOutput) -> Option<String> {
+        output
+            .rows
+            .get(0)?
+            .as_object()?
+            .values()
+            .next()?
+            .as_str()
+            .map(str::to_string)
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for TransactionInfoCommand {
+    fn command(&self) -> &'static str {
+        SERVER_TRANSACTION_INFO
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<TransactionInfoParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let session = req.session_id.as_deref().map(session::get).transpose()?;
+        let mut warnings = Vec::new();
+
+        let isolation_level = match pool.database_type() {
+            DatabaseType::PostgreSQL => {
+                let output = Self::query(
+                    session.as_ref(),
+                    &pool,
+                    "SELECT current_setting('transaction_isolation')",
+                )
+                .await?;
+                Self::first_value(&output)
+            }
+            DatabaseType::MySQL => {
+                let output =
+                    match Self::query(session.as_ref(), &pool, "SELECT @@transaction_isolation")
+                        .await
+                    {
+                        Ok(output) => output,
+                        // MySQL 5.7 之前只有 tx_isolation
+                        Err(_) => {
+                            Self::query(session.as_ref(), &pool, "SELECT @@tx_isolation").await?
+                        }
+                    };
+                Self::first_value(&output)
+            }
+            // SQLite 的事务总是可串行化的
+            DatabaseType::SQLite => Some("SERIALIZABLE".to_string()),
+        };
+
+        // 锁查询出错会中止会话中的事务，所以总是在连接池上执行
+        let locks = if pool.database_type() == DatabaseType::PostgreSQL {
+            match pool
+                .execute_query(POSTGRES_LOCKS, &[], ResultKind::Rows)
+                .await
+            {
+                Ok(output) => Some(output.rows),
+                Err(e) => {
+                    warnings.push(format!("Could not read lock information: {}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(
+            CommandResult::try_create(
+                json!({
+                    "isolation_level": isolation_level,
+                    "locks": locks,
+                }),
+                execution_time,
+            )?
+            .with_warnings(warnings),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerStateParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+}
+
+const MYSQL_STATEMENT_STATUS: &str = "SHOW GLOBAL STATUS WHERE Variable_name IN \
+     ('Prepared_stmt_count', 'Com_stmt_prepare', 'Com_stmt_execute', 'Com_stmt_close', \
+     'Com_stmt_reprepare', 'Threads_connected')";
+
+/// Reports prepared statement state to diagnose leaks, e.g. behind a
+/// connection pooler. PostgreSQL lists the statements prepared on one pooled
+/// connection, MySQL the server wide counters.
+pub struct ServerStateCommand;
+
+impl ServerStateCommand {
+    /// `SHOW STATUS` style rows as a name to value map.
+    fn variables(output: &QueryOutput) -> serde_json::Map<String, serde_json::Value> {
+        let rows = output
+            .rows
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        rows.iter()
+            .filter_map(|row| {
+                let name = row.get("Variable_name")?.as_str()?;
+                Some((name.to_string(), row.get("Value")?.clone()))
+            })
+            .collect()
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for ServerStateCommand {
+    fn command(&self) -> &'static str {
+        SERVER_SERVER_STATE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<ServerStateParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let mut warnings = Vec::new();
+
+        let result = match pool.database_type() {
+            DatabaseType::PostgreSQL => {
+                let statements = pool
+                    .execute_query(
+                        "SELECT name, statement, prepare_time::text AS prepare_time, \
+                         parameter_types::text AS parameter_types, from_sql \
+                         FROM pg_prepared_statements ORDER BY prepare_time",
+                        &[],
+                        ResultKind::Rows,
+                    )
+                    .await;
+                let statements = match statements {
+                    Ok(output) => output.rows,
+                    Err(e) => {
+                        warnings.push(format!("Could not read prepared statements: {}", e));
+                        serde_json::Value::Null
+                    }
+                };
+                json!({ "prepared_statements": statements })
+            }
+            DatabaseType::MySQL => {
+                let mut status = serde_json::Map::new();
+                for sql in [
+                    MYSQL_STATEMENT_STATUS,
+                    "SHOW GLOBAL VARIABLES LIKE 'max_prepared_stmt_count'",
+                ] {
+                    match pool.execute_query(sql, &[], ResultKind::Rows).await {
+                        Ok(output) => status.extend(Self::variables(&output)),
+                        Err(e) => warnings.push(format!("Could not read server status: {}", e)),
+                    }
+                }
+                json!({ "status": status })
+            }
+            DatabaseType::SQLite => {
+                return Err(anyhow::anyhow!("Server state is not available for SQLite"));
+            }
+        };
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(
+            CommandResult::try_create(result, execution_time)?.with_warnings(warnings),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GetServerVariablesParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    /// LIKE pattern the names must match, e.g. `%timeout%`
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(default = "default_max_variables")]
+    max_rows: usize,
+}
+
+/// Lists the settings of a connection with their values and, where the
+/// backend has them, descriptions, for a per-connection settings panel.
+pub struct GetServerVariablesCommand;
+
+#[tower_lsp::async_trait]
+impl Command for GetServerVariablesCommand {
+    fn command(&self) -> &'static str {
+        SERVER_GET_SERVER_VARIABLES
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<GetServerVariablesParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let mut variables = pool.get_server_variables(req.pattern.as_deref()).await?;
+        let truncated = variables.len() > req.max_rows;
+        variables.truncate(req.max_rows);
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "variables": variables,
+                "truncated": truncated,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LockAction {
+    #[default]
+    Status,
+    /// Take the lock if it is free, without waiting
+    Acquire,
+    Release,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryLockParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    /// Lock name, hashed to a lock key on PostgreSQL
+    name: String,
+    #[serde(default)]
+    action: LockAction,
+    /// Session owning the lock. Needed to acquire or release it, since an
+    /// advisory lock belongs to the connection that took it.
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// Sessions holding or waiting for the single bigint advisory lock
+/// `hashtext($1)`, which pg_locks splits into classid and objid.
+const POSTGRES_ADVISORY_HOLDERS: &str = "SELECT l.pid::text AS pid, l.granted, a.usename::text AS username, \
+     a.application_name, a.client_addr::text AS client_addr \
+     FROM pg_locks l LEFT JOIN pg_stat_activity a ON a.pid = l.pid \
+     WHERE l.locktype = 'advisory' AND l.objsubid = 1 \
+     AND l.classid::bigint = (hashtext($1)::bigint >> 32) & 4294967295 \
+     AND l.objid::bigint = hashtext($1)::bigint & 4294967295 \
+     ORDER BY l.granted DESC, l.pid";
+
+/// Reports who holds a named advisory lock and takes or releases it in a
+/// session, so tools can make sure only one person runs migrations at a
+/// time. PostgreSQL uses a transaction-level `pg_advisory_xact_lock` that
+/// goes away when the session ends. MySQL's `GET_LOCK` belongs to the
+/// connection, so the session can't end until it is released.
+pub struct AdvisoryLockStatusCommand;
+
+impl AdvisoryLockStatusCommand {
+    fn first(output: &QueryOutput, column: &str) -> serde_json::Value {
+        output
+            .rows
+            .get(0)
+            .and_then(|row| row.get(column))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// An integer result, numbers may be formatted as strings.
+    fn int(value: &serde_json::Value) -> Option<i64> {
+        match value {
+            serde_json::Value::String(s) => s.parse().ok(),
+            other => other.as_i64(),
+        }
+    }
+
+    /// Result of a lock function: a boolean on PostgreSQL, 1 or 0 on MySQL.
+    fn succeeded(value: &serde_json::Value) -> bool {
+        value
+            .as_bool()
+            .unwrap_or_else(|| Self::int(value) == Some(1))
+    }
+
+    async fn mysql_holders(
+        pool: &ConnectionPool,
+        name: &[BindValue],
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        let output = pool
+            .execute_query("SELECT IS_USED_LOCK(?) AS pid", name, ResultKind::Rows)
+            .await?;
+        let Some(pid) = Self::int(&Self::first(&output, "pid")) else {
+            return Ok(Vec::new());
+        };
+        // 没有 PROCESS 权限时看不到其他用户的连接，只返回连接 id
+        let process = pool
+            .execute_query(
+                "SELECT ID AS pid, USER AS username, HOST AS client_addr \
+                 FROM information_schema.PROCESSLIST WHERE ID = ?",
+                &[BindValue::Int(pid)],
+                ResultKind::Rows,
+            )
+            .await
+            .ok()
+            .and_then(|output| output.rows.get(0).cloned());
+        let mut holder = process.unwrap_or_else(|| json!({ "pid": pid }));
+        holder["granted"] = json!(true);
+        Ok(vec![holder])
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Command for AdvisoryLockStatusCommand {
+    fn command(&self) -> &'static str {
+        SERVER_ADVISORY_LOCK
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<AdvisoryLockParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let db_type = pool.database_type();
+        if db_type == DatabaseType::SQLite {
+            return Err(anyhow::anyhow!(
+                "Advisory locks are not available for SQLite"
+            ));
+        }
+        let session = req.session_id.as_deref().map(session::get).transpose()?;
+        if req.action != LockAction::Status && session.is_none() {
+            return Err(anyhow::anyhow!(
+                "A session_id is needed to {:?} a lock, the lock belongs to the session's connection",
+                req.action
+            ));
+        }
+        let name = [BindValue::Text(req.name.clone())];
+        let postgres = db_type == DatabaseType::PostgreSQL;
+
+        if req.action == LockAction::Release && postgres {
+            return Err(anyhow::anyhow!(
+                "PostgreSQL advisory locks are released when the session ends"
+            ));
+        }
+
+        let changed = match req.action {
+            LockAction::Status => None,
+            LockAction::Acquire | LockAction::Release => {
+                // 会话连接会回到连接池，PostgreSQL 只能使用事务级锁
+                let sql = match (req.action, postgres) {
+                    (LockAction::Acquire, true) => {
+                        "SELECT pg_try_advisory_xact_lock(hashtext($1)) AS result"
+                    }
+                    (LockAction::Acquire, false) => "SELECT GET_LOCK(?, 0) AS result",
+                    _ => "SELECT RELEASE_LOCK(?) AS result",
+                };
+                let output = query_on(session.as_ref(), &pool, sql, &name).await?;
+                let succeeded = Self::succeeded(&Self::first(&output, "result"));
+                // RELEASE_LOCK 返回 NULL 表示锁已不存在，同样不再记录
+                let held = req.action == LockAction::Acquire;
+                if !postgres
+                    && (succeeded || !held)
+                    && let Some(session_id) = &req.session_id
+                {
+                    session::track_lock(session_id, &req.name, held);
+                }
+                Some(succeeded)
+            }
+        };
+        let session_pid = match &session {
+            Some(session) => {
+                let sql = if postgres {
+                    "SELECT pg_backend_pid()::text AS pid"
+                } else {
+                    "SELECT CONNECTION_ID() AS pid"
+                };
+                let output = query_on(Some(session), &pool, sql, &[]).await?;
+                Self::int(&Self::first(&output, "pid"))
+            }
+            None => None,
+        };
+
+        // 查询失败会中止会话中的事务，所以在连接池上查询持有者
+        let mut holders = if postgres {
+            let output = pool
+                .execute_query(POSTGRES_ADVISORY_HOLDERS, &name, ResultKind::Rows)
+                .await?;
+            output.rows.as_array().cloned().unwrap_or_default()
+        } else {
+            Self::mysql_holders(&pool, &name).await?
+        };
+        for holder in &mut holders {
+            let pid = holder.get("pid").and_then(Self::int);
+            holder["owned_by_session"] = json!(session_pid.is_some() && pid == session_pid);
+        }
+        let held = holders
+            .iter()
+            .any(|holder| holder.get("granted").and_then(serde_json::Value::as_bool) == Some(true));
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "name": req.name,
+                "action": req.action,
+                "succeeded": changed,
+                "held": held,
+                "holders": holders,
+                "session_pid": session_pid,
+            }),
+            execution_time,
+        )?))
+    }
+}
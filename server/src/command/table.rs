@@ -0,0 +1,280 @@
+use serde::Deserialize;
+use serde_json::json;
+use sqlparser::ast::Statement;
+use tower_lsp::lsp_types::{ExecuteCommandParams, MessageType};
+
+use crate::{
+    constant::{
+        SERVER_CLONE_TABLE_STRUCTURE, SERVER_CREATE_TABLE_AS, SERVER_DROP_TABLE,
+        SERVER_IDENTITY_INFO, SERVER_MAINTAIN_TABLE, SERVER_RENAME_TABLE,
+    },
+    db::{self, connection::MaintenanceAction},
+    logger::log,
+    parser::{ResultKind, SqlParser},
+};
+
+use super::{Command, CommandResult, ConnectionParams, parse_arguments};
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    action: MaintenanceAction,
+}
+
+/// Runs VACUUM/ANALYZE/OPTIMIZE on a table.
+pub struct MaintenanceCommand;
+
+#[tower_lsp::async_trait]
+impl Command for MaintenanceCommand {
+    fn command(&self) -> &'static str {
+        SERVER_MAINTAIN_TABLE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<MaintenanceParams>(&params)?;
+        log(
+            MessageType::INFO,
+            format!("Running {:?} on table: {}", req.action, req.table),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let messages = pool.maintain_table(&req.table, req.action).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "result": true,
+                "messages": messages,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DropTableParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+    /// Must be true, the client is expected to have asked the user first
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Drops a table once the client has confirmed the action.
+pub struct DropTableCommand;
+
+#[tower_lsp::async_trait]
+impl Command for DropTableCommand {
+    fn command(&self) -> &'static str {
+        SERVER_DROP_TABLE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<DropTableParams>(&params)?;
+        if !req.confirm {
+            return Err(anyhow::anyhow!(
+                "Dropping table {} requires confirmation, ask the user and resend with confirm: true",
+                req.table
+            ));
+        }
+        log(MessageType::INFO, format!("Dropping table: {}", req.table));
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        let existed = pool.drop_table(&req.table).await?;
+        db::cache::clear(Some(&req.connection.connection_id));
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "result": true,
+                "existed": existed,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CloneTableStructureParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    source: String,
+    target: String,
+    /// Also copy the rows of `source`
+    #[serde(default)]
+    include_data: bool,
+}
+
+/// Creates a new table with the structure of an existing one.
+pub struct CloneTableStructureCommand;
+
+#[tower_lsp::async_trait]
+impl Command for CloneTableStructureCommand {
+    fn command(&self) -> &'static str {
+        SERVER_CLONE_TABLE_STRUCTURE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<CloneTableStructureParams>(&params)?;
+        log(
+            MessageType::INFO,
+            format!("Cloning table {} to {}", req.source, req.target),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        pool.clone_table_structure(&req.source, &req.target).await?;
+
+        let mut copied = None;
+        if req.include_data {
+            let dialect = pool.native_dialect();
+            let sql = format!(
+                "INSERT INTO {} SELECT * FROM {}",
+                dialect.quote_ident(&req.target),
+                dialect.quote_ident(&req.source)
+            );
+            let output = pool.execute_query(&sql, &[], ResultKind::Affected).await?;
+            copied = Some(output.total);
+        }
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "result": true,
+                "copied": copied,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameTableParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    old_name: String,
+    new_name: String,
+}
+
+/// Renames a table.
+pub struct RenameTableCommand;
+
+#[tower_lsp::async_trait]
+impl Command for RenameTableCommand {
+    fn command(&self) -> &'static str {
+        SERVER_RENAME_TABLE
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<RenameTableParams>(&params)?;
+        if !db::is_simple_identifier(&req.new_name) {
+            return Err(anyhow::anyhow!("Invalid table name: {}", req.new_name));
+        }
+        log(
+            MessageType::INFO,
+            format!("Renaming table {} to {}", req.old_name, req.new_name),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        pool.rename_table(&req.old_name, &req.new_name).await?;
+        db::schema::invalidate(&req.connection.connection_id).await;
+        db::cache::clear(Some(&req.connection.connection_id));
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({ "result": true }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateTableAsParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    query: String,
+    target: String,
+}
+
+/// Materializes a query result as a new table.
+pub struct CreateTableAsCommand;
+
+#[tower_lsp::async_trait]
+impl Command for CreateTableAsCommand {
+    fn command(&self) -> &'static str {
+        SERVER_CREATE_TABLE_AS
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let req = parse_arguments::<CreateTableAsParams>(&params)?;
+        let ast = SqlParser::new().with_recovery(false).parse(&req.query)?;
+        if !matches!(ast.statements.as_slice(), [Statement::Query(_)]) {
+            return Err(anyhow::anyhow!("The source must be a single SELECT query"));
+        }
+        log(
+            MessageType::INFO,
+            format!("Creating table {} from query: {}", req.target, req.query),
+        );
+
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        pool.create_table_as(&req.target, &req.query).await?;
+        db::schema::invalidate(&req.connection.connection_id).await;
+        db::cache::clear(Some(&req.connection.connection_id));
+        let rows = pool.count_rows(&req.target).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "table": req.target,
+                "rows": rows,
+            }),
+            execution_time,
+        )?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityInfoParams {
+    #[serde(flatten)]
+    connection: ConnectionParams,
+    table: String,
+}
+
+/// Reports a table's auto-increment or identity column and the next value
+/// it hands out, so the grid can show the next id. The fields are null
+/// when the table has no such column.
+pub struct IdentityInfoCommand;
+
+#[tower_lsp::async_trait]
+impl Command for IdentityInfoCommand {
+    fn command(&self) -> &'static str {
+        SERVER_IDENTITY_INFO
+    }
+
+    async fn handler(&self, params: ExecuteCommandParams) -> anyhow::Result<Option<CommandResult>> {
+        let mut req = parse_arguments::<IdentityInfoParams>(&params)?;
+        let start_time = std::time::Instant::now();
+        let pool = req.connection.pool().await?;
+        req.table = req.connection.resolve_table(&pool, &req.table).await?;
+        let identity = pool.get_identity(&req.table).await?;
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(Some(CommandResult::try_create(
+            json!({
+                "table": req.table,
+                "has_identity": identity.column.is_some(),
+                "column": identity.column,
+                "next_value": identity.next_value,
+            }),
+            execution_time,
+        )?))
+    }
+}
@@ -0,0 +1,308 @@
+use std::sync::Arc;
+
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, InsertTextFormat, MarkupContent, MarkupKind,
+};
+
+use crate::{db::schema::SchemaInfo, parser::CompletionContext, signature::FUNCTIONS};
+
+const KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "JOIN", "LEFT", "RIGHT", "INNER", "OUTER", "GROUP BY", "ORDER BY",
+    "HAVING", "LIMIT", "OFFSET", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "TABLE",
+    "INDEX", "VIEW", "AS",
+];
+
+/// Filter items to those fuzzily matching the word being typed, best match
+/// first, keeping at most `max` (zero for no limit). Returns whether items
+/// were left out, so the client asks again as the word grows.
+pub fn rank(items: Vec<CompletionItem>, word: &str, max: usize) -> (Vec<CompletionItem>, bool) {
+    let mut scored: Vec<(i64, CompletionItem)> = items
+        .into_iter()
+        .filter_map(|item| fuzzy_score(&item.label, word).map(|score| (score, item)))
+        .collect();
+    // 分数相同时保持原来的顺序
+    scored.sort_by_key(|(score, _)| -score);
+    let truncated = max > 0 && scored.len() > max;
+    if truncated {
+        scored.truncate(max);
+    }
+    let items = scored
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (_, mut item))| {
+            item.sort_text = Some(format!("{:05}", rank));
+            if item.filter_text.is_none() {
+                item.filter_text = Some(item.label.clone());
+            }
+            item
+        })
+        .collect();
+    (items, truncated)
+}
+
+/// How well `label` matches the typed `word` as a case-insensitive
+/// subsequence, None when it doesn't. Prefix matches, consecutive
+/// characters and matches at word boundaries score higher.
+fn fuzzy_score(label: &str, word: &str) -> Option<i64> {
+    if word.is_empty() {
+        return Some(0);
+    }
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let word: Vec<char> = word.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut is_prefix = true;
+    let mut next = 0;
+    for c in word {
+        let index = (next..label.len()).find(|&i| label[i] == c)?;
+        score += 10;
+        if index > 0 && index == next {
+            score += 15;
+        }
+        if index == 0 || matches!(label[index - 1], '_' | '.' | ' ') {
+            score += 20;
+        }
+        if index > next {
+            is_prefix = false;
+            score -= (index - next) as i64;
+        }
+        next = index + 1;
+    }
+    if is_prefix {
+        score += 100;
+    }
+    // 更短的候选更可能是想要的
+    Some(score - label.len() as i64)
+}
+
+/// Build completion items for a context from the schemas of all known connections.
+pub fn completion_items(
+    context: &CompletionContext,
+    schemas: &[(String, Arc<SchemaInfo>)],
+) -> Vec<CompletionItem> {
+    match context {
+        CompletionContext::TableName => table_items(schemas),
+        CompletionContext::ColumnName(table_name) => column_items(table_name, schemas),
+        CompletionContext::SchemaQualified(schema_name) => schema_table_items(schema_name, schemas),
+        CompletionContext::AfterTable { table, alias } => {
+            let mut items = join_items(table, alias.as_deref(), schemas);
+            items.extend(keyword_items());
+            items
+        }
+        CompletionContext::InsertColumnList { table, listed } => {
+            let mut items = column_items(table, schemas);
+            items.retain(|item| !listed.iter().any(|c| c.eq_ignore_ascii_case(&item.label)));
+            items
+        }
+        CompletionContext::None => {
+            let mut items = keyword_items();
+            items.extend(function_items());
+            items
+        }
+    }
+}
+
+fn table_items(schemas: &[(String, Arc<SchemaInfo>)]) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for (conn_id, schema) in schemas {
+        for (table_name, table_info) in &schema.tables {
+            items.push(CompletionItem {
+                label: table_name.clone(),
+                kind: Some(CompletionItemKind::CLASS),
+                detail: Some(format!("Table ({conn_id})")),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::MARKDOWN,
+                    value: format!(
+                        "### Table: {}\n\nColumns:\n{}",
+                        table_name,
+                        table_info
+                            .columns
+                            .iter()
+                            .map(|c| format!("- **{}**", c))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    ),
+                })),
+                ..Default::default()
+            });
+        }
+    }
+    items
+}
+
+/// Tables of one schema, matching its name case-insensitively as a fallback.
+fn schema_table_items(
+    schema_name: &str,
+    schemas: &[(String, Arc<SchemaInfo>)],
+) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for (_, schema) in schemas {
+        let tables = schema.schemas.get(schema_name).or_else(|| {
+            schema
+                .schemas
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(schema_name))
+                .map(|(_, tables)| tables)
+        });
+        for table in tables.into_iter().flatten() {
+            items.push(CompletionItem {
+                label: table.clone(),
+                kind: Some(CompletionItemKind::CLASS),
+                detail: Some(format!("Table ({})", schema_name)),
+                ..Default::default()
+            });
+        }
+    }
+    items
+}
+
+fn column_items(table_name: &str, schemas: &[(String, Arc<SchemaInfo>)]) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for (_, schema) in schemas {
+        if let Some(table) = schema.table(table_name) {
+            for column in &table.columns {
+                items.push(CompletionItem {
+                    label: column.clone(),
+                    kind: Some(CompletionItemKind::FIELD),
+                    detail: Some(format!("Column ({})", table_name)),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    items
+}
+
+/// JOIN snippets pre-filled from foreign keys in either direction.
+fn join_items(
+    table: &str,
+    alias: Option<&str>,
+    schemas: &[(String, Arc<SchemaInfo>)],
+) -> Vec<CompletionItem> {
+    let source = alias.unwrap_or(table);
+    // (joined table, joined column, source column)
+    let mut joins = Vec::new();
+    for (_, schema) in schemas {
+        if let Some(info) = schema.table(table) {
+            for fk in &info.foreign_keys {
+                joins.push((
+                    fk.referenced_table.clone(),
+                    fk.referenced_column.clone(),
+                    fk.column.clone(),
+                ));
+            }
+        }
+        for (other, info) in &schema.tables {
+            for fk in info
+                .foreign_keys
+                .iter()
+                .filter(|fk| fk.referenced_table.eq_ignore_ascii_case(table))
+            {
+                joins.push((
+                    other.clone(),
+                    fk.column.clone(),
+                    fk.referenced_column.clone(),
+                ));
+            }
+        }
+    }
+    joins.sort();
+    joins.dedup();
+
+    if joins.is_empty() {
+        return vec![join_snippet(
+            "JOIN … ON …".to_string(),
+            "JOIN ${1:other_table} ON ${2:a.id} = ${3:b.id}".to_string(),
+        )];
+    }
+
+    joins
+        .into_iter()
+        .map(|(other, other_column, source_column)| {
+            join_snippet(
+                format!(
+                    "JOIN {} ON {}.{} = {}.{}",
+                    other, other, other_column, source, source_column
+                ),
+                format!(
+                    "JOIN ${{1:{}}} ON ${{2:{}.{}}} = ${{3:{}.{}}}",
+                    other, other, other_column, source, source_column
+                ),
+            )
+        })
+        .collect()
+}
+
+fn join_snippet(label: String, snippet: String) -> CompletionItem {
+    CompletionItem {
+        label,
+        kind: Some(CompletionItemKind::SNIPPET),
+        insert_text: Some(snippet),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
+/// Known functions, inserted with the cursor between the parentheses.
+fn function_items() -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = Vec::new();
+    for function in FUNCTIONS {
+        if items.iter().any(|item| item.label == function.name) {
+            continue;
+        }
+        items.push(CompletionItem {
+            label: function.name.to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some(function.description.to_string()),
+            insert_text: Some(format!("{}($0)", function.name)),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        });
+    }
+    items
+}
+
+fn keyword_items() -> Vec<CompletionItem> {
+    KEYWORDS
+        .iter()
+        .map(|kw| CompletionItem {
+            label: kw.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let items = vec![
+            item("order_items"),
+            item("users"),
+            item("user_roles"),
+            item("audit_log"),
+        ];
+        let (ranked, truncated) = rank(items.clone(), "usr", 0);
+        let labels: Vec<&str> = ranked.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["users", "user_roles"]);
+        assert!(!truncated);
+        assert_eq!(ranked[0].sort_text.as_deref(), Some("00000"));
+
+        // 前缀匹配排在子序列匹配之前
+        assert!(fuzzy_score("roles", "ro") > fuzzy_score("user_roles", "ro"));
+        assert_eq!(fuzzy_score("audit_log", "usr"), None);
+
+        let (ranked, truncated) = rank(items, "", 2);
+        assert_eq!(ranked.len(), 2);
+        assert!(truncated);
+    }
+}
@@ -1,3 +1,56 @@
 pub const SERVER_EXECUTE_COMMAND: &str = "dbviewer.server.executeCommand";
 pub const SERVER_CHECK_CONNECTION: &str = "dbviewer.server.checkConnection";
 pub const CLIENT_EXECUTE_COMMAND: &str = "dbviewer.execute";
+pub const SERVER_GET_TYPES: &str = "dbviewer.server.getTypes";
+pub const SERVER_MAINTAIN_TABLE: &str = "dbviewer.server.maintainTable";
+pub const SERVER_GET_TRIGGERS: &str = "dbviewer.server.getTriggers";
+pub const SERVER_CREATE_DATABASE: &str = "dbviewer.server.createDatabase";
+pub const SERVER_DROP_TABLE: &str = "dbviewer.server.dropTable";
+pub const SERVER_OBJECT_EXISTS: &str = "dbviewer.server.objectExists";
+pub const SERVER_RUN_STATEMENT_AT: &str = "dbviewer.server.runStatementAt";
+pub const SERVER_DUMP_SCHEMA: &str = "dbviewer.server.dumpSchema";
+pub const SERVER_GENERATE_SELECT: &str = "dbviewer.server.generateSelect";
+pub const SERVER_GET_ROWS_BY_KEYS: &str = "dbviewer.server.getRowsByKeys";
+pub const SERVER_BUILD_CONNECTION_STRING: &str = "dbviewer.server.buildConnectionString";
+pub const SERVER_EXECUTE_TRANSACTION: &str = "dbviewer.server.executeTransaction";
+pub const SERVER_SLOW_QUERIES: &str = "dbviewer.server.slowQueries";
+pub const SERVER_SET_DOCUMENT_CONNECTION: &str = "dbviewer.server.setDocumentConnection";
+pub const SERVER_GET_COLUMN_INFO: &str = "dbviewer.server.getColumnInfo";
+pub const SERVER_CLONE_TABLE_STRUCTURE: &str = "dbviewer.server.cloneTableStructure";
+pub const SERVER_RENAME_TABLE: &str = "dbviewer.server.renameTable";
+pub const SERVER_FETCH_BLOB: &str = "dbviewer.server.fetchBlob";
+pub const SERVER_RUN_RANGE: &str = "dbviewer.server.runRange";
+pub const SERVER_EXPORT_TO_FILE: &str = "dbviewer.server.exportToFile";
+pub const SERVER_GET_VIEW_DEFINITION: &str = "dbviewer.server.getViewDefinition";
+pub const SERVER_LIST_VIEWS: &str = "dbviewer.server.listViews";
+pub const SERVER_DRY_RUN: &str = "dbviewer.server.dryRun";
+pub const SERVER_GET_PRIVILEGES: &str = "dbviewer.server.getPrivileges";
+pub const SERVER_BEGIN_SESSION: &str = "dbviewer.server.beginSession";
+pub const SERVER_END_SESSION: &str = "dbviewer.server.endSession";
+pub const SERVER_FETCH_CELL: &str = "dbviewer.server.fetchCell";
+pub const SERVER_DIFF_SCHEMA: &str = "dbviewer.server.diffSchema";
+pub const SERVER_CLEAR_RESULT_CACHE: &str = "dbviewer.server.clearResultCache";
+pub const SERVER_LIST_SEQUENCES: &str = "dbviewer.server.listSequences";
+pub const SERVER_CHECK_CONNECTIONS: &str = "dbviewer.server.checkConnections";
+pub const SERVER_CREATE_TABLE_AS: &str = "dbviewer.server.createTableAs";
+pub const SERVER_RUN_MACRO: &str = "dbviewer.server.runMacro";
+pub const SERVER_TRANSACTION_INFO: &str = "dbviewer.server.transactionInfo";
+pub const SERVER_GET_RECENT_ROWS: &str = "dbviewer.server.getRecentRows";
+pub const SERVER_RENAME_SCHEMA: &str = "dbviewer.server.renameSchema";
+pub const SERVER_SERVER_STATE: &str = "dbviewer.server.serverState";
+pub const SERVER_VALIDATE_CONNECTION: &str = "dbviewer.server.validateConnection";
+pub const SERVER_EXPLAIN_PLAN: &str = "dbviewer.server.explainPlan";
+pub const SERVER_IDENTITY_INFO: &str = "dbviewer.server.identityInfo";
+pub const SERVER_PIVOT: &str = "dbviewer.server.pivot";
+pub const SERVER_GET_CHECK_CONSTRAINTS: &str = "dbviewer.server.getCheckConstraints";
+pub const SERVER_EXPORT_STREAM: &str = "dbviewer.server.exportStream";
+pub const SERVER_GET_TABLE_CONSTRAINTS_DDL: &str = "dbviewer.server.getTableConstraintsDdl";
+pub const SERVER_GET_SERVER_VARIABLES: &str = "dbviewer.server.getServerVariables";
+pub const SERVER_COLUMN_PROFILE: &str = "dbviewer.server.columnProfile";
+pub const SERVER_ADVISORY_LOCK: &str = "dbviewer.server.advisoryLock";
+pub const SERVER_GENERATE_INSERTS: &str = "dbviewer.server.generateInserts";
+pub const SERVER_UPDATE_PREVIEW: &str = "dbviewer.server.updatePreview";
+pub const SERVER_GET_PARTITIONS: &str = "dbviewer.server.getPartitions";
+pub const SERVER_NORMALIZE_QUERY: &str = "dbviewer.server.normalizeQuery";
+pub const SERVER_GET_RELATED_ROWS: &str = "dbviewer.server.getRelatedRows";
+pub const SERVER_SCHEMA_OVERVIEW: &str = "dbviewer.server.schemaOverview";
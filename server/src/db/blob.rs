@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// How long a stored value can be fetched before it is dropped.
+const BLOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Total size of the stored values, the least recently used are dropped
+/// beyond it.
+const MAX_BLOB_BYTES: usize = 256 * 1024 * 1024;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+static BLOBS: once_cell::sync::Lazy<Mutex<HashMap<String, Blob>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Blob {
+    bytes: Vec<u8>,
+    stored_at: Instant,
+    used_at: Instant,
+}
+
+/// Drop expired values, then the least recently used ones until the rest
+/// fit in `max_bytes`. `keep` is never dropped for size.
+fn evict(blobs: &mut HashMap<String, Blob>, max_bytes: usize, keep: &str) {
+    blobs.retain(|_, blob| blob.stored_at.elapsed() < BLOB_TTL);
+    let mut total: usize = blobs.values().map(|blob| blob.bytes.len()).sum();
+    if total <= max_bytes {
+        return;
+    }
+    let mut by_use: Vec<(Instant, String)> = blobs
+        .iter()
+        .filter(|(id, _)| id.as_str() != keep)
+        .map(|(id, blob)| (blob.used_at, id.clone()))
+        .collect();
+    by_use.sort();
+    for (_, id) in by_use {
+        if total <= max_bytes {
+            break;
+        }
+        if let Some(blob) = blobs.remove(&id) {
+            total -= blob.bytes.len();
+        }
+    }
+}
+
+/// Keep a binary value out of the result payload, returning the id to fetch
+/// it with. Expired values are dropped on the way, and the least recently
+/// used ones once the store holds more than [`MAX_BLOB_BYTES`].
+pub fn store(bytes: Vec<u8>) -> String {
+    let id = format!("blob-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    if let Ok(mut blobs) = BLOBS.lock() {
+        let now = Instant::now();
+        blobs.insert(
+            id.clone(),
+            Blob {
+                bytes,
+                stored_at: now,
+                used_at: now,
+            },
+        );
+        evict(&mut blobs, MAX_BLOB_BYTES, &id);
+    }
+    id
+}
+
+/// Bytes of a stored value, None once it expired or was evicted.
+pub fn get(id: &str) -> Option<Vec<u8>> {
+    let mut blobs = BLOBS.lock().ok()?;
+    blobs.retain(|_, blob| blob.stored_at.elapsed() < BLOB_TTL);
+    let blob = blobs.get_mut(id)?;
+    blob.used_at = Instant::now();
+    Some(blob.bytes.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_get() {
+        let id = store(vec![1, 2, 3]);
+        assert_eq!(get(&id), Some(vec![1, 2, 3]));
+        assert_ne!(store(vec![4]), id);
+        assert_eq!(get("blob-unknown"), None);
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let now = Instant::now();
+        let blob = |size: usize, used: u64| Blob {
+            bytes: vec![0; size],
+            stored_at: now,
+            used_at: now + Duration::from_secs(used),
+        };
+        let mut blobs = HashMap::from([
+            ("a".to_string(), blob(4, 2)),
+            ("b".to_string(), blob(4, 1)),
+            ("c".to_string(), blob(4, 0)),
+        ]);
+        evict(&mut blobs, 5, "c");
+        let mut left: Vec<&String> = blobs.keys().collect();
+        left.sort();
+        assert_eq!(left, vec!["c"]);
+    }
+}
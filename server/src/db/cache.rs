@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use sqlparser::{
+    dialect::GenericDialect,
+    tokenizer::{Token, Tokenizer, Whitespace},
+};
+
+use super::connection::{BindValue, QueryOutput};
+
+static RESULTS: once_cell::sync::Lazy<Mutex<HashMap<CacheKey, Entry>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Result cache for read queries, sent as the `result_cache` init option.
+/// Nothing is cached unless `enabled` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ResultCacheSettings {
+    pub enabled: bool,
+    /// Seconds a cached result is served for
+    pub ttl_secs: u64,
+    /// Maximum number of cached results, the oldest is dropped first
+    pub max_entries: usize,
+}
+
+impl Default for ResultCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 60,
+            max_entries: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    connection_id: String,
+    /// Query with runs of whitespace collapsed, followed by the parameters
+    query: String,
+}
+
+impl CacheKey {
+    pub fn new(connection_id: &str, query: &str, params: &[BindValue]) -> Self {
+        let mut query = normalize(query);
+        if !params.is_empty() {
+            query.push_str(&format!(" -- {:?}", params));
+        }
+        Self {
+            connection_id: connection_id.to_string(),
+            query,
+        }
+    }
+}
+
+struct Entry {
+    output: QueryOutput,
+    stored_at: Instant,
+}
+
+/// Collapse whitespace between tokens so reformatting a query doesn't miss
+/// the cache. Literals and quoted identifiers are kept as written.
+fn normalize(query: &str) -> String {
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, query).tokenize() else {
+        return query.trim().to_string();
+    };
+    let mut normalized = String::new();
+    let mut space = false;
+    for token in &tokens {
+        match token {
+            Token::Whitespace(Whitespace::Space | Whitespace::Newline | Whitespace::Tab) => {
+                space = true;
+            }
+            token => {
+                if space && !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                space = false;
+                normalized.push_str(&token.to_string());
+            }
+        }
+    }
+    normalized.trim_end_matches(';').trim_end().to_string()
+}
+
+/// A cached result that hasn't expired.
+pub fn get(key: &CacheKey, settings: &ResultCacheSettings) -> Option<QueryOutput> {
+    let results = RESULTS.lock().ok()?;
+    results
+        .get(key)
+        .filter(|entry| entry.stored_at.elapsed() < Duration::from_secs(settings.ttl_secs))
+        .map(|entry| entry.output.clone())
+}
+
+pub fn put(key: CacheKey, output: &QueryOutput, settings: &ResultCacheSettings) {
+    let Ok(mut results) = RESULTS.lock() else {
+        return;
+    };
+    let ttl = Duration::from_secs(settings.ttl_secs);
+    results.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+    while !results.is_empty() && results.len() >= settings.max_entries {
+        let oldest = results
+            .iter()
+            .min_by_key(|(_, entry)| entry.stored_at)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            results.remove(&oldest);
+        }
+    }
+    if settings.max_entries > 0 {
+        results.insert(
+            key,
+            Entry {
+                output: output.clone(),
+                stored_at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// Drop the cached results of one connection, or of all connections.
+/// Returns how many were dropped.
+pub fn clear(connection_id: Option<&str>) -> usize {
+    let Ok(mut results) = RESULTS.lock() else {
+        return 0;
+    };
+    let before = results.len();
+    match connection_id {
+        Some(id) => results.retain(|key, _| key.connection_id != id),
+        None => results.clear(),
+    }
+    before - results.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(
+            CacheKey::new("c", "SELECT *\n  FROM users;", &[]),
+            CacheKey::new("c", "SELECT * FROM users", &[])
+        );
+        // 字符串里的空白是值的一部分
+        assert_ne!(
+            CacheKey::new("c", "SELECT * FROM users WHERE name = 'a  b'", &[]),
+            CacheKey::new("c", "SELECT * FROM users WHERE name = 'a b'", &[])
+        );
+        assert_ne!(
+            CacheKey::new("c", "SELECT \"a  b\" FROM users", &[]),
+            CacheKey::new("c", "SELECT \"a b\" FROM users", &[])
+        );
+        assert_ne!(
+            CacheKey::new(
+                "c",
+                "SELECT * FROM users WHERE id = ?",
+                &[BindValue::Int(1)]
+            ),
+            CacheKey::new(
+                "c",
+                "SELECT * FROM users WHERE id = ?",
+                &[BindValue::Int(2)]
+            )
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+
+use crate::settings;
+
+use super::DatabaseType;
+
+/// Quoting rules used when generating SQL for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    MySql,
+    Postgres,
+    Sqlite,
+    /// Standard SQL: double-quoted identifiers, no backslash escapes
+    Ansi,
+}
+
+impl Dialect {
+    /// Dialect for SQL returned to the client, honouring the `dialect` init
+    /// option. SQL the server runs itself uses `Dialect::from` instead.
+    pub fn for_type(db_type: &DatabaseType) -> Self {
+        settings::get()
+            .dialect
+            .unwrap_or_else(|| Dialect::from(db_type))
+    }
+
+    /// Quote an identifier, escaping any embedded quote characters.
+    pub fn quote_ident(&self, ident: &str) -> String {
+        match self {
+            Dialect::MySql => format!("`{}`", ident.replace('`', "``")),
+            Dialect::Postgres | Dialect::Sqlite | Dialect::Ansi => {
+                format!("\"{}\"", ident.replace('"', "\"\""))
+            }
+        }
+    }
+
+    /// Quote a string literal, escaping embedded quotes.
+    pub fn quote_literal(&self, value: &str) -> String {
+        match self {
+            // MySQL also treats backslash as an escape character
+            Dialect::MySql => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''")),
+            Dialect::Postgres | Dialect::Sqlite | Dialect::Ansi => {
+                format!("'{}'", value.replace('\'', "''"))
+            }
+        }
+    }
+}
+
+impl From<&DatabaseType> for Dialect {
+    fn from(db_type: &DatabaseType) -> Self {
+        match db_type {
+            DatabaseType::MySQL => Dialect::MySql,
+            DatabaseType::PostgreSQL => Dialect::Postgres,
+            DatabaseType::SQLite => Dialect::Sqlite,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(Dialect::MySql.quote_ident("a`b"), "`a``b`");
+        assert_eq!(Dialect::Postgres.quote_ident("a\"b"), "\"a\"\"b\"");
+        assert_eq!(Dialect::Ansi.quote_ident("users"), "\"users\"");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(Dialect::MySql.quote_literal("it's \\"), "'it''s \\\\'");
+        assert_eq!(Dialect::Sqlite.quote_literal("it's \\"), "'it''s \\'");
+    }
+}
@@ -0,0 +1,247 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Planner estimates for a query, see
+/// [`DatabaseOperations::explain_estimate`](super::connection::DatabaseOperations::explain_estimate).
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct QueryEstimate {
+    /// Rows the planner expects to read, None when the backend has no
+    /// estimate
+    pub rows: Option<f64>,
+    /// Total cost in the planner's own units
+    pub cost: Option<f64>,
+}
+
+/// Estimate from the output of PostgreSQL's `EXPLAIN (FORMAT JSON)`, read
+/// from the top plan node.
+pub fn from_postgres_plan(plan: &Value) -> QueryEstimate {
+    let node = plan
+        .get(0)
+        .and_then(|entry| entry.get("Plan"))
+        .unwrap_or(&Value::Null);
+    QueryEstimate {
+        rows: node.get("Plan Rows").and_then(Value::as_f64),
+        cost: node.get("Total Cost").and_then(Value::as_f64),
+    }
+}
+
+/// One node of a plan tree flattened in depth-first order, for drawing the
+/// plan as a flamegraph.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanNode {
+    /// Index of the node in the flat list
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub node_type: String,
+    /// Table, index or CTE the node reads, when it reads one
+    pub relation: Option<String>,
+    /// Planner cost of the node including its children
+    pub total_cost: Option<f64>,
+    /// Cost of the node alone, without its children
+    pub self_cost: Option<f64>,
+    pub plan_rows: Option<f64>,
+    /// Milliseconds spent in the node and its children over all loops,
+    /// only with ANALYZE
+    pub total_time: Option<f64>,
+    /// Milliseconds spent in the node alone
+    pub self_time: Option<f64>,
+    /// Rows returned over all loops, only with ANALYZE
+    pub actual_rows: Option<f64>,
+    pub loops: Option<f64>,
+    /// Blocks found in and read into the buffer cache, only with BUFFERS
+    pub shared_hit_blocks: Option<f64>,
+    pub shared_read_blocks: Option<f64>,
+}
+
+/// Flatten the output of PostgreSQL's `EXPLAIN (FORMAT JSON)`, with or
+/// without ANALYZE, into a list of nodes with parent links.
+pub fn flatten_postgres_plan(plan: &Value) -> Vec<PlanNode> {
+    let mut nodes = Vec::new();
+    if let Some(root) = plan.get(0).and_then(|entry| entry.get("Plan")) {
+        flatten_node(root, None, &mut nodes);
+    }
+    nodes
+}
+
+fn flatten_node(node: &Value, parent: Option<usize>, nodes: &mut Vec<PlanNode>) -> usize {
+    let id = nodes.len();
+    let loops = node.get("Actual Loops").and_then(Value::as_f64);
+    // 实际时间和行数是每次循环的平均值，乘以循环次数得到总量
+    let per_loop = |key: &str| {
+        node.get(key)
+            .and_then(Value::as_f64)
+            .map(|value| value * loops.unwrap_or(1.0))
+    };
+    let relation = ["Relation Name", "Index Name", "CTE Name", "Function Name"]
+        .iter()
+        .find_map(|key| node.get(*key).and_then(Value::as_str))
+        .map(|name| match node.get("Schema").and_then(Value::as_str) {
+            Some(schema) => format!("{}.{}", schema, name),
+            None => name.to_string(),
+        });
+    let total_cost = node.get("Total Cost").and_then(Value::as_f64);
+    let total_time = per_loop("Actual Total Time");
+    nodes.push(PlanNode {
+        id,
+        parent,
+        node_type: node
+            .get("Node Type")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown")
+            .to_string(),
+        relation,
+        total_cost,
+        self_cost: total_cost,
+        plan_rows: node.get("Plan Rows").and_then(Value::as_f64),
+        total_time,
+        self_time: total_time,
+        actual_rows: per_loop("Actual Rows"),
+        loops,
+        shared_hit_blocks: node.get("Shared Hit Blocks").and_then(Value::as_f64),
+        shared_read_blocks: node.get("Shared Read Blocks").and_then(Value::as_f64),
+    });
+
+    let (mut children_cost, mut children_time) = (0.0, 0.0);
+    for child in node
+        .get("Plans")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let child = flatten_node(child, Some(id), nodes);
+        children_cost += nodes[child].total_cost.unwrap_or(0.0);
+        children_time += nodes[child].total_time.unwrap_or(0.0);
+    }
+    // 并行节点的子节点时间可能超过父节点，不让自身时间为负
+    let node = &mut nodes[id];
+    node.self_cost = node.total_cost.map(|cost| (cost - children_cost).max(0.0));
+    node.self_time = node.total_time.map(|time| (time - children_time).max(0.0));
+    id
+}
+
+/// Estimate from the output of MySQL's `EXPLAIN FORMAT=JSON`. The row count
+/// adds up the rows examined per scan of every table in the plan.
+pub fn from_mysql_plan(plan: &Value) -> QueryEstimate {
+    let block = plan.get("query_block").unwrap_or(&Value::Null);
+    let mut rows = Vec::new();
+    collect_examined_rows(block, &mut rows);
+    QueryEstimate {
+        rows: (!rows.is_empty()).then(|| rows.iter().sum()),
+        cost: block
+            .get("cost_info")
+            .and_then(|info| info.get("query_cost"))
+            .and_then(number),
+    }
+}
+
+fn collect_examined_rows(value: &Value, rows: &mut Vec<f64>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if key == "rows_examined_per_scan" {
+                    rows.extend(number(value));
+                } else {
+                    collect_examined_rows(value, rows);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_examined_rows(item, rows)),
+        _ => {}
+    }
+}
+
+/// MySQL reports costs as strings, e.g. `"query_cost": "1.25"`.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        other => other.as_f64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_postgres_plan() {
+        let plan =
+            json!([{"Plan": {"Node Type": "Seq Scan", "Total Cost": 35.5, "Plan Rows": 2550}}]);
+        assert_eq!(
+            from_postgres_plan(&plan),
+            QueryEstimate {
+                rows: Some(2550.0),
+                cost: Some(35.5),
+            }
+        );
+    }
+
+    #[test]
+    fn test_flatten_postgres_plan() {
+        let plan = json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Total Cost": 50.0,
+                "Plan Rows": 10,
+                "Actual Total Time": 12.0,
+                "Actual Rows": 8,
+                "Actual Loops": 1,
+                "Plans": [
+                    {
+                        "Node Type": "Seq Scan",
+                        "Relation Name": "orders",
+                        "Schema": "public",
+                        "Total Cost": 30.0,
+                        "Actual Total Time": 2.5,
+                        "Actual Rows": 100,
+                        "Actual Loops": 2,
+                        "Shared Hit Blocks": 4
+                    },
+                    {"Node Type": "Hash", "Total Cost": 5.0, "Actual Total Time": 1.0, "Actual Loops": 1}
+                ]
+            },
+            "Execution Time": 12.3
+        }]);
+        let nodes = flatten_postgres_plan(&plan);
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].parent, None);
+        assert_eq!(nodes[0].self_cost, Some(15.0));
+        assert_eq!(nodes[0].self_time, Some(6.0));
+        assert_eq!(nodes[1].parent, Some(0));
+        assert_eq!(nodes[1].relation.as_deref(), Some("public.orders"));
+        assert_eq!(nodes[1].total_time, Some(5.0));
+        assert_eq!(nodes[1].actual_rows, Some(200.0));
+        assert_eq!(nodes[1].shared_hit_blocks, Some(4.0));
+        assert_eq!(nodes[2].node_type, "Hash");
+        assert_eq!(nodes[2].parent, Some(0));
+
+        // 没有 ANALYZE 时只有代价估算
+        let plan = json!([{"Plan": {"Node Type": "Seq Scan", "Total Cost": 35.5}}]);
+        let nodes = flatten_postgres_plan(&plan);
+        assert_eq!(nodes[0].self_time, None);
+        assert_eq!(nodes[0].self_cost, Some(35.5));
+    }
+
+    #[test]
+    fn test_mysql_plan() {
+        let plan = json!({
+            "query_block": {
+                "cost_info": {"query_cost": "12.40"},
+                "nested_loop": [
+                    {"table": {"table_name": "a", "rows_examined_per_scan": 100}},
+                    {"table": {"table_name": "b", "rows_examined_per_scan": 3}}
+                ]
+            }
+        });
+        assert_eq!(
+            from_mysql_plan(&plan),
+            QueryEstimate {
+                rows: Some(103.0),
+                cost: Some(12.4),
+            }
+        );
+        assert_eq!(from_mysql_plan(&json!({})), QueryEstimate::default());
+    }
+}
@@ -1,12 +1,25 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
-use connection::{DBConnection, DBConnectionOptions, DatabaseOperations};
+use connection::{ColumnMeta, DBConnection, DBConnectionOptions, DatabaseOperations};
+use serde::{Deserialize, Serialize};
+use sqlx::{Column, Executor, Row, TypeInfo};
 use tokio::sync::RwLock;
+use tower_lsp::lsp_types::MessageType;
 
+use crate::{logger::log, settings};
+
+pub mod blob;
+pub mod cache;
 pub mod connection;
+pub mod dialect;
+pub mod explain;
 mod mysql;
 mod postgres;
+pub mod schema;
+pub mod session;
 mod sqlite;
+pub mod value;
+pub mod wkt;
 
 static DB_POOL_MAP: once_cell::sync::Lazy<RwLock<HashMap<String, Arc<DBConnection>>>> =
     once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
@@ -14,32 +27,157 @@ static DB_POOL_MAP: once_cell::sync::Lazy<RwLock<HashMap<String, Arc<DBConnectio
 pub type ConnectionPool = Box<dyn DatabaseOperations + Send + Sync>;
 
 /// Supported database types
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DatabaseType {
     SQLite,
     MySQL,
+    #[serde(alias = "postgres")]
     PostgreSQL,
     // Add more as needed
 }
 
+impl DatabaseType {
+    /// Database type of a connection string, from its scheme.
+    pub fn detect(connection_string: &str) -> Option<Self> {
+        if connection_string.starts_with("sqlite:") {
+            Some(DatabaseType::SQLite)
+        } else if connection_string.starts_with("mysql:") || connection_string.contains("mysql://")
+        {
+            Some(DatabaseType::MySQL)
+        } else if connection_string.starts_with("postgres:")
+            || connection_string.contains("postgresql://")
+        {
+            Some(DatabaseType::PostgreSQL)
+        } else {
+            None
+        }
+    }
+
+    /// Placeholder for the `index`th (1-based) bound parameter.
+    pub fn placeholder(&self, index: usize) -> String {
+        match self {
+            DatabaseType::PostgreSQL => format!("${}", index),
+            DatabaseType::MySQL | DatabaseType::SQLite => "?".to_string(),
+        }
+    }
+}
+
+/// Whether `name` is a plain identifier (letters, digits and underscores,
+/// not starting with a digit) that is safe to use in generated DDL.
+pub fn is_simple_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    name.len() <= 63 && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Names and declared types of the columns of a result row.
+pub(crate) fn column_meta<R: Row>(row: &R) -> Vec<ColumnMeta> {
+    columns_meta(row.columns())
+}
+
+fn columns_meta<C: Column>(columns: &[C]) -> Vec<ColumnMeta> {
+    columns
+        .iter()
+        .map(|column| ColumnMeta {
+            name: column.name().to_string(),
+            type_name: column.type_info().name().to_string(),
+        })
+        .collect()
+}
+
+/// Columns `query` returns, for a result without rows to read them from.
+pub(crate) async fn describe_columns<'c, E: Executor<'c>>(
+    executor: E,
+    query: &str,
+) -> anyhow::Result<Vec<ColumnMeta>> {
+    let describe = executor.describe(query).await?;
+    Ok(columns_meta(describe.columns()))
+}
+
 pub async fn from_cache(id: &str, option: DBConnectionOptions) -> Arc<DBConnection> {
     {
         let map = DB_POOL_MAP.read().await;
         let v = map.get(id);
         if let Some(v) = v {
+            v.touch();
             return Arc::clone(v);
         }
     }
 
-    {
-        let db_connection = DBConnection {
-            options: option,
-            pool: tokio::sync::OnceCell::new(),
-        };
-        DB_POOL_MAP
-            .write()
-            .await
-            .insert(id.to_string(), Arc::new(db_connection));
-    }
-    Arc::clone(DB_POOL_MAP.read().await.get(id).unwrap())
+    let (db_connection, evicted) = {
+        let mut map = DB_POOL_MAP.write().await;
+        let db_connection = Arc::clone(
+            map.entry(id.to_string())
+                .or_insert_with(|| Arc::new(DBConnection::new(option))),
+        );
+        let evicted = evict(&mut map, settings::get().max_pools);
+        (db_connection, evicted)
+    };
+    for connection in evicted {
+        connection.close().await;
+    }
+    db_connection
+}
+
+/// Remove least recently used connections that aren't in use until at
+/// most `max` remain. Zero means no limit.
+fn evict(map: &mut HashMap<String, Arc<DBConnection>>, max: usize) -> Vec<Arc<DBConnection>> {
+    let mut evicted = Vec::new();
+    if max == 0 || map.len() <= max {
+        return evicted;
+    }
+
+    let mut idle: Vec<(String, Instant)> = map
+        .iter()
+        .filter(|(_, connection)| !connection.in_use())
+        .map(|(id, connection)| (id.clone(), connection.last_used()))
+        .collect();
+    idle.sort_by_key(|(_, last_used)| *last_used);
+    for (id, _) in idle.into_iter().take(map.len() - max) {
+        if let Some(connection) = map.remove(&id) {
+            log(
+                MessageType::INFO,
+                format!("Closing least recently used connection: {}", id),
+            );
+            evicted.push(connection);
+        }
+    }
+    evicted
+}
+
+/// The cached connection for an id, if one was created.
+pub async fn cached(id: &str) -> Option<Arc<DBConnection>> {
+    DB_POOL_MAP.read().await.get(id).cloned()
+}
+
+/// Drop a cached connection and close its pool, it reconnects on next use.
+pub async fn remove(id: &str) {
+    let connection = DB_POOL_MAP.write().await.remove(id);
+    if let Some(connection) = connection {
+        connection.close().await;
+    }
+}
+
+/// Ids of all cached connections.
+pub async fn connection_ids() -> Vec<String> {
+    DB_POOL_MAP.read().await.keys().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_simple_identifier() {
+        assert!(is_simple_identifier("users"));
+        assert!(is_simple_identifier("_tmp_2024"));
+        assert!(!is_simple_identifier(""));
+        assert!(!is_simple_identifier("2fast"));
+        assert!(!is_simple_identifier("app; DROP TABLE users"));
+        assert!(!is_simple_identifier("my-db"));
+    }
 }
@@ -1,20 +1,150 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use base64::Engine;
-use sqlx::{Column, MySql, Row, TypeInfo, mysql::MySqlPoolOptions};
+use futures::TryStreamExt;
+use sqlx::{
+    MySql, Row,
+    mysql::{MySqlArguments, MySqlConnection, MySqlPoolOptions, MySqlRow},
+    query::Query,
+};
+use tokio::sync::mpsc::Sender;
+use tower_lsp::lsp_types::MessageType;
+
+use crate::{
+    logger::log,
+    parser::{self, ResultKind},
+    settings,
+};
 
 use super::{
-    ConnectionPool,
-    connection::{DBConnectionOptions, DBSet, DatabaseManager, DatabaseOperations},
+    ConnectionPool, DatabaseType, column_meta,
+    connection::{
+        BindValue, BulkTransaction, CheckConstraint, ColumnInfo, DBConnectionOptions, DBSet,
+        DatabaseManager, DatabaseOperations, ForeignKey, IdentityInfo, IndexInfo,
+        MaintenanceAction, PartitionInfo, QueryOutput, QueryTiming, SequenceInfo, ServerVariable,
+        SlowQuery, SlowQueryOrder, StreamItem, TablePrivileges, TableStats, TransactionOutput,
+        TriggerInfo, execute_in, group_index_columns, run_transaction,
+    },
+    describe_columns,
+    dialect::Dialect,
+    explain::{self, QueryEstimate},
+    session::Session,
+    value::{self, FormatOptions},
 };
 
+/// The current account in the `'user'@'host'` form used by the GRANTEE
+/// columns of information_schema.
+const CURRENT_GRANTEE: &str = "CONCAT('''', SUBSTRING_INDEX(CURRENT_USER(), '@', 1), '''@''', \
+    SUBSTRING_INDEX(CURRENT_USER(), '@', -1), '''')";
+
+/// Read a text column, which MySQL may report as binary for metadata queries.
+fn get_string(row: &MySqlRow, column: &str) -> anyhow::Result<String> {
+    let bytes: Vec<u8> = row.try_get(column)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn get_optional_string(row: &MySqlRow, column: &str) -> anyhow::Result<Option<String>> {
+    let bytes: Option<Vec<u8>> = row.try_get(column)?;
+    Ok(bytes.map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+}
+
+/// `PARTITION_DESCRIPTION` of a RANGE or LIST partition as the clause
+/// that declared it. HASH and KEY partitions have no bound.
+fn partition_bound(method: &str, description: Option<String>) -> Option<String> {
+    let description = description?;
+    if method.starts_with("RANGE") {
+        if description == "MAXVALUE" {
+            Some("VALUES LESS THAN MAXVALUE".to_string())
+        } else {
+            Some(format!("VALUES LESS THAN ({})", description))
+        }
+    } else if method.starts_with("LIST") {
+        Some(format!("VALUES IN ({})", description))
+    } else {
+        None
+    }
+}
+
+/// Warnings left by the last statement on this session, e.g. truncated
+/// values. Failing to read them doesn't fail the statement, the error is
+/// logged instead.
+async fn show_warnings(conn: &mut MySqlConnection) -> Vec<String> {
+    match read_warnings(conn).await {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            log(
+                MessageType::WARNING,
+                format!("Could not read the MySQL warnings: {}", e),
+            );
+            Vec::new()
+        }
+    }
+}
+
+async fn read_warnings(conn: &mut MySqlConnection) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query("SHOW WARNINGS").fetch_all(&mut *conn).await?;
+    let mut warnings = Vec::new();
+    for row in rows {
+        let code: u32 = row.try_get("Code")?;
+        warnings.push(format!(
+            "{} {}: {}",
+            get_string(&row, "Level")?,
+            code,
+            get_string(&row, "Message")?
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Session variables that may be set through `driver_options`.
+const SESSION_VARIABLES: &[&str] = &[
+    "sql_mode",
+    "time_zone",
+    "max_execution_time",
+    "wait_timeout",
+    "net_read_timeout",
+    "net_write_timeout",
+    "lock_wait_timeout",
+    "innodb_lock_wait_timeout",
+    "group_concat_max_len",
+    "transaction_isolation",
+    "transaction_read_only",
+];
+
 #[tower_lsp::async_trait]
 impl DatabaseManager<MySql> for DBSet<MySql> {
     async fn create(options: &DBConnectionOptions) -> anyhow::Result<DBSet<MySql>> {
-        let pool = MySqlPoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(30))
-            .connect_lazy(&options.connection_string)?;
+        let mut assignments = Vec::new();
+        for (key, value) in settings::get().driver_options {
+            if !SESSION_VARIABLES.contains(&key.as_str()) {
+                log(
+                    MessageType::WARNING,
+                    format!("Ignoring unknown MySQL driver option: {}", key),
+                );
+                continue;
+            }
+            let value = match value {
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(true) => "ON".to_string(),
+                serde_json::Value::Bool(false) => "OFF".to_string(),
+                other => Dialect::MySql.quote_literal(&settings::option_value(&other)),
+            };
+            assignments.push(format!("SESSION {} = {}", key, value));
+        }
+
+        let mut pool_options = MySqlPoolOptions::new()
+            .max_connections(settings::get().pool_size())
+            .acquire_timeout(Duration::from_secs(30));
+        if !assignments.is_empty() {
+            let sql = format!("SET {}", assignments.join(", "));
+            pool_options = pool_options.after_connect(move |conn, _meta| {
+                let sql = sql.clone();
+                Box::pin(async move {
+                    sqlx::raw_sql(&sql).execute(&mut *conn).await?;
+                    Ok(())
+                })
+            });
+        }
+        let pool = pool_options.connect_lazy(&options.connection_string)?;
 
         Ok(DBSet::new(pool))
     }
@@ -26,73 +156,247 @@ impl Into<ConnectionPool> for DBSet<MySql> {
     }
 }
 
+/// Build a query with `params` bound in order.
+fn prepare<'q>(sql: &'q str, params: &'q [BindValue]) -> Query<'q, MySql, MySqlArguments> {
+    let mut query = sqlx::query(sql);
+    for param in params {
+        query = match param {
+            BindValue::Null => query.bind(None::<String>),
+            BindValue::Bool(v) => query.bind(*v),
+            BindValue::Int(v) => query.bind(*v),
+            BindValue::Float(v) => query.bind(*v),
+            BindValue::Text(v) => query.bind(v.as_str()),
+            BindValue::Date(v) => query.bind(*v),
+            BindValue::Time(v) => query.bind(*v),
+            BindValue::Timestamp(v) => query.bind(*v),
+            BindValue::Timestamptz(v) => query.bind(*v),
+            BindValue::Decimal(v) => query.bind(*v),
+        };
+    }
+    query
+}
+
 /// MySQL specific operations
 pub struct MySQLOperations(DBSet<MySql>);
 
+impl MySQLOperations {
+    /// Kills the statement running on connection `id` from another pooled
+    /// connection.
+    fn cancel(&self, id: u64) -> impl Future<Output = ()> + Send + 'static {
+        let pool = self.0.pool();
+        async move {
+            let sql = format!("KILL QUERY {}", id);
+            if let Err(e) = sqlx::raw_sql(&sql).execute(pool.as_ref()).await {
+                log(
+                    MessageType::WARNING,
+                    format!("Failed to cancel query on connection {}: {}", id, e),
+                );
+            }
+        }
+    }
+
+    /// The session running statements on `conn`, to kill them by.
+    async fn connection_id(conn: &mut sqlx::MySqlConnection) -> anyhow::Result<u64> {
+        Ok(sqlx::query_scalar("SELECT CONNECTION_ID()")
+            .fetch_one(conn)
+            .await?)
+    }
+}
+
+/// A transaction started by [`DatabaseOperations::begin_session`].
+struct MySQLSession(BulkTransaction<MySql>);
+
+#[tower_lsp::async_trait]
+impl Session for MySQLSession {
+    async fn execute_as(
+        &mut self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+        format: &FormatOptions,
+    ) -> anyhow::Result<QueryOutput> {
+        let binary_uuid = settings::get().binary_uuid;
+        let mut output = execute_in(
+            &mut *self.0,
+            prepare(query, params),
+            kind,
+            |result| result.rows_affected(),
+            |row| Ok(value::mysql_row(row, binary_uuid, format)),
+        )
+        .await?;
+        output.warnings = show_warnings(&mut self.0).await;
+        Ok(output)
+    }
+
+    async fn commit(self: Box<Self>) -> anyhow::Result<()> {
+        self.0.commit().await
+    }
+
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()> {
+        self.0.rollback().await
+    }
+}
+
 #[tower_lsp::async_trait]
 impl DatabaseOperations for MySQLOperations {
-    async fn execute_query(&self, query: &str) -> anyhow::Result<(serde_json::Value, usize)> {
-        // For SELECT queries, fetch rows
-        if query.trim().to_lowercase().starts_with("select") {
-            let rows = sqlx::query(query).fetch_all(self.0.pool().as_ref()).await?;
+    fn database_type(&self) -> DatabaseType {
+        DatabaseType::MySQL
+    }
+
+    async fn execute_query_as(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+        format: &FormatOptions,
+    ) -> anyhow::Result<QueryOutput> {
+        let started = Instant::now();
+        let mut conn = self.0.acquire().await?;
+        let mut timing = QueryTiming {
+            acquire: started.elapsed(),
+            ..Default::default()
+        };
+
+        // Dropping the future doesn't stop the server, so remember which
+        // session runs the statement in order to cancel it
+        let id = Self::connection_id(&mut conn).await?;
+
+        // For queries producing a result set, fetch rows
+        if kind == ResultKind::Rows {
+            let started = Instant::now();
+            conn.running(self.cancel(id));
+            let rows = prepare(query, params).fetch_all(&mut *conn).await;
+            conn.finished();
+            let rows = rows?;
+            timing.execute = started.elapsed();
+            let warnings = show_warnings(&mut conn).await;
+
+            let started = Instant::now();
             let total = rows.len();
+            let columns = rows.first().map(column_meta).unwrap_or_default();
             let mut result = Vec::new();
+            let binary_uuid = settings::get().binary_uuid;
             for row in rows {
-                let mut obj = serde_json::Map::new();
-
-                // Convert each column to a JSON value
-                for (i, column) in row.columns().iter().enumerate() {
-                    let column_name = column.name();
-                    // 这里直接尝试获取值作为字符串表示
-                    let value = if let Ok(val) = row.try_get::<Option<String>, _>(i) {
-                        match val {
-                            Some(s) => serde_json::Value::String(s),
-                            None => serde_json::Value::Null,
-                        }
-                    } else if let Ok(val) = row.try_get::<Option<Vec<u8>>, _>(i) {
-                        // 对于二进制数据特殊处理
-                        match val {
-                            Some(bytes) => {
-                                let base64_str =
-                                    base64::engine::general_purpose::STANDARD.encode(&bytes);
-                                serde_json::Value::String(format!("(binary) {}", base64_str))
-                            }
-                            None => serde_json::Value::Null,
-                        }
-                    } else if let Ok(val) = row.try_get::<Option<i64>, _>(i) {
-                        // 对于整数类型
-                        match val {
-                            Some(n) => serde_json::Value::String(n.to_string()),
-                            None => serde_json::Value::Null,
-                        }
-                    } else if let Ok(val) = row.try_get::<Option<f64>, _>(i) {
-                        // 对于浮点类型
-                        match val {
-                            Some(n) => serde_json::Value::String(n.to_string()),
-                            None => serde_json::Value::Null,
-                        }
-                    } else {
-                        // 如果所有尝试都失败，返回类型信息
-                        let type_info = column.type_info();
-                        serde_json::Value::String(format!("(unknown type: {})", type_info.name()))
-                    };
-
-                    obj.insert(column_name.to_string(), value);
-                }
-                result.push(serde_json::Value::Object(obj));
+                result.push(serde_json::Value::Object(value::mysql_row(
+                    &row,
+                    binary_uuid,
+                    format,
+                )));
             }
 
-            Ok((serde_json::Value::Array(result), total))
+            timing.serialize = started.elapsed();
+
+            Ok(QueryOutput {
+                columns,
+                rows: serde_json::Value::Array(result),
+                total,
+                timing,
+                warnings,
+            })
         } else {
-            // For non-SELECT queries, return affected rows
-            let result = sqlx::query(query).execute(self.0.pool().as_ref()).await?;
+            // For everything else, return affected rows
+            let started = Instant::now();
+            conn.running(self.cancel(id));
+            let result = prepare(query, params).execute(&mut *conn).await;
+            conn.finished();
+            let result = result?;
+            timing.execute = started.elapsed();
+            let warnings = show_warnings(&mut conn).await;
+
+            Ok(QueryOutput {
+                columns: Vec::new(),
+                rows: serde_json::Value::Null,
+                total: result.rows_affected() as usize,
+                timing,
+                warnings,
+            })
+        }
+    }
+
+    async fn execute_transaction(
+        &self,
+        statements: &[String],
+        continue_on_error: bool,
+    ) -> anyhow::Result<TransactionOutput> {
+        // DDL 会隐式提交并清除保存点，之后无法回滚
+        if let Some(statement) = statements
+            .iter()
+            .find(|statement| parser::commits_implicitly(statement))
+        {
+            return Err(anyhow::anyhow!(
+                "MySQL commits implicitly before running `{}`, so it can't be part of a \
+                 transaction that may be rolled back",
+                statement.trim()
+            ));
+        }
+        let _permit = self.0.bulk_permit().await?;
+        run_transaction(
+            self.0.pool().as_ref(),
+            statements,
+            continue_on_error,
+            |result| result.rows_affected(),
+        )
+        .await
+    }
 
-            Ok((serde_json::Value::Null, result.rows_affected() as usize))
+    async fn begin_session(&self) -> anyhow::Result<Box<dyn Session>> {
+        Ok(Box::new(MySQLSession(self.0.begin().await?)))
+    }
+
+    async fn stream_query(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        max_rows: Option<u64>,
+        format: &FormatOptions,
+        tx: Sender<StreamItem>,
+    ) -> anyhow::Result<bool> {
+        let mut conn = self.0.acquire().await?;
+        // 提前返回时取消查询，服务器随之停止发送剩余的行
+        let id = Self::connection_id(&mut conn).await?;
+        conn.running(self.cancel(id));
+        let binary_uuid = settings::get().binary_uuid;
+        let mut rows = prepare(query, params).fetch(&mut *conn);
+        let mut first = true;
+        let mut sent = 0;
+        while let Some(row) = rows.try_next().await? {
+            if max_rows.is_some_and(|max| sent >= max) {
+                return Ok(true);
+            }
+            if first {
+                first = false;
+                if tx
+                    .send(StreamItem::Columns(column_meta(&row)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(false);
+                }
+            }
+            let row = value::mysql_row(&row, binary_uuid, format);
+            if tx.send(StreamItem::Row(row)).await.is_err() {
+                return Ok(false);
+            }
+            sent += 1;
         }
+        drop(rows);
+        conn.finished();
+        if first && let Ok(columns) = describe_columns(&mut *conn, query).await {
+            let _ = tx.send(StreamItem::Columns(columns)).await;
+        }
+        Ok(false)
+    }
+
+    async fn explain_estimate(&self, query: &str) -> anyhow::Result<QueryEstimate> {
+        let sql = format!("EXPLAIN FORMAT=JSON {}", query);
+        let row = sqlx::query(&sql).fetch_one(self.0.pool().as_ref()).await?;
+        let plan = get_string(&row, "EXPLAIN")?;
+        Ok(explain::from_mysql_plan(&serde_json::from_str(&plan)?))
     }
 
     async fn get_tables(&self) -> anyhow::Result<Vec<String>> {
-        let rows = sqlx::query("SHOW TABLES")
+        // SHOW TABLES also lists views, those come from get_views
+        let rows = sqlx::query("SHOW FULL TABLES WHERE Table_type = 'BASE TABLE'")
             .fetch_all(self.0.pool().as_ref())
             .await?;
 
@@ -108,6 +412,28 @@ impl DatabaseOperations for MySQLOperations {
         Ok(tables)
     }
 
+    async fn get_views(&self) -> anyhow::Result<Vec<String>> {
+        let rows = sqlx::query("SHOW FULL TABLES WHERE Table_type = 'VIEW'")
+            .fetch_all(self.0.pool().as_ref())
+            .await?;
+
+        let mut views = Vec::new();
+        for row in rows {
+            let name: Vec<u8> = row.try_get(0)?;
+            views.push(String::from_utf8_lossy(&name).to_string());
+        }
+        Ok(views)
+    }
+
+    async fn get_view_definition(&self, view: &str) -> anyhow::Result<String> {
+        let sql = format!(
+            "SHOW CREATE VIEW {}",
+            self.native_dialect().quote_ident(view)
+        );
+        let row = sqlx::query(&sql).fetch_one(self.0.pool().as_ref()).await?;
+        get_string(&row, "Create View")
+    }
+
     async fn get_columns(&self, table_name: &str) -> anyhow::Result<Vec<String>> {
         let query = format!("SHOW COLUMNS FROM {}", table_name);
         let rows = sqlx::query(&query)
@@ -125,12 +451,519 @@ impl DatabaseOperations for MySQLOperations {
         Ok(columns)
     }
 
+    async fn get_column_info(&self, table_name: &str) -> anyhow::Result<Vec<ColumnInfo>> {
+        let rows = sqlx::query(
+            "SELECT COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE, EXTRA, \
+                COALESCE(GENERATION_EXPRESSION, '') AS GENERATION_EXPRESSION \
+            FROM information_schema.COLUMNS \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+            ORDER BY ORDINAL_POSITION",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            // EXTRA is also DEFAULT_GENERATED for expression defaults, which
+            // are writable
+            let extra = get_string(&row, "EXTRA")?.to_uppercase();
+            let is_generated = [
+                "VIRTUAL GENERATED",
+                "STORED GENERATED",
+                "PERSISTENT GENERATED",
+            ]
+            .iter()
+            .any(|kind| extra.contains(kind));
+            let expr = get_string(&row, "GENERATION_EXPRESSION")?;
+            columns.push(ColumnInfo {
+                name: get_string(&row, "COLUMN_NAME")?,
+                data_type: get_string(&row, "COLUMN_TYPE")?,
+                nullable: get_string(&row, "IS_NULLABLE")? == "YES",
+                is_generated,
+                generation_expr: (is_generated && !expr.is_empty()).then_some(expr),
+            });
+        }
+        Ok(columns)
+    }
+
+    async fn get_primary_keys(&self, table_name: &str) -> anyhow::Result<Vec<String>> {
+        let rows = sqlx::query(
+            "SELECT COLUMN_NAME FROM information_schema.KEY_COLUMN_USAGE \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY' \
+            ORDER BY ORDINAL_POSITION",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            columns.push(get_string(&row, "COLUMN_NAME")?);
+        }
+        Ok(columns)
+    }
+
+    async fn get_schema_tables(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let rows = sqlx::query(
+            "SELECT TABLE_SCHEMA, TABLE_NAME FROM information_schema.tables \
+            WHERE TABLE_SCHEMA NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')",
+        )
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut tables = Vec::new();
+        for row in rows {
+            tables.push((
+                get_string(&row, "TABLE_SCHEMA")?,
+                get_string(&row, "TABLE_NAME")?,
+            ));
+        }
+        Ok(tables)
+    }
+
+    async fn rename_table(&self, old_name: &str, new_name: &str) -> anyhow::Result<()> {
+        let dialect = self.native_dialect();
+        let sql = format!(
+            "RENAME TABLE {} TO {}",
+            dialect.quote_ident(old_name),
+            dialect.quote_ident(new_name)
+        );
+        sqlx::query(&sql).execute(self.0.pool().as_ref()).await?;
+        Ok(())
+    }
+
+    async fn maintain_table(
+        &self,
+        table: &str,
+        action: MaintenanceAction,
+    ) -> anyhow::Result<Vec<String>> {
+        let table = self.native_dialect().quote_ident(table);
+        let sql = match action {
+            MaintenanceAction::Optimize => format!("OPTIMIZE TABLE {}", table),
+            MaintenanceAction::Analyze => format!("ANALYZE TABLE {}", table),
+            MaintenanceAction::Vacuum => {
+                return Err(anyhow::anyhow!("MySQL does not support VACUUM"));
+            }
+        };
+        // Both statements report their outcome as a result set
+        let rows = sqlx::raw_sql(&sql)
+            .fetch_all(self.0.pool().as_ref())
+            .await?;
+
+        let mut messages = Vec::new();
+        for row in rows {
+            let msg_type = get_string(&row, "Msg_type")?;
+            let msg_text = get_string(&row, "Msg_text")?;
+            messages.push(format!("{}: {}", msg_type, msg_text));
+        }
+
+        Ok(messages)
+    }
+
+    async fn clone_table_structure(&self, source: &str, target: &str) -> anyhow::Result<()> {
+        let dialect = self.native_dialect();
+        let sql = format!(
+            "CREATE TABLE {} LIKE {}",
+            dialect.quote_ident(target),
+            dialect.quote_ident(source)
+        );
+        sqlx::query(&sql).execute(self.0.pool().as_ref()).await?;
+        Ok(())
+    }
+
+    async fn get_triggers(&self, table_name: &str) -> anyhow::Result<Vec<TriggerInfo>> {
+        let rows = sqlx::query(
+            "SELECT TRIGGER_NAME, ACTION_TIMING, EVENT_MANIPULATION, ACTION_STATEMENT \
+            FROM information_schema.triggers \
+            WHERE EVENT_OBJECT_SCHEMA = DATABASE() AND EVENT_OBJECT_TABLE = ? \
+            ORDER BY ACTION_ORDER",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut triggers = Vec::new();
+        for row in rows {
+            triggers.push(TriggerInfo {
+                name: get_string(&row, "TRIGGER_NAME")?,
+                timing: Some(get_string(&row, "ACTION_TIMING")?),
+                event: Some(get_string(&row, "EVENT_MANIPULATION")?),
+                definition: Some(get_string(&row, "ACTION_STATEMENT")?),
+            });
+        }
+
+        Ok(triggers)
+    }
+
+    async fn get_privileges(&self, table_name: &str) -> anyhow::Result<TablePrivileges> {
+        // 权限可能授予在全局、库或表级别
+        let sql = format!(
+            "SELECT PRIVILEGE_TYPE FROM information_schema.user_privileges \
+            WHERE GRANTEE = {grantee} \
+            UNION SELECT PRIVILEGE_TYPE FROM information_schema.schema_privileges \
+            WHERE GRANTEE = {grantee} AND TABLE_SCHEMA = DATABASE() \
+            UNION SELECT PRIVILEGE_TYPE FROM information_schema.table_privileges \
+            WHERE GRANTEE = {grantee} AND TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+            grantee = CURRENT_GRANTEE,
+        );
+        let rows = sqlx::query(&sql)
+            .bind(table_name)
+            .fetch_all(self.0.pool().as_ref())
+            .await?;
+        let mut names = Vec::new();
+        for row in rows {
+            names.push(get_string(&row, "PRIVILEGE_TYPE")?);
+        }
+        Ok(TablePrivileges::from_names(names))
+    }
+
+    async fn get_foreign_keys(&self, table_name: &str) -> anyhow::Result<Vec<ForeignKey>> {
+        let rows = sqlx::query(
+            "SELECT CONSTRAINT_NAME, COLUMN_NAME, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME \
+            FROM information_schema.KEY_COLUMN_USAGE \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+                AND REFERENCED_TABLE_NAME IS NOT NULL \
+            ORDER BY CONSTRAINT_NAME, ORDINAL_POSITION",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut foreign_keys = Vec::new();
+        for row in rows {
+            foreign_keys.push(ForeignKey {
+                name: Some(get_string(&row, "CONSTRAINT_NAME")?),
+                id: None,
+                column: get_string(&row, "COLUMN_NAME")?,
+                referenced_table: get_string(&row, "REFERENCED_TABLE_NAME")?,
+                referenced_column: get_string(&row, "REFERENCED_COLUMN_NAME")?,
+            });
+        }
+
+        Ok(foreign_keys)
+    }
+
+    async fn get_indexes(&self, table_name: &str) -> anyhow::Result<Vec<IndexInfo>> {
+        // 函数索引的 COLUMN_NAME 为 NULL
+        let rows = sqlx::query(
+            "SELECT INDEX_NAME, CAST(NON_UNIQUE AS SIGNED) AS NON_UNIQUE, COLUMN_NAME \
+            FROM information_schema.STATISTICS \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+                AND INDEX_NAME <> 'PRIMARY' \
+            ORDER BY INDEX_NAME, SEQ_IN_INDEX",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            columns.push((
+                get_string(&row, "INDEX_NAME")?,
+                row.try_get::<i64, _>("NON_UNIQUE")? == 0,
+                get_optional_string(&row, "COLUMN_NAME")?,
+            ));
+        }
+        Ok(group_index_columns(table_name, columns))
+    }
+
+    async fn get_server_variables(
+        &self,
+        pattern: Option<&str>,
+    ) -> anyhow::Result<Vec<ServerVariable>> {
+        // SHOW 语句不能绑定参数，模式作为字面量拼接
+        let sql = match pattern {
+            Some(pattern) => format!(
+                "SHOW SESSION VARIABLES LIKE {}",
+                Dialect::MySql.quote_literal(pattern)
+            ),
+            None => "SHOW SESSION VARIABLES".to_string(),
+        };
+        let rows = sqlx::query(&sql).fetch_all(self.0.pool().as_ref()).await?;
+
+        let mut variables = Vec::new();
+        for row in rows {
+            variables.push(ServerVariable {
+                name: get_string(&row, "Variable_name")?,
+                value: Some(get_string(&row, "Value")?),
+                unit: None,
+                description: None,
+            });
+        }
+
+        Ok(variables)
+    }
+
+    async fn get_check_constraints(
+        &self,
+        table_name: &str,
+    ) -> anyhow::Result<Vec<CheckConstraint>> {
+        // CHECK_CONSTRAINTS 从 MySQL 8.0.16 开始才有，之前的版本会忽略 CHECK
+        let rows = sqlx::query(
+            "SELECT cc.CONSTRAINT_NAME, cc.CHECK_CLAUSE \
+            FROM information_schema.CHECK_CONSTRAINTS cc \
+            JOIN information_schema.TABLE_CONSTRAINTS tc \
+                ON tc.CONSTRAINT_SCHEMA = cc.CONSTRAINT_SCHEMA \
+                AND tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME \
+            WHERE tc.TABLE_SCHEMA = DATABASE() AND tc.TABLE_NAME = ? \
+                AND tc.CONSTRAINT_TYPE = 'CHECK' \
+            ORDER BY cc.CONSTRAINT_NAME",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await
+        .map_err(|e| anyhow::anyhow!("CHECK constraints need MySQL 8.0.16 or later: {}", e))?;
+
+        let mut constraints = Vec::new();
+        for row in rows {
+            constraints.push(CheckConstraint {
+                name: Some(get_string(&row, "CONSTRAINT_NAME")?),
+                column: None,
+                expression: get_string(&row, "CHECK_CLAUSE")?,
+                approximate: false,
+            });
+        }
+
+        Ok(constraints)
+    }
+
+    async fn get_partitions(&self, table_name: &str) -> anyhow::Result<Vec<PartitionInfo>> {
+        // 未分区的表只有一行，PARTITION_NAME 为 NULL
+        let rows = sqlx::query(
+            "SELECT PARTITION_NAME, SUBPARTITION_NAME, PARTITION_METHOD, SUBPARTITION_METHOD, \
+                PARTITION_EXPRESSION, SUBPARTITION_EXPRESSION, PARTITION_DESCRIPTION, \
+                CAST(TABLE_ROWS AS SIGNED) AS TABLE_ROWS \
+            FROM information_schema.PARTITIONS \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND PARTITION_NAME IS NOT NULL \
+            ORDER BY PARTITION_ORDINAL_POSITION, SUBPARTITION_ORDINAL_POSITION",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut partitions: Vec<PartitionInfo> = Vec::new();
+        for row in rows {
+            let name = get_string(&row, "PARTITION_NAME")?;
+            let rows: Option<i64> = row.try_get("TABLE_ROWS")?;
+            let subpartition = get_optional_string(&row, "SUBPARTITION_NAME")?;
+            if !partitions
+                .iter()
+                .any(|p| p.name == name && p.parent == table_name)
+            {
+                let method = get_string(&row, "PARTITION_METHOD")?;
+                let expression = get_optional_string(&row, "PARTITION_EXPRESSION")?;
+                partitions.push(PartitionInfo {
+                    name: name.clone(),
+                    parent: table_name.to_string(),
+                    partition_by: Some(format!("{} ({})", method, expression.unwrap_or_default())),
+                    bound: partition_bound(
+                        &method,
+                        get_optional_string(&row, "PARTITION_DESCRIPTION")?,
+                    ),
+                    // 有子分区时行数记在子分区上
+                    rows: rows.filter(|_| subpartition.is_none()),
+                });
+            }
+            if let Some(subpartition) = subpartition {
+                let method = get_optional_string(&row, "SUBPARTITION_METHOD")?.unwrap_or_default();
+                let expression =
+                    get_optional_string(&row, "SUBPARTITION_EXPRESSION")?.unwrap_or_default();
+                partitions.push(PartitionInfo {
+                    name: subpartition,
+                    parent: name,
+                    partition_by: Some(format!("{} ({})", method, expression)),
+                    bound: None,
+                    rows,
+                });
+            }
+        }
+
+        Ok(partitions)
+    }
+
+    async fn get_table_stats(&self, table_name: &str) -> anyhow::Result<TableStats> {
+        // InnoDB 的 TABLE_ROWS 只是估算值
+        let row = sqlx::query(
+            "SELECT CAST(TABLE_ROWS AS SIGNED) AS TABLE_ROWS, \
+                CAST(DATA_LENGTH + INDEX_LENGTH AS SIGNED) AS SIZE_BYTES \
+            FROM information_schema.TABLES \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+        )
+        .bind(table_name)
+        .fetch_optional(self.0.pool().as_ref())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Table not found: {}", table_name))?;
+        Ok(TableStats {
+            rows: row.try_get("TABLE_ROWS")?,
+            size_bytes: row.try_get("SIZE_BYTES")?,
+            estimated: true,
+        })
+    }
+
+    async fn get_table_ddl(&self, table_name: &str) -> anyhow::Result<String> {
+        // SHOW CREATE TABLE already includes indexes and constraints
+        let sql = format!(
+            "SHOW CREATE TABLE {}",
+            Dialect::MySql.quote_ident(table_name)
+        );
+        let row = sqlx::query(&sql).fetch_one(self.0.pool().as_ref()).await?;
+        Ok(format!("{};", get_string(&row, "Create Table")?))
+    }
+
+    async fn create_database(&self, name: &str) -> anyhow::Result<()> {
+        let sql = format!(
+            "CREATE DATABASE {}",
+            self.native_dialect().quote_ident(name)
+        );
+        sqlx::raw_sql(&sql).execute(self.0.pool().as_ref()).await?;
+        Ok(())
+    }
+
+    async fn rename_schema(&self, old_name: &str, new_name: &str) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!(
+            "MySQL can't rename a database. Create {} and move the tables of {} \
+             with RENAME TABLE instead",
+            new_name,
+            old_name
+        ))
+    }
+
+    async fn table_exists(&self, table_name: &str) -> anyhow::Result<bool> {
+        // Table name case sensitivity follows the server's lower_case_table_names
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM information_schema.tables \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+        )
+        .bind(table_name)
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn column_exists(&self, table_name: &str, column_name: &str) -> anyhow::Result<bool> {
+        // Column names are never case sensitive in MySQL
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM information_schema.columns \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+            AND LOWER(COLUMN_NAME) = LOWER(?)",
+        )
+        .bind(table_name)
+        .bind(column_name)
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn get_slow_queries(
+        &self,
+        limit: i64,
+        order: SlowQueryOrder,
+    ) -> anyhow::Result<Vec<SlowQuery>> {
+        let order_by = match order {
+            SlowQueryOrder::Total => "SUM_TIMER_WAIT",
+            SlowQueryOrder::Mean => "AVG_TIMER_WAIT",
+        };
+        // Timer columns are in picoseconds
+        let sql = format!(
+            "SELECT DIGEST_TEXT, COUNT_STAR, SUM_TIMER_WAIT / 1e9 AS total_ms, \
+                AVG_TIMER_WAIT / 1e9 AS mean_ms \
+            FROM performance_schema.events_statements_summary_by_digest \
+            WHERE DIGEST_TEXT IS NOT NULL ORDER BY {} DESC LIMIT ?",
+            order_by
+        );
+        let rows = match sqlx::query(&sql)
+            .bind(limit)
+            .fetch_all(self.0.pool().as_ref())
+            .await
+        {
+            Ok(rows) => rows,
+            // Table doesn't exist
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42S02") => {
+                return Err(anyhow::anyhow!("performance_schema is not enabled"));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut queries = Vec::new();
+        for row in rows {
+            queries.push(SlowQuery {
+                query: get_string(&row, "DIGEST_TEXT")?,
+                calls: row.try_get::<u64, _>("COUNT_STAR")? as i64,
+                total_ms: row.try_get("total_ms")?,
+                mean_ms: row.try_get("mean_ms")?,
+            });
+        }
+        Ok(queries)
+    }
+
     async fn check_connection(&self) -> anyhow::Result<bool> {
         sqlx::query("SELECT 1")
             .execute(self.0.pool().as_ref())
             .await?;
         Ok(true)
     }
+
+    async fn close(&self) {
+        self.0.close().await;
+    }
+
+    async fn get_sequences(&self) -> anyhow::Result<Vec<SequenceInfo>> {
+        let increment: i64 =
+            sqlx::query_scalar("SELECT CAST(@@auto_increment_increment AS SIGNED)")
+                .fetch_one(self.0.pool().as_ref())
+                .await?;
+        // AUTO_INCREMENT 是下一个值
+        let rows = sqlx::query(
+            "SELECT TABLE_NAME, CAST(AUTO_INCREMENT AS SIGNED) AS next_value \
+            FROM information_schema.TABLES \
+            WHERE TABLE_SCHEMA = DATABASE() AND AUTO_INCREMENT IS NOT NULL \
+            ORDER BY TABLE_NAME",
+        )
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut sequences = Vec::new();
+        for row in rows {
+            let table = get_string(&row, "TABLE_NAME")?;
+            let next_value: Option<i64> = row.try_get("next_value")?;
+            sequences.push(SequenceInfo {
+                schema: None,
+                name: table.clone(),
+                table: Some(table),
+                last_value: next_value.map(|v| v - increment).filter(|v| *v > 0),
+                increment: Some(increment),
+                min_value: None,
+                max_value: None,
+            });
+        }
+        Ok(sequences)
+    }
+
+    async fn get_identity(&self, table_name: &str) -> anyhow::Result<IdentityInfo> {
+        let column: Option<String> = sqlx::query_scalar(
+            "SELECT CAST(COLUMN_NAME AS CHAR) FROM information_schema.COLUMNS \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND EXTRA LIKE '%auto_increment%'",
+        )
+        .bind(table_name)
+        .fetch_optional(self.0.pool().as_ref())
+        .await?;
+        if column.is_none() {
+            return Ok(IdentityInfo::default());
+        }
+        // MySQL 8 缓存 information_schema 的表统计，AUTO_INCREMENT 可能稍有滞后
+        let next_value: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT CAST(AUTO_INCREMENT AS SIGNED) FROM information_schema.TABLES \
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+        )
+        .bind(table_name)
+        .fetch_optional(self.0.pool().as_ref())
+        .await?;
+        Ok(IdentityInfo {
+            column,
+            next_value: next_value.flatten(),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -151,7 +984,7 @@ mod tests {
 
         // Test execute_query
         let result = operations
-            .execute_query(&format!("SELECT * FROM {}", table))
+            .execute_query(&format!("SELECT * FROM {}", table), &[], ResultKind::Rows)
             .await
             .unwrap();
         println!("{:?}", result);
@@ -168,4 +1001,21 @@ mod tests {
         let is_connected = operations.check_connection().await.unwrap();
         assert!(is_connected);
     }
+
+    #[test]
+    fn test_partition_bound() {
+        assert_eq!(
+            partition_bound("RANGE COLUMNS", Some("'2024-01-01'".to_string())).as_deref(),
+            Some("VALUES LESS THAN ('2024-01-01')")
+        );
+        assert_eq!(
+            partition_bound("RANGE", Some("MAXVALUE".to_string())).as_deref(),
+            Some("VALUES LESS THAN MAXVALUE")
+        );
+        assert_eq!(
+            partition_bound("LIST", Some("1,2".to_string())).as_deref(),
+            Some("VALUES IN (1,2)")
+        );
+        assert_eq!(partition_bound("HASH", None), None);
+    }
 }
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::RwLock;
+use tower_lsp::lsp_types::MessageType;
+
+use crate::logger::log;
+
+use super::{ConnectionPool, DatabaseType, connection::ForeignKey};
+
+static SCHEMA_CACHE: once_cell::sync::Lazy<RwLock<HashMap<String, Arc<SchemaInfo>>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Tables and columns of a connection, used for completion.
+#[derive(Debug, Default)]
+pub struct SchemaInfo {
+    pub tables: HashMap<String, TableInfo>,
+    /// Table names by schema (database on MySQL)
+    pub schemas: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct TableInfo {
+    pub columns: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl SchemaInfo {
+    /// Columns and keys of a table, falling back to a case-insensitive match
+    /// of the name.
+    pub fn table(&self, name: &str) -> Option<&TableInfo> {
+        self.tables.get(name).or_else(|| {
+            self.tables
+                .iter()
+                .find(|(table, _)| table.eq_ignore_ascii_case(name))
+                .map(|(_, info)| info)
+        })
+    }
+
+    async fn load(pool: &ConnectionPool) -> anyhow::Result<Self> {
+        let mut tables = HashMap::new();
+        // 视图也可以查询，一起用于补全
+        let views = pool.get_views().await.unwrap_or_default();
+        for table in pool.get_tables().await?.into_iter().chain(views) {
+            let columns = pool.get_columns(&table).await?;
+            // Foreign keys only improve suggestions, don't fail the whole load on them
+            let foreign_keys = pool.get_foreign_keys(&table).await.unwrap_or_default();
+            tables.insert(
+                table,
+                TableInfo {
+                    columns,
+                    foreign_keys,
+                },
+            );
+        }
+
+        let mut schemas: HashMap<String, Vec<String>> = HashMap::new();
+        for (schema, table) in pool.get_schema_tables().await.unwrap_or_default() {
+            schemas.entry(schema).or_default().push(table);
+        }
+        Ok(SchemaInfo { tables, schemas })
+    }
+}
+
+/// Schema of a cached connection, loading it on first use.
+pub async fn get(connection_id: &str) -> Option<Arc<SchemaInfo>> {
+    if let Some(schema) = SCHEMA_CACHE.read().await.get(connection_id) {
+        return Some(Arc::clone(schema));
+    }
+
+    let connection = super::cached(connection_id).await?;
+    let pool = connection.get_pool().await?;
+    match SchemaInfo::load(&pool).await {
+        Ok(schema) => {
+            let schema = Arc::new(schema);
+            SCHEMA_CACHE
+                .write()
+                .await
+                .insert(connection_id.to_string(), Arc::clone(&schema));
+            Some(schema)
+        }
+        Err(e) => {
+            log(
+                MessageType::ERROR,
+                format!("Failed to load schema for {}: {}", connection_id, e),
+            );
+            None
+        }
+    }
+}
+
+/// Schema of a connection if it is already loaded, without loading it.
+pub async fn cached(connection_id: &str) -> Option<Arc<SchemaInfo>> {
+    SCHEMA_CACHE.read().await.get(connection_id).cloned()
+}
+
+/// Schemas of every cached connection, keyed by connection id.
+pub async fn all() -> Vec<(String, Arc<SchemaInfo>)> {
+    let mut schemas = Vec::new();
+    for id in super::connection_ids().await {
+        if let Some(schema) = get(&id).await {
+            schemas.push((id, schema));
+        }
+    }
+    schemas
+}
+
+/// Forget the cached schema of a connection after a DDL change, it is
+/// reloaded on next use.
+pub async fn invalidate(connection_id: &str) {
+    SCHEMA_CACHE.write().await.remove(connection_id);
+}
+
+/// The name in `candidates` that `name`, as typed by the user, refers to.
+/// An exact match wins. Otherwise Postgres folds unquoted names to lower
+/// case and matches quoted ones as written, and any backend accepts a
+/// single case-insensitive match. None when nothing or several names match.
+pub fn resolve_name(db_type: &DatabaseType, name: &str, candidates: &[String]) -> Option<String> {
+    let quote = name
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '"' | '`') && name.len() > 1 && name.ends_with(*c));
+    let bare = match quote {
+        Some(quote) => {
+            name[1..name.len() - 1].replace(&quote.to_string().repeat(2), &quote.to_string())
+        }
+        None => name.to_string(),
+    };
+    if let Some(exact) = candidates.iter().find(|c| **c == bare) {
+        return Some(exact.clone());
+    }
+    if *db_type == DatabaseType::PostgreSQL {
+        if quote.is_some() {
+            return None;
+        }
+        let folded = bare.to_lowercase();
+        if let Some(folded) = candidates.iter().find(|c| **c == folded) {
+            return Some(folded.clone());
+        }
+    }
+
+    let mut matches = candidates
+        .iter()
+        .filter(|c| c.to_lowercase() == bare.to_lowercase());
+    match (matches.next(), matches.next()) {
+        (Some(only), None) => Some(only.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_name() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let tables = names(&["users", "Orders", "items", "ITEMS"]);
+        let pg = DatabaseType::PostgreSQL;
+        assert_eq!(
+            resolve_name(&pg, "Users", &tables).as_deref(),
+            Some("users")
+        );
+        assert_eq!(
+            resolve_name(&pg, "orders", &tables).as_deref(),
+            Some("Orders")
+        );
+        assert_eq!(
+            resolve_name(&pg, "\"Orders\"", &tables).as_deref(),
+            Some("Orders")
+        );
+        assert_eq!(resolve_name(&pg, "\"USERS\"", &tables), None);
+        // 未加引号时折叠为小写
+        assert_eq!(
+            resolve_name(&pg, "Items", &tables).as_deref(),
+            Some("items")
+        );
+
+        let mysql = DatabaseType::MySQL;
+        assert_eq!(
+            resolve_name(&mysql, "`USERS`", &tables).as_deref(),
+            Some("users")
+        );
+        assert_eq!(resolve_name(&mysql, "Items", &tables), None);
+        assert_eq!(
+            resolve_name(&mysql, "ITEMS", &tables).as_deref(),
+            Some("ITEMS")
+        );
+        assert_eq!(resolve_name(&mysql, "missing", &tables), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex;
+use tower_lsp::lsp_types::MessageType;
+
+use crate::{logger::log, parser::ResultKind, settings};
+
+use super::{
+    cache,
+    connection::{BindValue, QueryOutput},
+    value::FormatOptions,
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Whether the task rolling back idle sessions is running.
+static REAPER: AtomicBool = AtomicBool::new(false);
+
+/// How often idle sessions are looked for.
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+static SESSIONS: once_cell::sync::Lazy<std::sync::Mutex<HashMap<String, Registered>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+struct Registered {
+    session: SharedSession,
+    /// Connection the session was opened on, whose cached results a
+    /// commit makes stale
+    connection_id: String,
+    last_used: Instant,
+}
+
+/// Named MySQL locks taken with `GET_LOCK` in each session. They belong to
+/// the connection rather than the transaction, so they outlive a commit.
+static HELD_LOCKS: once_cell::sync::Lazy<std::sync::Mutex<HashMap<String, HashSet<String>>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+pub type SharedSession = Arc<Mutex<Box<dyn Session>>>;
+
+/// An open transaction on a connection taken out of the pool for as long as
+/// the session lasts, see
+/// [`DatabaseOperations::begin_session`](super::connection::DatabaseOperations::begin_session).
+#[tower_lsp::async_trait]
+pub trait Session: Send {
+    async fn execute(
+        &mut self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+    ) -> anyhow::Result<QueryOutput> {
+        self.execute_as(query, params, kind, &settings::get().format)
+            .await
+    }
+    /// [`execute`](Self::execute) with rows formatted by `format` instead
+    /// of the `format` init option.
+    async fn execute_as(
+        &mut self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+        format: &FormatOptions,
+    ) -> anyhow::Result<QueryOutput>;
+    async fn commit(self: Box<Self>) -> anyhow::Result<()>;
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Keep a session open, returning the id to run statements in it with.
+pub fn register(connection_id: &str, session: Box<dyn Session>) -> String {
+    let id = format!("session-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        sessions.insert(
+            id.clone(),
+            Registered {
+                session: Arc::new(Mutex::new(session)),
+                connection_id: connection_id.to_string(),
+                last_used: Instant::now(),
+            },
+        );
+    }
+    if !REAPER.swap(true, Ordering::Relaxed) {
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Some(timeout) = settings::get().session_idle_timeout() {
+                    expire_idle(timeout).await;
+                }
+            }
+        });
+    }
+    id
+}
+
+/// A session to run a statement in, counting as a use of it.
+pub fn get(id: &str) -> anyhow::Result<SharedSession> {
+    SESSIONS
+        .lock()
+        .ok()
+        .and_then(|mut sessions| {
+            sessions.get_mut(id).map(|registered| {
+                registered.last_used = Instant::now();
+                registered.session.clone()
+            })
+        })
+        .ok_or_else(|| anyhow::anyhow!("Unknown session: {}", id))
+}
+
+/// Roll back the sessions unused for `timeout`. A session running a
+/// statement isn't idle. MySQL locks still held are released first, since
+/// they would otherwise stay on the pooled connection.
+async fn expire_idle(timeout: Duration) {
+    let idle: Vec<(String, SharedSession)> = SESSIONS
+        .lock()
+        .map(|sessions| {
+            sessions
+                .iter()
+                .filter(|(_, registered)| registered.last_used.elapsed() >= timeout)
+                .map(|(id, registered)| (id.clone(), registered.session.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    for (id, shared) in idle {
+        let Ok(mut session) = shared.try_lock() else {
+            continue;
+        };
+        // 可能在检查之后刚被使用
+        let still_idle = SESSIONS.lock().is_ok_and(|sessions| {
+            sessions
+                .get(&id)
+                .is_some_and(|registered| registered.last_used.elapsed() >= timeout)
+        });
+        if !still_idle {
+            continue;
+        }
+        if !held_locks(&id).is_empty() {
+            if let Err(e) = session
+                .execute("SELECT RELEASE_ALL_LOCKS()", &[], ResultKind::Rows)
+                .await
+            {
+                log(
+                    MessageType::WARNING,
+                    format!("Could not release the locks of session {}: {}", id, e),
+                );
+            }
+            if let Ok(mut locks) = HELD_LOCKS.lock() {
+                locks.remove(&id);
+            }
+        }
+        drop(session);
+        drop(shared);
+        match end(&id, false).await {
+            Ok(()) => log(
+                MessageType::INFO,
+                format!(
+                    "Rolled back session {} after {} seconds unused",
+                    id,
+                    timeout.as_secs()
+                ),
+            ),
+            Err(e) => log(
+                MessageType::WARNING,
+                format!("Could not roll back idle session {}: {}", id, e),
+            ),
+        }
+    }
+}
+
+/// Record a connection-level lock taken or released in a session.
+pub fn track_lock(id: &str, name: &str, held: bool) {
+    if let Ok(mut locks) = HELD_LOCKS.lock() {
+        let names = locks.entry(id.to_string()).or_default();
+        if held {
+            names.insert(name.to_string());
+        } else {
+            names.remove(name);
+        }
+        if names.is_empty() {
+            locks.remove(id);
+        }
+    }
+}
+
+/// Connection-level locks a session still holds.
+pub fn held_locks(id: &str) -> Vec<String> {
+    let mut names: Vec<String> = HELD_LOCKS
+        .lock()
+        .ok()
+        .and_then(|locks| locks.get(id).map(|names| names.iter().cloned().collect()))
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Commit or roll back a session and release its connection. Waits for a
+/// statement still running in it. Refused while the session holds locks
+/// that would stay on the pooled connection.
+pub async fn end(id: &str, commit: bool) -> anyhow::Result<()> {
+    let locks = held_locks(id);
+    if !locks.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Session {} still holds the locks {}, release them before ending it",
+            id,
+            locks.join(", ")
+        ));
+    }
+    let Registered {
+        session: shared,
+        connection_id,
+    } = SESSIONS
+        .lock()
+        .ok()
+        .and_then(|mut sessions| sessions.remove(id))
+        .ok_or_else(|| anyhow::anyhow!("Unknown session: {}", id))?;
+    // 等待正在执行的语句结束后再取出会话
+    drop(shared.lock().await);
+    let session = Arc::try_unwrap(shared)
+        .map_err(|_| anyhow::anyhow!("Session {} is still in use", id))?
+        .into_inner();
+    if commit {
+        session.commit().await?;
+        cache::clear(Some(&connection_id));
+        Ok(())
+    } else {
+        session.rollback().await
+    }
+}
@@ -1,19 +1,316 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use sqlparser::{
+    ast::{ColumnOption, Statement, TableConstraint},
+    dialect::SQLiteDialect,
+    parser::Parser,
+};
 
-use sqlx::{Column, Row, Sqlite, sqlite::SqlitePoolOptions};
+use futures::TryStreamExt;
+use sqlx::{
+    Row, Sqlite,
+    query::Query,
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqlitePoolOptions},
+};
+use tokio::sync::mpsc::Sender;
+use tower_lsp::lsp_types::MessageType;
+
+use crate::{logger::log, parser::ResultKind, settings};
 
 use super::{
-    ConnectionPool,
-    connection::{DBConnectionOptions, DBSet, DatabaseManager, DatabaseOperations},
+    ConnectionPool, DatabaseType, column_meta,
+    connection::{
+        BindValue, BulkTransaction, CheckConstraint, ColumnInfo, DBConnectionOptions, DBSet,
+        DatabaseManager, DatabaseOperations, ForeignKey, IdentityInfo, IndexInfo,
+        MaintenanceAction, QueryOutput, QueryTiming, SequenceInfo, ServerVariable, StreamItem,
+        TableStats, TransactionOutput, TriggerInfo, execute_in, group_index_columns,
+        run_transaction,
+    },
+    describe_columns,
+    dialect::Dialect,
+    explain::QueryEstimate,
+    session::Session,
+    value::{self, FormatOptions},
 };
 
+/// Expressions of the generated columns in a CREATE TABLE statement, which
+/// `PRAGMA table_xinfo` doesn't report.
+fn generation_exprs(sql: &str) -> HashMap<String, String> {
+    let mut exprs = HashMap::new();
+    let Ok(statements) = Parser::parse_sql(&SQLiteDialect {}, sql) else {
+        return exprs;
+    };
+    for statement in statements {
+        let Statement::CreateTable(create) = statement else {
+            continue;
+        };
+        for column in create.columns {
+            for option in column.options {
+                if let ColumnOption::Generated {
+                    generation_expr: Some(expr),
+                    ..
+                } = option.option
+                {
+                    exprs.insert(column.name.value.clone(), expr.to_string());
+                }
+            }
+        }
+    }
+    exprs
+}
+
+/// Length of the name at the start of `text`, quoted in any of the styles
+/// SQLite accepts or bare.
+fn name_len(text: &str) -> Option<usize> {
+    let close = match text.chars().next()? {
+        '"' => '"',
+        '`' => '`',
+        '\'' => '\'',
+        '[' => ']',
+        _ => {
+            let len = text
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(text.len());
+            return (len > 0).then_some(len);
+        }
+    };
+    // 引号内用两个引号表示一个
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == close {
+            if close != ']' && chars.peek().is_some_and(|(_, next)| *next == close) {
+                chars.next();
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// The CREATE TABLE statement `sql` from `sqlite_master` with the table
+/// renamed to `target`. Only the name is replaced, so the rest, such as
+/// `STRICT` or `WITHOUT ROWID`, stays as written.
+fn rename_create_table(sql: &str, target: &str) -> anyhow::Result<String> {
+    let not_create_table = || anyhow::anyhow!("Not a CREATE TABLE statement: {}", sql);
+    // sqlite_master 中的语句以 CREATE TABLE 开头，已去掉 TEMP 和 IF NOT EXISTS
+    let prefix = sql.get(..12).ok_or_else(not_create_table)?;
+    if !prefix.eq_ignore_ascii_case("CREATE TABLE") {
+        return Err(not_create_table());
+    }
+    let start = sql.len() - sql[12..].trim_start().len();
+    let mut end = start + name_len(&sql[start..]).ok_or_else(not_create_table)?;
+    // schema.table
+    if sql[end..].starts_with('.') {
+        end += 1 + name_len(&sql[end + 1..]).ok_or_else(not_create_table)?;
+    }
+    Ok(format!(
+        "{}{}{}",
+        &sql[..start],
+        Dialect::Sqlite.quote_ident(target),
+        &sql[end..]
+    ))
+}
+
+/// SQLite only keeps the CREATE TRIGGER text, so pull the timing and event
+/// out of the words before `ON`.
+fn parse_trigger_header(sql: &str) -> (Option<String>, Option<String>) {
+    let upper = sql.to_uppercase();
+    let header = upper.split(" ON ").next().unwrap_or_default();
+    let words: Vec<&str> = header.split_whitespace().collect();
+
+    let timing = if words.contains(&"BEFORE") {
+        Some("BEFORE".to_string())
+    } else if words.contains(&"INSTEAD") {
+        Some("INSTEAD OF".to_string())
+    } else {
+        // AFTER is the default when no timing is given
+        Some("AFTER".to_string())
+    };
+    let event = ["INSERT", "UPDATE", "DELETE"]
+        .into_iter()
+        .find(|event| words.contains(event))
+        .map(str::to_string);
+
+    (timing, event)
+}
+
+/// CHECK constraints of a CREATE TABLE statement. When the statement
+/// doesn't parse, the text is scanned for `CHECK (...)` instead.
+fn parse_check_constraints(sql: &str) -> Vec<CheckConstraint> {
+    let Ok([Statement::CreateTable(create)]) = Parser::parse_sql(&SQLiteDialect {}, sql).as_deref()
+    else {
+        return scan_check_constraints(sql);
+    };
+
+    let mut constraints = Vec::new();
+    for column in &create.columns {
+        for option in &column.options {
+            if let ColumnOption::Check(expr) = &option.option {
+                constraints.push(CheckConstraint {
+                    name: option.name.as_ref().map(|name| name.value.clone()),
+                    column: Some(column.name.value.clone()),
+                    expression: expr.to_string(),
+                    approximate: false,
+                });
+            }
+        }
+    }
+    for constraint in &create.constraints {
+        if let TableConstraint::Check { name, expr } = constraint {
+            constraints.push(CheckConstraint {
+                name: name.as_ref().map(|name| name.value.clone()),
+                column: None,
+                expression: expr.to_string(),
+                approximate: false,
+            });
+        }
+    }
+    constraints
+}
+
+/// Text in balanced parentheses after each `CHECK` keyword. Parentheses
+/// inside string literals can throw this off.
+fn scan_check_constraints(sql: &str) -> Vec<CheckConstraint> {
+    // ASCII 大写不改变字节偏移
+    let upper = sql.to_ascii_uppercase();
+    let mut constraints = Vec::new();
+    let mut from = 0;
+    while let Some(found) = upper[from..].find("CHECK") {
+        let keyword = from + found;
+        from = keyword + "CHECK".len();
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if is_word(sql[..keyword].chars().next_back()) {
+            continue;
+        }
+        let rest = sql[from..].trim_start();
+        if !rest.starts_with('(') {
+            continue;
+        }
+        let open = sql.len() - rest.len();
+        let mut depth = 0;
+        let close = sql[open..].char_indices().find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(open + i)
+        });
+        let Some(close) = close else {
+            break;
+        };
+        constraints.push(CheckConstraint {
+            name: None,
+            column: None,
+            expression: sql[open + 1..close].trim().to_string(),
+            approximate: true,
+        });
+        from = close + 1;
+    }
+    constraints
+}
+
+/// Pragmas that may be set through `driver_options`.
+const PRAGMAS: &[&str] = &[
+    "busy_timeout",
+    "cache_size",
+    "foreign_keys",
+    "journal_mode",
+    "synchronous",
+    "temp_store",
+    "mmap_size",
+    "locking_mode",
+    "case_sensitive_like",
+    "recursive_triggers",
+];
+
+/// Read-only pragmas reported as variables besides [`PRAGMAS`].
+const INFO_PRAGMAS: &[&str] = &[
+    "auto_vacuum",
+    "encoding",
+    "page_size",
+    "page_count",
+    "freelist_count",
+    "user_version",
+    "application_id",
+    "wal_autocheckpoint",
+];
+
+/// SQL LIKE matching: `%` matches any run of characters, `_` a single one,
+/// and letters compare case-insensitively.
+fn like_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    // matched[j]: the pattern so far matches the first j characters
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for p in pattern {
+        let mut next = vec![false; text.len() + 1];
+        for j in 0..=text.len() {
+            next[j] = match p {
+                '%' => matched[j] || (j > 0 && next[j - 1]),
+                '_' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && text[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
+
 #[tower_lsp::async_trait]
 impl DatabaseManager<Sqlite> for DBSet<Sqlite> {
     async fn create(options: &DBConnectionOptions) -> anyhow::Result<DBSet<Sqlite>> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(30))
-            .connect_lazy(&options.connection_string)?;
+        let connection_string = &options.connection_string;
+        let mut connect_options = if connection_string.starts_with("sqlite:") {
+            connection_string.parse()?
+        } else {
+            // 通过类型提示打开的普通文件路径
+            SqliteConnectOptions::new().filename(connection_string)
+        };
+        // 没有 SQLCipher 时 PRAGMA key 什么也不做，之后只会报 key 错误
+        #[cfg(not(feature = "sqlcipher"))]
+        if options.key.is_some() {
+            return Err(anyhow::anyhow!(
+                "Encrypted databases are not available, the server was built without SQLCipher \
+                 support (the `sqlcipher` feature)"
+            ));
+        }
+        // sqlx 总是最先执行 key pragma，保证解密发生在其它 pragma 之前
+        if let Some(key) = &options.key {
+            connect_options = connect_options.pragma("key", Dialect::Sqlite.quote_literal(key));
+        }
+        for (key, value) in settings::get().driver_options {
+            if PRAGMAS.contains(&key.as_str()) {
+                connect_options = connect_options.pragma(key, settings::option_value(&value));
+            } else {
+                log(
+                    MessageType::WARNING,
+                    format!("Ignoring unknown SQLite driver option: {}", key),
+                );
+            }
+        }
+
+        let mut pool_options = SqlitePoolOptions::new()
+            .max_connections(settings::get().pool_size())
+            .acquire_timeout(Duration::from_secs(30));
+        if options.key.is_some() {
+            // 错误的 key 直到第一次读取才会暴露出来
+            pool_options = pool_options.after_connect(|conn, _meta| {
+                Box::pin(async move {
+                    sqlx::query("SELECT count(*) FROM sqlite_master")
+                        .execute(&mut *conn)
+                        .await
+                        .map_err(key_error)?;
+                    Ok(())
+                })
+            });
+        }
+        let pool = pool_options.connect_lazy_with(connect_options);
 
         Ok(DBSet::new(pool))
     }
@@ -25,43 +322,213 @@ impl Into<ConnectionPool> for DBSet<Sqlite> {
     }
 }
 
+/// Replace SQLite's "file is not a database" error with one that points at
+/// the key.
+fn key_error(err: sqlx::Error) -> sqlx::Error {
+    match &err {
+        sqlx::Error::Database(e) if e.message().contains("file is not a database") => {
+            sqlx::Error::Configuration(
+                "file is encrypted or not a database: the SQLite key is wrong or missing".into(),
+            )
+        }
+        _ => err,
+    }
+}
+
+/// Build a query with `params` bound in order.
+fn prepare<'q>(sql: &'q str, params: &'q [BindValue]) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let mut query = sqlx::query(sql);
+    for param in params {
+        query = match param {
+            BindValue::Null => query.bind(None::<String>),
+            BindValue::Bool(v) => query.bind(*v),
+            BindValue::Int(v) => query.bind(*v),
+            BindValue::Float(v) => query.bind(*v),
+            BindValue::Text(v) => query.bind(v.as_str()),
+            BindValue::Date(v) => query.bind(*v),
+            BindValue::Time(v) => query.bind(*v),
+            BindValue::Timestamp(v) => query.bind(*v),
+            BindValue::Timestamptz(v) => query.bind(*v),
+            // SQLite has no decimal type, keep the exact digits as text
+            BindValue::Decimal(v) => query.bind(v.to_string()),
+        };
+    }
+    query
+}
+
 /// SQLite specific operations
 pub struct SQLiteOperations(DBSet<Sqlite>);
 
+impl SQLiteOperations {
+    /// The first primary key column of a table, if it declares one.
+    async fn primary_key_column(&self, table_name: &str) -> anyhow::Result<Option<String>> {
+        let column: Option<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?) WHERE pk = 1")
+                .bind(table_name)
+                .fetch_optional(self.0.pool().as_ref())
+                .await?;
+        Ok(column)
+    }
+}
+
+/// A transaction started by [`DatabaseOperations::begin_session`].
+struct SQLiteSession(BulkTransaction<Sqlite>);
+
+#[tower_lsp::async_trait]
+impl Session for SQLiteSession {
+    async fn execute_as(
+        &mut self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+        format: &FormatOptions,
+    ) -> anyhow::Result<QueryOutput> {
+        execute_in(
+            &mut *self.0,
+            prepare(query, params),
+            kind,
+            |result| result.rows_affected(),
+            |row| value::sqlite_row(row, format),
+        )
+        .await
+    }
+
+    async fn commit(self: Box<Self>) -> anyhow::Result<()> {
+        self.0.commit().await
+    }
+
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()> {
+        self.0.rollback().await
+    }
+}
+
 #[tower_lsp::async_trait]
 impl DatabaseOperations for SQLiteOperations {
-    async fn execute_query(&self, query: &str) -> anyhow::Result<(serde_json::Value, usize)> {
-        // For SELECT queries, fetch rows
-        if query.trim().to_lowercase().starts_with("select") {
-            let rows = sqlx::query(query).fetch_all(self.0.pool().as_ref()).await?;
+    fn database_type(&self) -> DatabaseType {
+        DatabaseType::SQLite
+    }
+
+    async fn execute_query_as(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        kind: ResultKind,
+        format: &FormatOptions,
+    ) -> anyhow::Result<QueryOutput> {
+        let started = Instant::now();
+        let mut conn = self.0.acquire().await?;
+        let mut timing = QueryTiming {
+            acquire: started.elapsed(),
+            ..Default::default()
+        };
+
+        // For queries producing a result set, fetch rows
+        if kind == ResultKind::Rows {
+            let started = Instant::now();
+            let rows = prepare(query, params).fetch_all(&mut *conn).await?;
+            timing.execute = started.elapsed();
+
+            let started = Instant::now();
             let total = rows.len();
+            let columns = rows.first().map(column_meta).unwrap_or_default();
             // Convert to JSON
             let mut result = Vec::new();
             for row in rows {
-                let mut obj = serde_json::Map::new();
-
-                // Convert each column to a JSON value
-                for (i, column) in row.columns().iter().enumerate() {
-                    let column_name = column.name();
-                    let value: Option<String> = row.try_get(i)?;
-                    obj.insert(
-                        column_name.to_string(),
-                        serde_json::Value::String(value.unwrap_or_default()),
-                    );
-                }
-
-                result.push(serde_json::Value::Object(obj));
+                result.push(serde_json::Value::Object(value::sqlite_row(&row, format)?));
             }
 
-            Ok((serde_json::Value::Array(result), total))
+            timing.serialize = started.elapsed();
+
+            Ok(QueryOutput {
+                columns,
+                rows: serde_json::Value::Array(result),
+                total,
+                timing,
+                warnings: Vec::new(),
+            })
         } else {
-            // For non-SELECT queries, return affected rows
-            let result = sqlx::query(query).execute(self.0.pool().as_ref()).await?;
+            // For everything else, return affected rows
+            let started = Instant::now();
+            let result = prepare(query, params).execute(&mut *conn).await?;
+            timing.execute = started.elapsed();
 
-            Ok((serde_json::Value::Null, result.rows_affected() as usize))
+            Ok(QueryOutput {
+                columns: Vec::new(),
+                rows: serde_json::Value::Null,
+                total: result.rows_affected() as usize,
+                timing,
+                warnings: Vec::new(),
+            })
         }
     }
 
+    async fn execute_transaction(
+        &self,
+        statements: &[String],
+        continue_on_error: bool,
+    ) -> anyhow::Result<TransactionOutput> {
+        let _permit = self.0.bulk_permit().await?;
+        run_transaction(
+            self.0.pool().as_ref(),
+            statements,
+            continue_on_error,
+            |result| result.rows_affected(),
+        )
+        .await
+    }
+
+    async fn begin_session(&self) -> anyhow::Result<Box<dyn Session>> {
+        Ok(Box::new(SQLiteSession(self.0.begin().await?)))
+    }
+
+    async fn stream_query(
+        &self,
+        query: &str,
+        params: &[BindValue],
+        max_rows: Option<u64>,
+        format: &FormatOptions,
+        tx: Sender<StreamItem>,
+    ) -> anyhow::Result<bool> {
+        let _permit = self.0.bulk_permit().await?;
+        let mut rows = prepare(query, params).fetch(self.0.pool().as_ref());
+        let mut first = true;
+        let mut sent = 0;
+        while let Some(row) = rows.try_next().await? {
+            if max_rows.is_some_and(|max| sent >= max) {
+                // 达到行数上限后直接丢弃结果流，不再读取剩余的行
+                return Ok(true);
+            }
+            if first {
+                first = false;
+                if tx
+                    .send(StreamItem::Columns(column_meta(&row)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(false);
+                }
+            }
+            let row = value::sqlite_row(&row, format)?;
+            if tx.send(StreamItem::Row(row)).await.is_err() {
+                return Ok(false);
+            }
+            sent += 1;
+        }
+        drop(rows);
+        if first && let Ok(columns) = describe_columns(self.0.pool().as_ref(), query).await {
+            let _ = tx.send(StreamItem::Columns(columns)).await;
+        }
+        Ok(false)
+    }
+
+    async fn explain_estimate(&self, query: &str) -> anyhow::Result<QueryEstimate> {
+        // EXPLAIN QUERY PLAN 只描述访问路径，没有行数和代价估算，
+        // 这里只用来校验语句
+        let sql = format!("EXPLAIN QUERY PLAN {}", query);
+        sqlx::query(&sql).fetch_all(self.0.pool().as_ref()).await?;
+        Ok(QueryEstimate::default())
+    }
+
     async fn get_tables(&self) -> anyhow::Result<Vec<String>> {
         let rows = sqlx::query(
             "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
@@ -78,6 +545,23 @@ impl DatabaseOperations for SQLiteOperations {
         Ok(tables)
     }
 
+    async fn get_views(&self) -> anyhow::Result<Vec<String>> {
+        let views: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'view' ORDER BY name")
+                .fetch_all(self.0.pool().as_ref())
+                .await?;
+        Ok(views)
+    }
+
+    async fn get_view_definition(&self, view: &str) -> anyhow::Result<String> {
+        let definition: Option<String> =
+            sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'view' AND name = ?")
+                .bind(view)
+                .fetch_optional(self.0.pool().as_ref())
+                .await?;
+        definition.ok_or_else(|| anyhow::anyhow!("View not found: {}", view))
+    }
+
     async fn get_columns(&self, table_name: &str) -> anyhow::Result<Vec<String>> {
         let query = format!("PRAGMA table_info({})", table_name);
         let rows = sqlx::query(&query)
@@ -93,10 +577,514 @@ impl DatabaseOperations for SQLiteOperations {
         Ok(columns)
     }
 
+    async fn get_column_info(&self, table_name: &str) -> anyhow::Result<Vec<ColumnInfo>> {
+        let rows = sqlx::query(
+            "SELECT name, type, \"notnull\", hidden FROM pragma_table_xinfo(?) ORDER BY cid",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let sql: Option<String> =
+            sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(table_name)
+                .fetch_optional(self.0.pool().as_ref())
+                .await?
+                .flatten();
+        let mut exprs = sql.as_deref().map(generation_exprs).unwrap_or_default();
+
+        let mut columns = Vec::new();
+        for row in rows {
+            let name: String = row.try_get("name")?;
+            // hidden: 2 是虚拟生成列，3 是存储生成列
+            let hidden: i64 = row.try_get("hidden")?;
+            let is_generated = hidden == 2 || hidden == 3;
+            columns.push(ColumnInfo {
+                generation_expr: if is_generated {
+                    exprs.remove(&name)
+                } else {
+                    None
+                },
+                data_type: row.try_get("type")?,
+                nullable: row.try_get::<i64, _>("notnull")? == 0,
+                is_generated,
+                name,
+            });
+        }
+        Ok(columns)
+    }
+
+    async fn get_primary_keys(&self, table_name: &str) -> anyhow::Result<Vec<String>> {
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")
+                .bind(table_name)
+                .fetch_all(self.0.pool().as_ref())
+                .await?;
+        Ok(columns)
+    }
+
+    async fn get_schema_tables(&self) -> anyhow::Result<Vec<(String, String)>> {
+        // Attached databases aren't listed, only the main one
+        Ok(self
+            .get_tables()
+            .await?
+            .into_iter()
+            .map(|table| ("main".to_string(), table))
+            .collect())
+    }
+
+    async fn maintain_table(
+        &self,
+        table: &str,
+        action: MaintenanceAction,
+    ) -> anyhow::Result<Vec<String>> {
+        let sql = match action {
+            // SQLite only vacuums whole databases
+            MaintenanceAction::Vacuum => "VACUUM".to_string(),
+            MaintenanceAction::Analyze => {
+                format!("ANALYZE {}", self.native_dialect().quote_ident(table))
+            }
+            MaintenanceAction::Optimize => {
+                return Err(anyhow::anyhow!("SQLite does not support OPTIMIZE"));
+            }
+        };
+        sqlx::query(&sql).execute(self.0.pool().as_ref()).await?;
+        Ok(Vec::new())
+    }
+
+    async fn clone_table_structure(&self, source: &str, target: &str) -> anyhow::Result<()> {
+        let sql: Option<String> =
+            sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(source)
+                .fetch_optional(self.0.pool().as_ref())
+                .await?
+                .flatten();
+        let sql = sql.ok_or_else(|| anyhow::anyhow!("Table not found: {}", source))?;
+        // SQLite has no CREATE TABLE ... LIKE, rebuild the statement under the
+        // new name. Indexes are left out since their names would clash.
+        let sql = rename_create_table(&sql, target)?;
+        sqlx::query(&sql).execute(self.0.pool().as_ref()).await?;
+        Ok(())
+    }
+
+    async fn get_triggers(&self, table_name: &str) -> anyhow::Result<Vec<TriggerInfo>> {
+        let rows = sqlx::query(
+            "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ? ORDER BY name",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+
+        let mut triggers = Vec::new();
+        for row in rows {
+            let definition: Option<String> = row.try_get("sql")?;
+            let (timing, event) = definition
+                .as_deref()
+                .map(parse_trigger_header)
+                .unwrap_or_default();
+            triggers.push(TriggerInfo {
+                name: row.try_get("name")?,
+                timing,
+                event,
+                definition,
+            });
+        }
+
+        Ok(triggers)
+    }
+
+    async fn get_indexes(&self, table_name: &str) -> anyhow::Result<Vec<IndexInfo>> {
+        // Key parts on expressions have no name
+        let rows: Vec<(String, bool, Option<String>)> = sqlx::query_as(
+            "SELECT il.name, il.\"unique\", ii.name \
+            FROM pragma_index_list(?) il, pragma_index_info(il.name) ii \
+            WHERE il.origin <> 'pk' \
+            ORDER BY il.name, ii.seqno",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+        Ok(group_index_columns(table_name, rows))
+    }
+
+    async fn get_server_variables(
+        &self,
+        pattern: Option<&str>,
+    ) -> anyhow::Result<Vec<ServerVariable>> {
+        let mut variables = Vec::new();
+        for name in PRAGMAS.iter().chain(INFO_PRAGMAS) {
+            if pattern.is_some_and(|pattern| !like_match(pattern, name)) {
+                continue;
+            }
+            let row = sqlx::query(&format!("PRAGMA {}", name))
+                .fetch_optional(self.0.pool().as_ref())
+                .await?;
+            let value = match row {
+                Some(row) => row.try_get_unchecked::<Option<String>, _>(0)?,
+                None => None,
+            };
+            variables.push(ServerVariable {
+                name: name.to_string(),
+                value,
+                unit: None,
+                description: None,
+            });
+        }
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(variables)
+    }
+
+    async fn get_check_constraints(
+        &self,
+        table_name: &str,
+    ) -> anyhow::Result<Vec<CheckConstraint>> {
+        let sql: Option<Option<String>> =
+            sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(table_name)
+                .fetch_optional(self.0.pool().as_ref())
+                .await?;
+        let sql = sql
+            .ok_or_else(|| anyhow::anyhow!("Table {} does not exist", table_name))?
+            .unwrap_or_default();
+        Ok(parse_check_constraints(&sql))
+    }
+
+    async fn get_foreign_keys(&self, table_name: &str) -> anyhow::Result<Vec<ForeignKey>> {
+        let query = format!(
+            "PRAGMA foreign_key_list({})",
+            self.native_dialect().quote_ident(table_name)
+        );
+        let rows = sqlx::query(&query)
+            .fetch_all(self.0.pool().as_ref())
+            .await?;
+
+        let mut foreign_keys = Vec::new();
+        for row in rows {
+            let referenced_table: String = row.try_get("table")?;
+            // `to` is NULL when the key references the parent's primary key
+            let referenced_column = match row.try_get::<Option<String>, _>("to")? {
+                Some(column) => column,
+                None => self
+                    .primary_key_column(&referenced_table)
+                    .await?
+                    .unwrap_or_else(|| "rowid".to_string()),
+            };
+            foreign_keys.push(ForeignKey {
+                name: None,
+                id: Some(row.try_get("id")?),
+                column: row.try_get("from")?,
+                referenced_table,
+                referenced_column,
+            });
+        }
+
+        Ok(foreign_keys)
+    }
+
+    async fn get_table_stats(&self, table_name: &str) -> anyhow::Result<TableStats> {
+        // SQLite 没有行数统计，只能 COUNT(*)
+        let sql = format!(
+            "SELECT COUNT(*) FROM {}",
+            self.native_dialect().quote_ident(table_name)
+        );
+        let rows: i64 = sqlx::query_scalar(&sql)
+            .fetch_one(self.0.pool().as_ref())
+            .await?;
+        // dbstat 虚拟表需要 SQLITE_ENABLE_DBSTAT_VTAB，没有时大小未知
+        let size_bytes: Option<i64> = sqlx::query_scalar(
+            "SELECT SUM(pgsize) FROM dbstat WHERE name = ? \
+                OR name IN (SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = ?)",
+        )
+        .bind(table_name)
+        .bind(table_name)
+        .fetch_one(self.0.pool().as_ref())
+        .await
+        .ok()
+        .flatten();
+        Ok(TableStats {
+            rows: Some(rows),
+            size_bytes,
+            estimated: false,
+        })
+    }
+
+    async fn get_table_ddl(&self, table_name: &str) -> anyhow::Result<String> {
+        let rows = sqlx::query(
+            "SELECT sql FROM sqlite_master \
+            WHERE tbl_name = ? AND type IN ('table', 'index') AND sql IS NOT NULL \
+            ORDER BY type = 'table' DESC, name",
+        )
+        .bind(table_name)
+        .fetch_all(self.0.pool().as_ref())
+        .await?;
+        if rows.is_empty() {
+            return Err(anyhow::anyhow!("Table not found: {}", table_name));
+        }
+
+        let mut statements = Vec::new();
+        for row in rows {
+            let sql: String = row.try_get("sql")?;
+            statements.push(format!("{};", sql));
+        }
+        Ok(statements.join("\n"))
+    }
+
+    async fn table_exists(&self, table_name: &str) -> anyhow::Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master \
+            WHERE type IN ('table', 'view') AND name = ? COLLATE NOCASE",
+        )
+        .bind(table_name)
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn column_exists(&self, table_name: &str, column_name: &str) -> anyhow::Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ? COLLATE NOCASE",
+        )
+        .bind(table_name)
+        .bind(column_name)
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        Ok(count > 0)
+    }
+
     async fn check_connection(&self) -> anyhow::Result<bool> {
         sqlx::query("SELECT 1")
             .execute(self.0.pool().as_ref())
             .await?;
         Ok(true)
     }
+
+    async fn close(&self) {
+        self.0.close().await;
+    }
+
+    async fn get_sequences(&self) -> anyhow::Result<Vec<SequenceInfo>> {
+        // sqlite_sequence 只在第一张 AUTOINCREMENT 表创建后才存在
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_sequence'",
+        )
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query("SELECT name, seq FROM sqlite_sequence ORDER BY name")
+            .fetch_all(self.0.pool().as_ref())
+            .await?;
+
+        let mut sequences = Vec::new();
+        for row in rows {
+            let table: String = row.try_get("name")?;
+            sequences.push(SequenceInfo {
+                schema: None,
+                name: table.clone(),
+                table: Some(table),
+                last_value: row.try_get("seq")?,
+                increment: Some(1),
+                min_value: None,
+                max_value: None,
+            });
+        }
+        Ok(sequences)
+    }
+
+    async fn get_identity(&self, table_name: &str) -> anyhow::Result<IdentityInfo> {
+        // 只有单列的 INTEGER PRIMARY KEY 是 rowid 的别名，会自动编号
+        let keys: Vec<(String, String)> =
+            sqlx::query_as("SELECT name, type FROM pragma_table_info(?) WHERE pk > 0")
+                .bind(table_name)
+                .fetch_all(self.0.pool().as_ref())
+                .await?;
+        let column = match keys.as_slice() {
+            [(name, data_type)] if data_type.eq_ignore_ascii_case("INTEGER") => name.clone(),
+            _ => return Ok(IdentityInfo::default()),
+        };
+
+        // AUTOINCREMENT 表不会重用删掉的最大值，sqlite_sequence 记录了用过的最大值
+        let has_sequence: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_sequence'",
+        )
+        .fetch_one(self.0.pool().as_ref())
+        .await?;
+        let mut last_value: i64 = 0;
+        if has_sequence > 0 {
+            let seq: Option<i64> =
+                sqlx::query_scalar("SELECT seq FROM sqlite_sequence WHERE name = ?")
+                    .bind(table_name)
+                    .fetch_optional(self.0.pool().as_ref())
+                    .await?;
+            last_value = seq.unwrap_or(0);
+        }
+        let sql = format!(
+            "SELECT COALESCE(MAX({}), 0) FROM {}",
+            self.native_dialect().quote_ident(&column),
+            self.native_dialect().quote_ident(table_name)
+        );
+        let max_id: i64 = sqlx::query_scalar(&sql)
+            .fetch_one(self.0.pool().as_ref())
+            .await?;
+        Ok(IdentityInfo {
+            column: Some(column),
+            next_value: Some(last_value.max(max_id) + 1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Operations over a fresh in-memory database.
+    async fn memory_operations() -> SQLiteOperations {
+        let options = DBConnectionOptions {
+            connection_string: "sqlite::memory:".to_string(),
+            ..Default::default()
+        };
+        SQLiteOperations(DBSet::<Sqlite>::create(&options).await.unwrap())
+    }
+
+    #[test]
+    fn test_generation_exprs() {
+        let exprs = generation_exprs(
+            "CREATE TABLE t (a INTEGER, b INTEGER GENERATED ALWAYS AS (a * 2) STORED, \
+            c TEXT AS (upper(d)), d TEXT)",
+        );
+        assert_eq!(exprs.len(), 2);
+        assert_eq!(exprs["b"], "a * 2");
+        assert_eq!(exprs["c"], "upper(d)");
+    }
+
+    #[test]
+    fn test_rename_create_table() {
+        assert_eq!(
+            rename_create_table(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+                "copy"
+            )
+            .unwrap(),
+            "CREATE TABLE \"copy\" (id INTEGER PRIMARY KEY, name TEXT)"
+        );
+        assert_eq!(
+            rename_create_table(
+                "CREATE TABLE main.\"user \"\"list\"\"\"(id INT) STRICT, WITHOUT ROWID",
+                "copy"
+            )
+            .unwrap(),
+            "CREATE TABLE \"copy\"(id INT) STRICT, WITHOUT ROWID"
+        );
+        assert!(rename_create_table("CREATE VIEW v AS SELECT 1", "copy").is_err());
+    }
+
+    #[test]
+    fn test_like_match() {
+        assert!(like_match("%cache%", "cache_size"));
+        assert!(like_match("PAGE_%", "page_size"));
+        assert!(like_match("page_siz_", "page_size"));
+        assert!(!like_match("page_", "page_size"));
+        assert!(!like_match("%journal", "journal_mode"));
+        assert!(like_match("%", ""));
+    }
+
+    #[test]
+    fn test_parse_check_constraints() {
+        let constraints = parse_check_constraints(
+            "CREATE TABLE t (price REAL CHECK (price > 0), qty INTEGER, \
+            CONSTRAINT qty_range CHECK (qty BETWEEN 1 AND 10))",
+        );
+        assert_eq!(
+            constraints,
+            vec![
+                CheckConstraint {
+                    name: None,
+                    column: Some("price".to_string()),
+                    expression: "price > 0".to_string(),
+                    approximate: false,
+                },
+                CheckConstraint {
+                    name: Some("qty_range".to_string()),
+                    column: None,
+                    expression: "qty BETWEEN 1 AND 10".to_string(),
+                    approximate: false,
+                },
+            ]
+        );
+
+        let scanned = scan_check_constraints(
+            "CREATE TABLE t (rechecked INT, a INT check((a + 1) > 0), b check (b <> ''))",
+        );
+        let expressions: Vec<&str> = scanned.iter().map(|c| c.expression.as_str()).collect();
+        assert_eq!(expressions, ["(a + 1) > 0", "b <> ''"]);
+        assert!(scanned.iter().all(|c| c.approximate));
+    }
+
+    #[tokio::test]
+    async fn test_null_is_not_empty_string() {
+        let operations = memory_operations().await;
+        let output = operations
+            .execute_query("SELECT NULL AS missing, '' AS empty", &[], ResultKind::Rows)
+            .await
+            .unwrap();
+        assert_eq!(output.rows[0]["missing"], serde_json::Value::Null);
+        assert_eq!(output.rows[0]["empty"], serde_json::json!(""));
+    }
+
+    #[tokio::test]
+    async fn test_get_identity() {
+        let operations = memory_operations().await;
+        for sql in [
+            "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+            "CREATE TABLE tags (name TEXT PRIMARY KEY)",
+            "INSERT INTO items (name) VALUES ('a'), ('b'), ('c')",
+            "DELETE FROM items WHERE id = 3",
+        ] {
+            operations
+                .execute_query(sql, &[], ResultKind::Affected)
+                .await
+                .unwrap();
+        }
+        let identity = operations.get_identity("items").await.unwrap();
+        assert_eq!(identity.column.as_deref(), Some("id"));
+        // AUTOINCREMENT 不会重用删掉的 3
+        assert_eq!(identity.next_value, Some(4));
+
+        let identity = operations.get_identity("tags").await.unwrap();
+        assert_eq!(identity.column, None);
+        assert_eq!(identity.next_value, None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_columns_without_rows() {
+        let operations = memory_operations().await;
+        operations
+            .execute_query(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+                &[],
+                ResultKind::Affected,
+            )
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let truncated = operations
+            .stream_query(
+                "SELECT id, name FROM items",
+                &[],
+                None,
+                &FormatOptions::default(),
+                tx,
+            )
+            .await
+            .unwrap();
+        assert!(!truncated);
+        // 没有行时也要先收到列，导出的 CSV 才有表头
+        let Some(StreamItem::Columns(columns)) = rx.recv().await else {
+            panic!("expected the columns");
+        };
+        let names: Vec<_> = columns.into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["id", "name"]);
+        assert!(rx.recv().await.is_none());
+    }
 }
@@ -0,0 +1,490 @@
+use std::str::FromStr;
+
+use base64::Engine;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{
+    Column, ColumnIndex, Decode, Row, Type, TypeInfo,
+    mysql::MySqlRow,
+    postgres::PgRow,
+    sqlite::SqliteRow,
+    types::{Decimal, Uuid},
+};
+
+use super::{
+    blob,
+    connection::{BindValue, ParamType, QueryParam},
+    wkt,
+};
+
+/// How dates and times are rendered in results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateFormat {
+    /// ISO-8601 text
+    #[default]
+    Iso,
+    /// Milliseconds since the Unix epoch, values without a zone taken as UTC.
+    /// Times of day stay text.
+    EpochMillis,
+}
+
+/// Zone `TIMESTAMPTZ` values are shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Timezone {
+    #[default]
+    Utc,
+    Local,
+}
+
+/// Result value formatting, sent as the `format` init option.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    pub date_format: DateFormat,
+    /// Only applies to values stored with a zone, timestamps without one are
+    /// shown as stored
+    pub timezone: Timezone,
+    /// Text shown for NULL, JSON null when unset
+    pub null_display: Option<String>,
+    /// Binary values longer than this many bytes are returned as
+    /// `{ "blob_ref": id, "size": n }`, to download with the fetch blob
+    /// command. Always inlined when unset.
+    pub blob_threshold: Option<usize>,
+    /// Render PostGIS and MySQL spatial values as Well-Known-Text instead
+    /// of binary
+    pub spatial_wkt: bool,
+}
+
+impl FormatOptions {
+    /// Formatting for rows written to files: NULL stays null and binary
+    /// values are always inlined instead of kept in the blob store.
+    pub fn for_export(&self) -> Self {
+        Self {
+            null_display: None,
+            blob_threshold: None,
+            ..self.clone()
+        }
+    }
+
+    /// Formatting for rows turned into Arrow columns, which are parsed
+    /// back from the values: [`for_export`](Self::for_export) with dates
+    /// always as ISO text.
+    pub fn for_arrow(&self) -> Self {
+        Self {
+            date_format: DateFormat::Iso,
+            ..self.for_export()
+        }
+    }
+
+    pub fn null(&self) -> Value {
+        match &self.null_display {
+            Some(text) => Value::String(text.clone()),
+            None => Value::Null,
+        }
+    }
+
+    pub fn date(&self, date: NaiveDate) -> Value {
+        match self.date_format {
+            DateFormat::Iso => Value::String(date.format("%Y-%m-%d").to_string()),
+            DateFormat::EpochMillis => self.timestamp(date.and_time(NaiveTime::MIN)),
+        }
+    }
+
+    pub fn time(&self, time: NaiveTime) -> Value {
+        Value::String(time.format("%H:%M:%S%.f").to_string())
+    }
+
+    pub fn timestamp(&self, timestamp: NaiveDateTime) -> Value {
+        match self.date_format {
+            DateFormat::Iso => Value::String(timestamp.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            DateFormat::EpochMillis => Value::from(timestamp.and_utc().timestamp_millis()),
+        }
+    }
+
+    pub fn timestamptz(&self, timestamp: DateTime<Utc>) -> Value {
+        match (self.date_format, self.timezone) {
+            (DateFormat::EpochMillis, _) => Value::from(timestamp.timestamp_millis()),
+            (DateFormat::Iso, Timezone::Utc) => {
+                Value::String(timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            (DateFormat::Iso, Timezone::Local) => Value::String(
+                timestamp
+                    .with_timezone(&Local)
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            ),
+        }
+    }
+
+    fn optional<T>(&self, value: Option<T>, format: impl Fn(&Self, T) -> Value) -> Value {
+        match value {
+            Some(value) => format(self, value),
+            None => self.null(),
+        }
+    }
+}
+
+/// Decode a date or time column with chrono and format it, None when the
+/// column isn't one or doesn't decode so the caller falls back to text.
+fn temporal_value<'r, R>(row: &'r R, i: usize, format: &FormatOptions) -> Option<Value>
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    NaiveDate: Decode<'r, R::Database> + Type<R::Database>,
+    NaiveTime: Decode<'r, R::Database> + Type<R::Database>,
+    NaiveDateTime: Decode<'r, R::Database> + Type<R::Database>,
+    DateTime<Utc>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let type_name = row.column(i).type_info().name().to_uppercase();
+    match type_name.as_str() {
+        "DATE" => row
+            .try_get::<Option<NaiveDate>, _>(i)
+            .ok()
+            .map(|v| format.optional(v, FormatOptions::date)),
+        "TIME" => row
+            .try_get::<Option<NaiveTime>, _>(i)
+            .ok()
+            .map(|v| format.optional(v, FormatOptions::time)),
+        "TIMESTAMPTZ" => row
+            .try_get::<Option<DateTime<Utc>>, _>(i)
+            .ok()
+            .map(|v| format.optional(v, FormatOptions::timestamptz)),
+        "TIMESTAMP" | "DATETIME" => {
+            // MySQL 的 TIMESTAMP 带时区，PostgreSQL 和 SQLite 的不带
+            if type_name == "TIMESTAMP"
+                && let Ok(v) = row.try_get::<Option<DateTime<Utc>>, _>(i)
+            {
+                return Some(format.optional(v, FormatOptions::timestamptz));
+            }
+            row.try_get::<Option<NaiveDateTime>, _>(i)
+                .ok()
+                .map(|v| format.optional(v, FormatOptions::timestamp))
+        }
+        _ => None,
+    }
+}
+
+/// Decode a boolean column as a JSON boolean: PostgreSQL `boolean`, MySQL
+/// `TINYINT(1)` and SQLite columns declared `BOOLEAN`. None for any other
+/// column, whose integers stay numbers.
+fn bool_value<'r, R>(row: &'r R, i: usize, format: &FormatOptions) -> Option<Value>
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    bool: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let type_name = row.column(i).type_info().name().to_uppercase();
+    if !matches!(type_name.as_str(), "BOOL" | "BOOLEAN") {
+        return None;
+    }
+    row.try_get::<Option<bool>, _>(i)
+        .ok()
+        .map(|v| format.optional(v, |_, b| Value::Bool(b)))
+}
+
+impl TryFrom<&QueryParam> for BindValue {
+    type Error = anyhow::Error;
+
+    fn try_from(param: &QueryParam) -> anyhow::Result<Self> {
+        match param {
+            QueryParam::Plain(value) => Ok(plain_value(value)),
+            QueryParam::Typed { value, param_type } => typed_value(value, *param_type),
+        }
+    }
+}
+
+fn plain_value(value: &Value) -> BindValue {
+    match value {
+        Value::Null => BindValue::Null,
+        Value::Bool(b) => BindValue::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => BindValue::Int(i),
+            None => BindValue::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => BindValue::Text(s.clone()),
+        // 数组和对象按 JSON 文本绑定
+        other => BindValue::Text(other.to_string()),
+    }
+}
+
+fn typed_value(value: &Value, param_type: ParamType) -> anyhow::Result<BindValue> {
+    let text = match value {
+        Value::Null => return Ok(BindValue::Null),
+        Value::String(s) => s.clone(),
+        Value::Number(n) if param_type == ParamType::Decimal => n.to_string(),
+        other => {
+            return Err(anyhow::anyhow!(
+                "Expected a string for a {:?} parameter, got {}",
+                param_type,
+                other
+            ));
+        }
+    };
+    let invalid = |e: &dyn std::fmt::Display| {
+        anyhow::anyhow!("Invalid {:?} parameter {:?}: {}", param_type, text, e)
+    };
+
+    Ok(match param_type {
+        ParamType::Date => {
+            BindValue::Date(NaiveDate::parse_from_str(&text, "%Y-%m-%d").map_err(|e| invalid(&e))?)
+        }
+        ParamType::Time => BindValue::Time(
+            NaiveTime::parse_from_str(&text, "%H:%M:%S%.f").map_err(|e| invalid(&e))?,
+        ),
+        ParamType::Timestamp => BindValue::Timestamp(
+            NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f")
+                .or_else(|_| NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f"))
+                .map_err(|e| invalid(&e))?,
+        ),
+        ParamType::Timestamptz => BindValue::Timestamptz(
+            DateTime::parse_from_rfc3339(&text)
+                .map_err(|e| invalid(&e))?
+                .with_timezone(&Utc),
+        ),
+        ParamType::Decimal => {
+            BindValue::Decimal(Decimal::from_str(&text).map_err(|e| invalid(&e))?)
+        }
+    })
+}
+
+/// Convert a MySQL row to a JSON object.
+///
+/// With `binary_uuid` set, 16 byte `BINARY` values are rendered as UUIDs.
+pub fn mysql_row(
+    row: &MySqlRow,
+    binary_uuid: bool,
+    format: &FormatOptions,
+) -> serde_json::Map<String, Value> {
+    let mut obj = serde_json::Map::new();
+
+    // Convert each column to a JSON value
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        // 这里直接尝试获取值作为字符串表示
+        let value = if let Some(val) = temporal_value(row, i, format) {
+            val
+        } else if let Some(val) = bool_value(row, i, format) {
+            val
+        } else if column.type_info().name() == "GEOMETRY" {
+            match row.try_get_unchecked::<Option<Vec<u8>>, _>(i) {
+                Ok(val) => format.optional(val, |format, bytes| spatial_value(bytes, true, format)),
+                Err(_) => format.null(),
+            }
+        } else if let Ok(val) = row.try_get::<Option<String>, _>(i) {
+            match val {
+                Some(s) => Value::String(s),
+                None => format.null(),
+            }
+        } else if let Ok(val) = row.try_get::<Option<Vec<u8>>, _>(i) {
+            // 对于二进制数据特殊处理
+            match val {
+                Some(bytes)
+                    if binary_uuid
+                        && bytes.len() == 16
+                        && column.type_info().name() == "BINARY" =>
+                {
+                    match Uuid::from_slice(&bytes) {
+                        Ok(uuid) => Value::String(uuid.hyphenated().to_string()),
+                        Err(_) => binary_value(bytes, format),
+                    }
+                }
+                Some(bytes) => binary_value(bytes, format),
+                None => format.null(),
+            }
+        } else if let Ok(val) = row.try_get::<Option<i64>, _>(i) {
+            // 对于整数类型
+            match val {
+                Some(n) => Value::String(n.to_string()),
+                None => format.null(),
+            }
+        } else if let Ok(val) = row.try_get::<Option<f64>, _>(i) {
+            // 对于浮点类型
+            match val {
+                Some(n) => Value::String(n.to_string()),
+                None => format.null(),
+            }
+        } else {
+            // 如果所有尝试都失败，返回类型信息
+            let type_info = column.type_info();
+            Value::String(format!("(unknown type: {})", type_info.name()))
+        };
+
+        obj.insert(column_name.to_string(), value);
+    }
+    obj
+}
+
+/// Convert a PostgreSQL row to a JSON object.
+pub fn postgres_row(
+    row: &PgRow,
+    format: &FormatOptions,
+) -> anyhow::Result<serde_json::Map<String, Value>> {
+    let mut obj = serde_json::Map::new();
+
+    // Convert each column to a JSON value
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let value = if let Some(value) = temporal_value(row, i, format) {
+            value
+        } else if let Some(value) = bool_value(row, i, format) {
+            value
+        } else if column.type_info().name() == "UUID" {
+            let value: Option<Uuid> = row.try_get(i)?;
+            format.optional(value, |_, uuid| {
+                Value::String(uuid.hyphenated().to_string())
+            })
+        } else if column.type_info().name() == "BYTEA" {
+            let value: Option<Vec<u8>> = row.try_get(i)?;
+            format.optional(value, |format, bytes| binary_value(bytes, format))
+        } else if is_postgis_type(column.type_info().name()) {
+            // PostGIS 类型以 EWKB 二进制格式返回
+            let value: Option<Vec<u8>> = row.try_get_unchecked(i)?;
+            format.optional(value, |format, bytes| spatial_value(bytes, false, format))
+        } else {
+            let value: Option<String> = row.try_get(i)?;
+            format.optional(value, |_, s| Value::String(s))
+        };
+        obj.insert(column_name.to_string(), value);
+    }
+    Ok(obj)
+}
+
+/// Convert a SQLite row to a JSON object.
+pub fn sqlite_row(
+    row: &SqliteRow,
+    format: &FormatOptions,
+) -> anyhow::Result<serde_json::Map<String, Value>> {
+    let mut obj = serde_json::Map::new();
+
+    // Convert each column to a JSON value
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let value = match temporal_value(row, i, format).or_else(|| bool_value(row, i, format)) {
+            Some(value) => value,
+            None if column.type_info().name() == "BLOB" => {
+                let value: Option<Vec<u8>> = row.try_get(i)?;
+                format.optional(value, |format, bytes| binary_value(bytes, format))
+            }
+            None => {
+                let value: Option<String> = row.try_get(i)?;
+                format.optional(value, |_, s| Value::String(s))
+            }
+        };
+        obj.insert(column_name.to_string(), value);
+    }
+    Ok(obj)
+}
+
+fn is_postgis_type(name: &str) -> bool {
+    name.eq_ignore_ascii_case("geometry") || name.eq_ignore_ascii_case("geography")
+}
+
+/// A spatial value as WKT when `spatial_wkt` is set, as binary otherwise or
+/// when it can't be decoded.
+fn spatial_value(bytes: Vec<u8>, srid_prefix: bool, format: &FormatOptions) -> Value {
+    if format.spatial_wkt
+        && let Ok(text) = wkt::from_wkb(&bytes, srid_prefix)
+    {
+        return Value::String(text);
+    }
+    binary_value(bytes, format)
+}
+
+fn binary_value(bytes: Vec<u8>, format: &FormatOptions) -> Value {
+    if format
+        .blob_threshold
+        .is_some_and(|limit| bytes.len() > limit)
+    {
+        let size = bytes.len();
+        return serde_json::json!({
+            "blob_ref": blob::store(bytes),
+            "size": size,
+        });
+    }
+    let base64_str = base64::engine::general_purpose::STANDARD.encode(&bytes);
+    Value::String(format!("(binary) {}", base64_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bind(value: Value) -> anyhow::Result<BindValue> {
+        BindValue::try_from(&serde_json::from_value::<QueryParam>(value)?)
+    }
+
+    #[test]
+    fn test_bind_value() {
+        assert_eq!(bind(json!(1)).unwrap(), BindValue::Int(1));
+        assert_eq!(
+            bind(json!("2024-01-31")).unwrap(),
+            BindValue::Text("2024-01-31".to_string())
+        );
+        assert_eq!(
+            bind(json!({"value": "2024-01-31", "type": "date"})).unwrap(),
+            BindValue::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())
+        );
+        assert_eq!(
+            bind(json!({"value": "2024-01-31 10:20:30", "type": "timestamp"})).unwrap(),
+            BindValue::Timestamp(
+                NaiveDate::from_ymd_opt(2024, 1, 31)
+                    .unwrap()
+                    .and_hms_opt(10, 20, 30)
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            bind(json!({"value": "12.50", "type": "decimal"})).unwrap(),
+            BindValue::Decimal(Decimal::new(1250, 2))
+        );
+        assert!(bind(json!({"value": "31/01/2024", "type": "date"})).is_err());
+        assert_eq!(
+            bind(json!({"value": null, "type": "date"})).unwrap(),
+            BindValue::Null
+        );
+    }
+
+    #[test]
+    fn test_format_options() {
+        let timestamp = NaiveDate::from_ymd_opt(2024, 1, 31)
+            .unwrap()
+            .and_hms_opt(10, 20, 30)
+            .unwrap();
+        let iso = FormatOptions::default();
+        assert_eq!(iso.timestamp(timestamp), json!("2024-01-31T10:20:30"));
+        assert_eq!(
+            iso.timestamptz(timestamp.and_utc()),
+            json!("2024-01-31T10:20:30Z")
+        );
+        assert_eq!(iso.date(timestamp.date()), json!("2024-01-31"));
+        assert_eq!(iso.null(), Value::Null);
+
+        let epoch = FormatOptions {
+            date_format: DateFormat::EpochMillis,
+            null_display: Some("NULL".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(epoch.timestamp(timestamp), json!(1706696430000i64));
+        assert_eq!(epoch.date(timestamp.date()), json!(1706659200000i64));
+        assert_eq!(epoch.null(), json!("NULL"));
+        let arrow = epoch.for_arrow();
+        assert_eq!(arrow.date(timestamp.date()), json!("2024-01-31"));
+        assert_eq!(arrow.null(), Value::Null);
+    }
+
+    #[test]
+    fn test_binary_value() {
+        let format = FormatOptions {
+            blob_threshold: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(binary_value(vec![1, 2], &format), json!("(binary) AQI="));
+        let value = binary_value(vec![1, 2, 3], &format);
+        assert_eq!(value["size"], json!(3));
+        let id = value["blob_ref"].as_str().unwrap();
+        assert_eq!(blob::get(id), Some(vec![1, 2, 3]));
+    }
+}
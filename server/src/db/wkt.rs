@@ -0,0 +1,215 @@
+/// Well-Known-Text for a geometry in Well-Known-Binary. Accepts the EWKB
+/// written by PostGIS and, with `srid_prefix`, MySQL's internal format of
+/// a 4 byte SRID followed by WKB.
+pub fn from_wkb(bytes: &[u8], srid_prefix: bool) -> anyhow::Result<String> {
+    let bytes = if srid_prefix {
+        bytes
+            .get(4..)
+            .ok_or_else(|| anyhow::anyhow!("Geometry value is too short"))?
+    } else {
+        bytes
+    };
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut wkt = String::new();
+    reader.geometry(&mut wkt)?;
+    Ok(wkt)
+}
+
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let chunk = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| anyhow::anyhow!("Geometry value ends unexpectedly"))?;
+        self.pos += N;
+        Ok(chunk.try_into()?)
+    }
+
+    fn u32(&mut self, little_endian: bool) -> anyhow::Result<u32> {
+        let bytes = self.take::<4>()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn f64(&mut self, little_endian: bool) -> anyhow::Result<f64> {
+        let bytes = self.take::<8>()?;
+        Ok(if little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    fn geometry(&mut self, wkt: &mut String) -> anyhow::Result<()> {
+        let [order] = self.take::<1>()?;
+        let le = order == 1;
+        let raw = self.u32(le)?;
+        if raw & EWKB_SRID != 0 {
+            self.u32(le)?;
+        }
+        // EWKB 用高位标记 Z/M，ISO WKB 在类型上加 1000/2000/3000
+        let iso = (raw & 0x0fff_ffff) / 1000;
+        let has_z = raw & EWKB_Z != 0 || iso == 1 || iso == 3;
+        let has_m = raw & EWKB_M != 0 || iso == 2 || iso == 3;
+        let dims = 2 + has_z as usize + has_m as usize;
+        let kind = (raw & 0x0fff_ffff) % 1000;
+
+        let name = match kind {
+            1 => "POINT",
+            2 => "LINESTRING",
+            3 => "POLYGON",
+            4 => "MULTIPOINT",
+            5 => "MULTILINESTRING",
+            6 => "MULTIPOLYGON",
+            7 => "GEOMETRYCOLLECTION",
+            other => return Err(anyhow::anyhow!("Unsupported geometry type: {}", other)),
+        };
+        wkt.push_str(name);
+        match (has_z, has_m) {
+            (true, true) => wkt.push_str(" ZM "),
+            (true, false) => wkt.push_str(" Z "),
+            (false, true) => wkt.push_str(" M "),
+            (false, false) => {}
+        }
+
+        if kind == 1 {
+            let coords = self.coords(le, dims)?;
+            // 空点用 NaN 坐标表示
+            if coords.iter().all(|c| c.is_nan()) {
+                push_empty(wkt);
+            } else {
+                wkt.push('(');
+                push_coords(wkt, &coords);
+                wkt.push(')');
+            }
+            return Ok(());
+        }
+
+        let count = self.u32(le)?;
+        if count == 0 {
+            push_empty(wkt);
+            return Ok(());
+        }
+        wkt.push('(');
+        for i in 0..count {
+            if i > 0 {
+                wkt.push(',');
+            }
+            match kind {
+                2 => push_coords(wkt, &self.coords(le, dims)?),
+                3 => self.ring(wkt, le, dims)?,
+                // 集合的成员各自带字节序和类型头
+                _ => {
+                    let mut member = String::new();
+                    self.geometry(&mut member)?;
+                    if kind == 7 {
+                        wkt.push_str(&member);
+                    } else {
+                        // MULTIPOINT((1 2),(3 4)) 去掉成员的类型名
+                        let body = member.find('(').map_or("EMPTY", |i| &member[i..]);
+                        wkt.push_str(body);
+                    }
+                }
+            }
+        }
+        wkt.push(')');
+        Ok(())
+    }
+
+    fn ring(&mut self, wkt: &mut String, le: bool, dims: usize) -> anyhow::Result<()> {
+        let count = self.u32(le)?;
+        wkt.push('(');
+        for i in 0..count {
+            if i > 0 {
+                wkt.push(',');
+            }
+            push_coords(wkt, &self.coords(le, dims)?);
+        }
+        wkt.push(')');
+        Ok(())
+    }
+
+    fn coords(&mut self, le: bool, dims: usize) -> anyhow::Result<Vec<f64>> {
+        (0..dims).map(|_| self.f64(le)).collect()
+    }
+}
+
+/// After a dimension suffix such as `POINT Z ` the space is already there.
+fn push_empty(wkt: &mut String) {
+    if !wkt.ends_with(' ') {
+        wkt.push(' ');
+    }
+    wkt.push_str("EMPTY");
+}
+
+fn push_coords(wkt: &mut String, coords: &[f64]) {
+    let text: Vec<String> = coords.iter().map(f64::to_string).collect();
+    wkt.push_str(&text.join(" "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(le: bool, x: f64, y: f64) -> Vec<u8> {
+        let mut bytes = vec![le as u8];
+        if le {
+            bytes.extend(1u32.to_le_bytes());
+            bytes.extend(x.to_le_bytes());
+            bytes.extend(y.to_le_bytes());
+        } else {
+            bytes.extend(1u32.to_be_bytes());
+            bytes.extend(x.to_be_bytes());
+            bytes.extend(y.to_be_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_from_wkb() {
+        assert_eq!(
+            from_wkb(&point(true, 1.0, 2.5), false).unwrap(),
+            "POINT(1 2.5)"
+        );
+        assert_eq!(
+            from_wkb(&point(false, -3.0, 4.0), false).unwrap(),
+            "POINT(-3 4)"
+        );
+
+        // MySQL: SRID 前缀 + WKB
+        let mut mysql = 4326u32.to_le_bytes().to_vec();
+        mysql.extend(point(true, 1.0, 2.0));
+        assert_eq!(from_wkb(&mysql, true).unwrap(), "POINT(1 2)");
+
+        // EWKB LINESTRING with SRID
+        let mut line = vec![1u8];
+        line.extend((2u32 | EWKB_SRID).to_le_bytes());
+        line.extend(4326u32.to_le_bytes());
+        line.extend(2u32.to_le_bytes());
+        for c in [0.0f64, 0.0, 1.0, 1.0] {
+            line.extend(c.to_le_bytes());
+        }
+        assert_eq!(from_wkb(&line, false).unwrap(), "LINESTRING(0 0,1 1)");
+
+        let mut multi = vec![1u8];
+        multi.extend(4u32.to_le_bytes());
+        multi.extend(2u32.to_le_bytes());
+        multi.extend(point(true, 1.0, 2.0));
+        multi.extend(point(true, 3.0, 4.0));
+        assert_eq!(from_wkb(&multi, false).unwrap(), "MULTIPOINT((1 2),(3 4))");
+
+        assert!(from_wkb(&[1, 1, 0], false).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::Deserialize;
+use sqlparser::{
+    ast::{Query, Select, SelectItem, SetExpr, Spanned, Statement, TableFactor, TableWithJoins},
+    tokenizer::Span,
+};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::parser::{SqlAst, to_position};
+
+const SELECT_STAR: &str = "select-star";
+const MISSING_WHERE: &str = "missing-where";
+const CROSS_JOIN: &str = "cross-join";
+
+/// Lint rules, sent as the `lint` init option. Nothing is reported unless
+/// `enabled` is set, each rule can then be switched off on its own.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LintSettings {
+    pub enabled: bool,
+    /// `SELECT *` in views, inserts, CTEs and subqueries
+    pub select_star: bool,
+    /// `UPDATE`/`DELETE` without `WHERE`
+    pub missing_where: bool,
+    /// Comma separated `FROM` without a `WHERE`
+    pub cross_join: bool,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            select_star: true,
+            missing_where: true,
+            cross_join: true,
+        }
+    }
+}
+
+/// Warnings for common mistakes in the parsed statements.
+pub fn lint(ast: &SqlAst, settings: &LintSettings) -> Vec<Diagnostic> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+    let mut linter = Linter {
+        settings,
+        diagnostics: Vec::new(),
+    };
+    for statement in &ast.statements {
+        linter.statement(statement);
+    }
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    settings: &'a LintSettings,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            // 顶层的 SELECT * 只是临时查看数据，不提示
+            Statement::Query(query) => self.query(query, true),
+            Statement::Insert(insert) => {
+                if let Some(source) = &insert.source {
+                    self.query(source, false);
+                }
+            }
+            Statement::CreateView { query, .. } => self.query(query, false),
+            Statement::CreateTable(create) => {
+                if let Some(query) = &create.query {
+                    self.query(query, false);
+                }
+            }
+            Statement::Update {
+                selection: None, ..
+            } if self.settings.missing_where => self.warn(
+                statement.span(),
+                MISSING_WHERE,
+                "UPDATE without WHERE changes every row",
+            ),
+            Statement::Delete(delete)
+                if delete.selection.is_none() && self.settings.missing_where =>
+            {
+                self.warn(
+                    statement.span(),
+                    MISSING_WHERE,
+                    "DELETE without WHERE removes every row",
+                )
+            }
+            _ => {}
+        }
+    }
+
+    fn query(&mut self, query: &Query, ad_hoc: bool) {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.query(&cte.query, false);
+            }
+        }
+        self.set_expr(&query.body, ad_hoc);
+    }
+
+    fn set_expr(&mut self, body: &SetExpr, ad_hoc: bool) {
+        match body {
+            SetExpr::Select(select) => self.select(select, ad_hoc),
+            SetExpr::Query(query) => self.query(query, ad_hoc),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left, ad_hoc);
+                self.set_expr(right, ad_hoc);
+            }
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, select: &Select, ad_hoc: bool) {
+        if self.settings.select_star && !ad_hoc {
+            for item in &select.projection {
+                if let SelectItem::Wildcard(options) = item {
+                    self.warn(
+                        options.wildcard_token.0.span,
+                        SELECT_STAR,
+                        "SELECT * breaks when the table's columns change, list the columns",
+                    );
+                }
+            }
+        }
+        if self.settings.cross_join && select.from.len() > 1 && select.selection.is_none() {
+            self.warn(
+                select.from[1].span(),
+                CROSS_JOIN,
+                "Comma separated tables without a join condition produce a cross join",
+            );
+        }
+        for table in &select.from {
+            self.table(table);
+        }
+    }
+
+    fn table(&mut self, table: &TableWithJoins) {
+        self.factor(&table.relation);
+        for join in &table.joins {
+            self.factor(&join.relation);
+        }
+    }
+
+    fn factor(&mut self, factor: &TableFactor) {
+        match factor {
+            TableFactor::Derived { subquery, .. } => self.query(subquery, false),
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => self.table(table_with_joins),
+            _ => {}
+        }
+    }
+
+    fn warn(&mut self, span: Span, code: &str, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            range: Range {
+                start: to_position(span.start),
+                end: to_position(span.end),
+            },
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(code.to_string())),
+            source: Some("db-viewer".to_string()),
+            message: message.to_string(),
+            ..Default::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+
+    fn codes(sql: &str, settings: &LintSettings) -> Vec<String> {
+        let ast = SqlParser::new().parse(sql).unwrap();
+        lint(&ast, settings)
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => Some(code),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let settings = LintSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(codes("SELECT * FROM users", &settings).is_empty());
+        assert_eq!(
+            codes("CREATE VIEW v AS SELECT * FROM users", &settings),
+            vec![SELECT_STAR]
+        );
+        assert_eq!(codes("DELETE FROM users", &settings), vec![MISSING_WHERE]);
+        assert_eq!(
+            codes("UPDATE users SET name = 'a'", &settings),
+            vec![MISSING_WHERE]
+        );
+        assert!(codes("DELETE FROM users WHERE id = 1", &settings).is_empty());
+        assert_eq!(codes("SELECT a.id FROM a, b", &settings), vec![CROSS_JOIN]);
+
+        let settings = LintSettings {
+            enabled: true,
+            missing_where: false,
+            ..Default::default()
+        };
+        assert!(codes("DELETE FROM users", &settings).is_empty());
+        assert!(codes("DELETE FROM users", &LintSettings::default()).is_empty());
+    }
+}
@@ -1,19 +1,136 @@
+use std::{
+    cell::RefCell,
+    sync::{
+        PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
 use tower_lsp::lsp_types::MessageType;
 
-static LOGGER: once_cell::sync::OnceCell<tokio::sync::broadcast::Sender<(MessageType, String)>> =
-    once_cell::sync::OnceCell::new();
+/// Target sqlx logs PostgreSQL notices and warnings under.
+const NOTICE_TARGET: &str = "sqlx::postgres::notice";
+
+tokio::task_local! {
+    static NOTICES: RefCell<Vec<String>>;
+    /// Correlation id of the command being handled, see [`with_request_id`]
+    static REQUEST_ID: String;
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+type LogSender = tokio::sync::broadcast::Sender<(MessageType, String)>;
 
+static LOGGER: once_cell::sync::Lazy<RwLock<LogSender>> = once_cell::sync::Lazy::new(|| {
+    let (tx, _) = tokio::sync::broadcast::channel(crate::settings::DEFAULT_LOG_CAPACITY);
+    RwLock::new(tx)
+});
+
+/// Log a message, tagged with the id of the command being handled if any.
 pub fn log(tye: MessageType, message: String) {
-    if let Some(tx) = LOGGER.get() {
+    log_with(tye, request_id().as_deref(), message);
+}
+
+/// Log a message with an explicit context, e.g. a request id, written as a
+/// `[context]` prefix.
+pub fn log_with(tye: MessageType, context: Option<&str>, message: String) {
+    if !crate::settings::get().log_level.allows(tye) {
+        return;
+    }
+    let message = match context {
+        Some(context) => format!("[{}] {}", context, message),
+        None => message,
+    };
+    if let Ok(tx) = LOGGER.read() {
         let _ = tx.send((tye, message));
     }
 }
 
+/// A new id to correlate one command's logs, result and errors.
+pub fn next_request_id() -> String {
+    format!("req-{}", NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Id of the command handled by the current task, if any.
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `future` with `id` attached to everything it logs. Tasks it spawns
+/// don't inherit the id.
+pub async fn with_request_id<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
 pub fn subscribe() -> tokio::sync::broadcast::Receiver<(MessageType, String)> {
     LOGGER
-        .get_or_init(|| {
-            let (tx, _) = tokio::sync::broadcast::channel(100);
-            tx
-        })
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
         .subscribe()
 }
+
+/// Replace the log channel with one buffering `capacity` messages for a
+/// slow client. Receivers of the old channel see it closed, so call this
+/// before [`subscribe`].
+pub fn set_log_capacity(capacity: usize) {
+    let (tx, _) = tokio::sync::broadcast::channel(capacity.max(1));
+    if let Ok(mut current) = LOGGER.write() {
+        *current = tx;
+    }
+}
+
+/// Whether the current task is running a query under [`capture_notices`].
+fn capturing() -> bool {
+    NOTICES.try_with(|_| ()).is_ok()
+}
+
+/// env_logger that also hands database notices to [`capture_notices`].
+/// Notices are only taken from the task that is capturing them, i.e. the
+/// one driving the connection that received them; all others go to
+/// env_logger as before.
+struct NoticeLogger(env_logger::Logger);
+
+impl Log for NoticeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        (metadata.target() == NOTICE_TARGET && capturing()) || self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target() == NOTICE_TARGET
+            && NOTICES
+                .try_with(|notices| notices.borrow_mut().push(record.args().to_string()))
+                .is_ok()
+        {
+            return;
+        }
+        self.0.log(record);
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Install the process logger, configured through `RUST_LOG`.
+pub fn init() {
+    let logger = env_logger::Builder::from_default_env().build();
+    // sqlx only reports notices when INFO is enabled for their target
+    let max_level = logger.filter().max(LevelFilter::Info);
+    if log::set_boxed_logger(Box::new(NoticeLogger(logger))).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Run `future`, collecting the notices the database sends meanwhile. sqlx
+/// has no notice callback and only reports them through `log`, on the task
+/// that polls the connection, so only the notices of the connection this
+/// future drives are seen.
+pub async fn capture_notices<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    NOTICES
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            (output, NOTICES.with(|notices| notices.take()))
+        })
+        .await
+}
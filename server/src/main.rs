@@ -5,15 +5,20 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use command::Command;
-use parser::{SqlAst, SqlParser};
+use command::{Command, DocumentConnections};
+use parser::{DocumentMap, SqlAst, SqlParser};
 use serde_json::Value;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast::error::RecvError};
 use tokio_util::sync::CancellationToken;
 use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
 use tower_lsp::lsp_types::{
-    CodeLens, CodeLensOptions, CodeLensParams, CompletionOptions, ExecuteCommandOptions,
-    ExecuteCommandParams, InitializedParams, MessageType, ServerCapabilities, TextDocumentSyncKind,
+    CodeLens, CodeLensOptions, CodeLensParams, CompletionList, CompletionOptions, CompletionParams,
+    CompletionResponse, Diagnostic, DidChangeConfigurationParams, ExecuteCommandOptions,
+    ExecuteCommandParams, FoldingRange, FoldingRangeParams, FoldingRangeProviderCapability,
+    InitializedParams, MessageType, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensParams, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, SignatureHelp, SignatureHelpOptions,
+    SignatureHelpParams, TextDocumentSyncKind, Url,
 };
 use tower_lsp::{Client, LspService};
 use tower_lsp::{
@@ -22,14 +27,19 @@ use tower_lsp::{
 };
 
 mod command;
+mod completion;
 mod constant;
 mod db;
+mod lint;
 mod logger;
 mod parser;
+mod semantic;
+mod settings;
+mod signature;
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    logger::init();
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
@@ -41,7 +51,8 @@ async fn main() {
 
 struct Backend {
     client: Arc<Client>,
-    document_map: Arc<RwLock<HashMap<String, SqlAst>>>,
+    document_map: DocumentMap,
+    document_connections: DocumentConnections,
     sql_parser: SqlParser,
     commands: Vec<Box<dyn Command + Send + Sync>>,
 
@@ -50,7 +61,19 @@ struct Backend {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(options) = params.initialization_options
+            && let Err(e) = settings::update(options)
+        {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Invalid initialization options: {}", e),
+                )
+                .await
+        }
+        // 先按设置重建日志通道，再订阅
+        logger::set_log_capacity(settings::get().log_capacity());
         self.log_message_spawn();
         let capabilities = ServerCapabilities {
             completion_provider: Some(CompletionOptions {
@@ -58,9 +81,22 @@ impl LanguageServer for Backend {
                 resolve_provider: Some(false),
                 ..Default::default()
             }),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                retrigger_characters: None,
+                work_done_progress_options: Default::default(),
+            }),
             code_lens_provider: Some(CodeLensOptions {
                 resolve_provider: Some(false),
             }),
+            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            semantic_tokens_provider: Some(
+                SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                    legend: semantic::legend(),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                    ..SemanticTokensOptions::default()
+                }),
+            ),
             text_document_sync: Some(tower_lsp::lsp_types::TextDocumentSyncCapability::Kind(
                 TextDocumentSyncKind::FULL,
             )),
@@ -96,14 +132,22 @@ impl LanguageServer for Backend {
 
     async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
         let document_uri = params.text_document.uri.to_string();
+        let connection_id = self
+            .document_connections
+            .read()
+            .await
+            .get(&document_uri)
+            .cloned();
         let document_map = self.document_map.read().await;
 
         if let Some(content) = document_map.get(&document_uri) {
-            content.code_lens().map_err(|e| Error {
-                code: ErrorCode::InternalError,
-                message: "Failed to generate CodeLens".to_string().into(),
-                data: Some(e.to_string().into()),
-            })
+            content
+                .code_lens(connection_id.as_deref())
+                .map_err(|e| Error {
+                    code: ErrorCode::InternalError,
+                    message: "Failed to generate CodeLens".to_string().into(),
+                    data: Some(e.to_string().into()),
+                })
         } else {
             Ok(None)
         }
@@ -111,22 +155,11 @@ impl LanguageServer for Backend {
 
     // 实现文档同步，以便跟踪文档内容
     async fn did_open(&self, params: tower_lsp::lsp_types::DidOpenTextDocumentParams) {
-        let ast = match self.sql_parser.parse(&params.text_document.text) {
-            Ok(ast) => ast,
-            Err(_) => {
-                self.client
-                    .log_message(
-                        MessageType::ERROR,
-                        "Failed to parse SQL document".to_string(),
-                    )
-                    .await;
-                return;
-            }
-        };
-
+        if !self
+            .update_document(params.text_document.uri, &params.text_document.text)
+            .await
         {
-            let mut document_map = self.document_map.write().await;
-            document_map.insert(params.text_document.uri.to_string(), ast);
+            return;
         }
 
         // 通知客户端刷新CodeLens
@@ -145,32 +178,22 @@ impl LanguageServer for Backend {
             Some(change) => change,
             None => return,
         };
-        let ast = match self.sql_parser.parse(&change.text) {
-            Ok(ast) => ast,
-            Err(_) => {
-                self.client
-                    .log_message(
-                        MessageType::ERROR,
-                        "Failed to parse SQL document".to_string(),
-                    )
-                    .await;
-                return;
-            }
-        };
-
+        if !self
+            .update_document(params.text_document.uri, &change.text)
+            .await
         {
-            let mut document_map = self.document_map.write().await;
-            document_map.insert(params.text_document.uri.to_string(), ast);
-            self.client
-                .log_message(
-                    MessageType::INFO,
-                    "SQL document changed, refreshing CodeLens",
-                )
-                .await;
-
-            // 通知客户端刷新CodeLens
-            self.client.code_lens_refresh().await.unwrap();
+            return;
         }
+
+        self.client
+            .log_message(
+                MessageType::INFO,
+                "SQL document changed, refreshing CodeLens",
+            )
+            .await;
+
+        // 通知客户端刷新CodeLens
+        self.client.code_lens_refresh().await.unwrap();
     }
 
     async fn did_close(&self, params: tower_lsp::lsp_types::DidCloseTextDocumentParams) {
@@ -185,137 +208,225 @@ impl LanguageServer for Backend {
     }
 
     async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
-        self.commands
+        let command = self
+            .commands
             .iter()
             .find(|cmd| cmd.command() == params.command)
             .ok_or_else(|| Error {
                 code: ErrorCode::MethodNotFound,
                 message: "Command not found".to_string().into(),
                 data: None,
-            })?
-            .handler(params)
+            })?;
+        let request_id = logger::next_request_id();
+        logger::log_with(
+            MessageType::LOG,
+            Some(&request_id),
+            format!("Running command {}", params.command),
+        );
+        let compress_threshold = settings::get().compress_threshold;
+        logger::with_request_id(request_id.clone(), command.handler(params))
             .await
             .map(|result| {
-                result.map(|res| serde_json::to_value(res).unwrap_or_else(|_| Value::Null))
+                result.map(|res| {
+                    let mut res = res.with_request_id(request_id.clone());
+                    if let Some(threshold) = compress_threshold {
+                        res = res.compress(threshold);
+                    }
+                    serde_json::to_value(res).unwrap_or_else(|_| Value::Null)
+                })
             })
             .map_err(|e| Error {
                 code: ErrorCode::InternalError,
                 message: "Command execution failed".to_string().into(),
-                data: Some(e.to_string().into()),
+                // 错误信息带上请求 id，便于对照日志
+                data: Some(format!("[{}] {}", request_id, e).into()),
+            })
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let document_uri = params.text_document_position.text_document.uri.to_string();
+        let position = params.text_document_position.position;
+
+        // 分析当前光标位置的上下文
+        let (context, word) = {
+            let document_map = self.document_map.read().await;
+            match document_map.get(&document_uri) {
+                Some(doc) => (
+                    doc.get_completion_context(position),
+                    doc.word_before(position),
+                ),
+                None => return Ok(None),
+            }
+        };
+
+        // 优先使用文档关联的连接，否则遍历所有已知数据库连接的模式信息
+        let connection_id = self
+            .document_connections
+            .read()
+            .await
+            .get(&document_uri)
+            .cloned();
+        let schemas = match connection_id {
+            Some(id) => db::schema::get(&id)
+                .await
+                .map(|schema| vec![(id, schema)])
+                .unwrap_or_default(),
+            None => db::schema::all().await,
+        };
+        let items = completion::completion_items(&context, &schemas);
+        let (items, is_incomplete) =
+            completion::rank(items, &word, settings::get().max_completion_items());
+        Ok(Some(CompletionResponse::List(CompletionList {
+            is_incomplete,
+            items,
+        })))
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // 客户端可能把设置放在 dbviewer 配置段下
+        let options = match params.settings.get("dbviewer") {
+            Some(section) => section.clone(),
+            None => params.settings,
+        };
+        match settings::update(options) {
+            Ok(()) => self.refresh_diagnostics().await,
+            Err(e) => {
+                self.client
+                    .log_message(MessageType::WARNING, format!("Invalid settings: {}", e))
+                    .await
+            }
+        }
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let document_uri = params.text_document.uri.to_string();
+        let document_map = self.document_map.read().await;
+        Ok(document_map
+            .get(&document_uri)
+            .map(|doc| doc.folding_ranges()))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let document_uri = params.text_document.uri.to_string();
+        let document_map = self.document_map.read().await;
+        Ok(document_map.get(&document_uri).map(|doc| {
+            SemanticTokensResult::Tokens(SemanticTokens {
+                result_id: None,
+                data: semantic::semantic_tokens(&doc.document),
             })
+        }))
     }
 
-    // async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-    //     let document_uri = params.text_document_position.text_document.uri.to_string();
-    //     let position = params.text_document_position.position;
-
-    //     // 获取当前文档
-    //     let document_map = self.document_map.read().await;
-    //     let doc = match document_map.get(&document_uri) {
-    //         Some(doc) => doc,
-    //         None => return Ok(None),
-    //     };
-
-    //     // 分析当前光标位置的上下文
-    //     let context = doc.get_completion_context(position);
-
-    //     match context {
-    //         CompletionContext::TableName => {
-    //             // 提供表名列表
-    //             let mut items = Vec::new();
-    //             let schema_cache = self.schema_cache.read().await;
-
-    //             // 遍历所有已知数据库连接的模式信息
-    //             for (conn_id, schema) in schema_cache.iter() {
-    //                 for (table_name, table_info) in &schema.tables {
-    //                     items.push(CompletionItem {
-    //                         label: table_name.clone(),
-    //                         kind: Some(CompletionItemKind::CLASS),
-    //                         detail: Some(format!("Table ({conn_id})")),
-    //                         documentation: Some(Documentation::MarkupContent(MarkupContent {
-    //                             kind: MarkupKind::MARKDOWN,
-    //                             value: format!(
-    //                                 "### Table: {}\n\nColumns:\n{}",
-    //                                 table_name,
-    //                                 table_info
-    //                                     .columns
-    //                                     .iter()
-    //                                     .map(|c| format!(
-    //                                         "- **{}**: {} {}{}",
-    //                                         c.name,
-    //                                         c.data_type,
-    //                                         if c.is_primary { " (PK)" } else { "" },
-    //                                         if c.is_nullable { "" } else { " NOT NULL" }
-    //                                     ))
-    //                                     .collect::<Vec<_>>()
-    //                                     .join("\n")
-    //                             ),
-    //                         })),
-    //                         ..Default::default()
-    //                     });
-    //                 }
-    //             }
-
-    //             Ok(Some(CompletionResponse::Array(items)))
-    //         }
-    //         CompletionContext::ColumnName(table_name) => {
-    //             // 提供指定表的列名列表
-    //             let mut items = Vec::new();
-    //             let schema_cache = self.schema_cache.read().await;
-
-    //             for schema in schema_cache.values() {
-    //                 if let Some(table) = schema.tables.get(&table_name) {
-    //                     for column in &table.columns {
-    //                         items.push(CompletionItem {
-    //                             label: column.name.clone(),
-    //                             kind: Some(CompletionItemKind::FIELD),
-    //                             detail: Some(format!("{} ({})", column.data_type, table_name)),
-    //                             documentation: Some(Documentation::String(format!(
-    //                                 "Column: {} \nType: {}\nTable: {}",
-    //                                 column.name, column.data_type, table_name
-    //                             ))),
-    //                             ..Default::default()
-    //                         });
-    //                     }
-    //                 }
-    //             }
-
-    //             Ok(Some(CompletionResponse::Array(items)))
-    //         }
-    //         CompletionContext::None => {
-    //             // 无特定上下文时的通用建议（关键字等）
-    //             let keywords = vec![
-    //                 "SELECT", "FROM", "WHERE", "JOIN", "LEFT", "RIGHT", "INNER", "OUTER",
-    //                 "GROUP BY", "ORDER BY", "HAVING", "LIMIT", "OFFSET", "INSERT", "UPDATE",
-    //                 "DELETE", "CREATE", "ALTER", "DROP", "TABLE", "INDEX", "VIEW", "AS",
-    //             ];
-
-    //             let items = keywords
-    //                 .into_iter()
-    //                 .map(|kw| CompletionItem {
-    //                     label: kw.to_string(),
-    //                     kind: Some(CompletionItemKind::KEYWORD),
-    //                     ..Default::default()
-    //                 })
-    //                 .collect();
-
-    //             Ok(Some(CompletionResponse::Array(items)))
-    //         }
-    //     }
-    // }
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let document_uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .to_string();
+        let position = params.text_document_position_params.position;
+
+        let call = {
+            let document_map = self.document_map.read().await;
+            document_map
+                .get(&document_uri)
+                .and_then(|doc| doc.call_at(position))
+        };
+        let Some((name, argument)) = call else {
+            return Ok(None);
+        };
+
+        // 根据文档关联的连接选择对应数据库的函数签名
+        let connection_id = self
+            .document_connections
+            .read()
+            .await
+            .get(&document_uri)
+            .cloned();
+        let mut db_type = None;
+        if let Some(id) = connection_id
+            && let Some(connection) = db::cached(&id).await
+            && let Some(pool) = connection.get_pool().await
+        {
+            db_type = Some(pool.database_type());
+        }
+        Ok(signature::signature_help(&name, argument, db_type.as_ref()))
+    }
 }
 
 impl Backend {
     fn new(client: Client) -> Self {
+        let document_map: DocumentMap = Arc::new(RwLock::new(HashMap::new()));
+        let document_connections: DocumentConnections = Arc::new(RwLock::new(HashMap::new()));
+        let client = Arc::new(client);
         Self {
-            client: Arc::new(client),
-            document_map: Arc::new(RwLock::new(HashMap::new())),
+            commands: command::commands(
+                client.clone(),
+                document_map.clone(),
+                document_connections.clone(),
+            ),
+            client,
+            document_map,
+            document_connections,
             sql_parser: SqlParser::new(),
-            commands: command::commands(),
             cancel: CancellationToken::new(),
         }
     }
 
+    /// Re-parse a document and publish its parse errors. Returns false if
+    /// the document couldn't be parsed at all.
+    async fn update_document(&self, uri: Url, text: &str) -> bool {
+        let ast = match self.sql_parser.parse(text) {
+            Ok(ast) => ast,
+            Err(_) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        "Failed to parse SQL document".to_string(),
+                    )
+                    .await;
+                return false;
+            }
+        };
+
+        let diagnostics = Self::diagnostics(&ast);
+        {
+            let mut document_map = self.document_map.write().await;
+            document_map.insert(uri.to_string(), ast);
+        }
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+        true
+    }
+
+    fn diagnostics(ast: &SqlAst) -> Vec<Diagnostic> {
+        let mut diagnostics = ast.diagnostics();
+        diagnostics.extend(lint::lint(ast, &settings::get().lint));
+        diagnostics
+    }
+
+    /// Publish diagnostics again for every open document, e.g. after the
+    /// lint settings changed.
+    async fn refresh_diagnostics(&self) {
+        let documents: Vec<(String, Vec<Diagnostic>)> = self
+            .document_map
+            .read()
+            .await
+            .iter()
+            .map(|(uri, ast)| (uri.clone(), Self::diagnostics(ast)))
+            .collect();
+        for (uri, diagnostics) in documents {
+            if let Ok(uri) = Url::parse(&uri) {
+                self.client
+                    .publish_diagnostics(uri, diagnostics, None)
+                    .await;
+            }
+        }
+    }
+
     fn cancel(&self) {
         self.cancel.cancel();
     }
@@ -330,9 +441,19 @@ impl Backend {
                     _ = cancel.cancelled() => {
                         break;
                     }
-                    Ok((t, v)) = rx.recv() => {
-                        client_clone.log_message(t, v).await;
-                    }
+                    message = rx.recv() => match message {
+                        Ok((t, v)) => client_clone.log_message(t, v).await,
+                        // 客户端跟不上时最旧的消息被覆盖，提示日志不完整
+                        Err(RecvError::Lagged(n)) => {
+                            client_clone
+                                .log_message(
+                                    MessageType::WARNING,
+                                    format!("Log is incomplete, dropped {} messages", n),
+                                )
+                                .await;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
                 }
             }
         });
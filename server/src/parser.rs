@@ -1,33 +1,626 @@
-use std::vec;
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
 
-use chrono::format;
-use sqlparser::{ast::Spanned, dialect::GenericDialect};
-use tower_lsp::lsp_types::{CodeLens, Command, MessageType, Position, Range};
+use sqlparser::{
+    ast::{
+        FromTable, GroupByExpr, Query, SetExpr, Spanned, Statement, TableFactor, TableObject,
+        TableWithJoins, UpdateTableFromKind,
+    },
+    dialect::GenericDialect,
+    keywords::Keyword,
+    tokenizer::{Location, Span, Token, TokenWithSpan, Tokenizer, Word},
+};
+use tower_lsp::lsp_types::{
+    CodeLens, Command, Diagnostic, DiagnosticSeverity, FoldingRange, FoldingRangeKind, MessageType,
+    Position, Range,
+};
 
-use crate::{constant::CLIENT_EXECUTE_COMMAND, logger::log};
+use crate::{constant::CLIENT_EXECUTE_COMMAND, db::DatabaseType, logger::log};
+
+/// Parsed open documents keyed by URI.
+pub type DocumentMap = Arc<tokio::sync::RwLock<HashMap<String, SqlAst>>>;
 
 #[derive(Debug, Clone)]
 /// Represents a SQL AST (Abstract Syntax Tree).
 pub struct SqlAst {
     pub statements: Vec<sqlparser::ast::Statement>,
     pub document: String,
+    /// Statements skipped because they failed to parse
+    pub errors: Vec<ParseFailure>,
+}
+
+/// A statement that couldn't be parsed.
+#[derive(Debug, Clone)]
+pub struct ParseFailure {
+    pub range: Range,
+    pub message: String,
+}
+
+/// Convert a 1-based sqlparser location to a 0-based LSP position.
+pub(crate) fn to_position(location: Location) -> Position {
+    Position {
+        line: location.line.saturating_sub(1) as u32,
+        character: location.column.saturating_sub(1) as u32,
+    }
+}
+
+/// How a statement reports its outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultKind {
+    /// The statement produces a result set
+    Rows,
+    /// The statement only reports how many rows it affected
+    Affected,
+}
+
+impl ResultKind {
+    pub fn of(statement: &Statement) -> Self {
+        match statement {
+            Statement::Query(_)
+            | Statement::Explain { .. }
+            | Statement::ExplainTable { .. }
+            | Statement::Pragma { .. }
+            | Statement::ShowFunctions { .. }
+            | Statement::ShowVariable { .. }
+            | Statement::ShowStatus { .. }
+            | Statement::ShowVariables { .. }
+            | Statement::ShowCreate { .. }
+            | Statement::ShowColumns { .. }
+            | Statement::ShowDatabases { .. }
+            | Statement::ShowSchemas { .. }
+            | Statement::ShowObjects(_)
+            | Statement::ShowTables { .. }
+            | Statement::ShowViews { .. }
+            | Statement::ShowCollation { .. } => ResultKind::Rows,
+            Statement::Insert(insert) if insert.returning.is_some() => ResultKind::Rows,
+            Statement::Update { returning, .. } if returning.is_some() => ResultKind::Rows,
+            Statement::Delete(delete) if delete.returning.is_some() => ResultKind::Rows,
+            _ => ResultKind::Affected,
+        }
+    }
+}
+
+/// Leading keywords of a statement, e.g. `UPDATE` or `DROP TABLE`.
+pub fn statement_kind(statement: &Statement) -> String {
+    match statement {
+        Statement::Drop { object_type, .. } => format!("DROP {}", object_type),
+        Statement::CreateTable(_) => "CREATE TABLE".to_string(),
+        Statement::CreateView { .. } => "CREATE VIEW".to_string(),
+        Statement::CreateIndex(_) => "CREATE INDEX".to_string(),
+        Statement::AlterTable { .. } => "ALTER TABLE".to_string(),
+        other => other
+            .to_string()
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase(),
+    }
+}
+
+/// Whether a statement changes the schema rather than data.
+pub fn is_ddl(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::CreateTable(_)
+            | Statement::CreateView { .. }
+            | Statement::CreateIndex(_)
+            | Statement::AlterTable { .. }
+            | Statement::Drop { .. }
+    )
+}
+
+/// Whether MySQL commits the open transaction before running `sql`, as it
+/// does for DDL and locking statements, which also discards savepoints.
+pub fn commits_implicitly(sql: &str) -> bool {
+    let Some(chunk) = split_statements(sql).into_iter().next() else {
+        return false;
+    };
+    let mut words = chunk.sql.split_whitespace().map(str::to_uppercase);
+    match words.next().as_deref() {
+        Some(
+            "ALTER" | "DROP" | "RENAME" | "TRUNCATE" | "GRANT" | "REVOKE" | "LOCK" | "UNLOCK"
+            | "BEGIN" | "START" | "COMMIT",
+        ) => true,
+        Some("CREATE") => words.next().as_deref() != Some("TEMPORARY"),
+        _ => false,
+    }
+}
+
+/// Tables or other objects a statement writes to, empty for statements
+/// that aren't recognized.
+pub fn affected_objects(statement: &Statement) -> Vec<String> {
+    let from_tables = |tables: &[TableWithJoins]| -> Vec<String> {
+        tables
+            .iter()
+            .filter_map(|table| match &table.relation {
+                TableFactor::Table { name, .. } => Some(name.to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+    match statement {
+        Statement::Insert(insert) => match &insert.table {
+            TableObject::TableName(name) => vec![name.to_string()],
+            _ => Vec::new(),
+        },
+        Statement::Update { table, .. } => from_tables(std::slice::from_ref(table)),
+        Statement::Delete(delete) if !delete.tables.is_empty() => {
+            delete.tables.iter().map(ToString::to_string).collect()
+        }
+        Statement::Delete(delete) => match &delete.from {
+            FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables) => {
+                from_tables(tables)
+            }
+        },
+        Statement::Drop { names, .. } => names.iter().map(ToString::to_string).collect(),
+        Statement::Truncate { table_names, .. } => table_names
+            .iter()
+            .map(|target| target.name.to_string())
+            .collect(),
+        Statement::CreateTable(create) => vec![create.name.to_string()],
+        Statement::CreateView { name, .. } => vec![name.to_string()],
+        Statement::CreateIndex(index) => vec![index.table_name.to_string()],
+        Statement::AlterTable { name, .. } => vec![name.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// A query counting the rows of `sql` without its ORDER BY, LIMIT and
+/// OFFSET, so a paged grid can show the total. A plain SELECT gets
+/// `COUNT(*)` as its select list, a query that groups or combines rows or
+/// has placeholders in its select list is wrapped in a subquery, so every
+/// parameter is still used. None for anything but a single query, or when
+/// the dropped clauses hold placeholders the parameters are bound to.
+/// The count is cast to text, the row converters don't decode integers.
+pub fn count_query(sql: &str, db_type: &DatabaseType) -> Option<String> {
+    let count = match db_type {
+        DatabaseType::MySQL => "CAST(COUNT(*) AS CHAR)",
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => "CAST(COUNT(*) AS TEXT)",
+    };
+    let parser = SqlParser::new().with_recovery(false);
+    let ast = parser.parse(sql).ok()?;
+    let [Statement::Query(query)] = ast.statements.as_slice() else {
+        return None;
+    };
+    let mut query = query.as_ref().clone();
+    let dropped = [
+        query.order_by.take().map(|clause| clause.to_string()),
+        query.limit.take().map(|clause| clause.to_string()),
+        query.offset.take().map(|clause| clause.to_string()),
+        query.fetch.take().map(|clause| clause.to_string()),
+    ];
+    if dropped
+        .iter()
+        .flatten()
+        .any(|clause| has_placeholder(clause))
+        || !query.limit_by.is_empty()
+    {
+        return None;
+    }
+    query.locks.clear();
+
+    if let SetExpr::Select(select) = query.body.as_mut() {
+        if select.into.is_some() {
+            return None;
+        }
+        // 聚合函数会把结果合成一行，这种情况也包成子查询
+        let plain = select.distinct.is_none()
+            && select.top.is_none()
+            && select.having.is_none()
+            && matches!(&select.group_by, GroupByExpr::Expressions(exprs, _) if exprs.is_empty())
+            && !select.projection.iter().any(|item| {
+                let item = item.to_string();
+                item.contains('(') || has_placeholder(&item)
+            });
+        if plain {
+            let count = parser.parse(&format!("SELECT {}", count)).ok()?;
+            let [Statement::Query(count)] = count.statements.as_slice() else {
+                return None;
+            };
+            let SetExpr::Select(count) = count.body.as_ref() else {
+                return None;
+            };
+            select.projection = count.projection.clone();
+            return Some(query.to_string());
+        }
+    }
+    Some(format!("SELECT {} FROM ({}) AS counted", count, query))
+}
+
+/// Functions that write or take locks even when called from a SELECT.
+const WRITING_FUNCTIONS: &[&str] = &[
+    "nextval",
+    "setval",
+    "set_config",
+    "pg_advisory_lock",
+    "pg_advisory_xact_lock",
+    "pg_try_advisory_lock",
+    "pg_try_advisory_xact_lock",
+    "pg_cancel_backend",
+    "pg_terminate_backend",
+    "get_lock",
+    "release_lock",
+    "release_all_locks",
+];
+
+/// Functions whose result changes from one call to the next.
+const VOLATILE_FUNCTIONS: &[&str] = &[
+    "now",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "localtime",
+    "localtimestamp",
+    "clock_timestamp",
+    "statement_timestamp",
+    "transaction_timestamp",
+    "timeofday",
+    "sysdate",
+    "curdate",
+    "curtime",
+    "utc_date",
+    "utc_time",
+    "utc_timestamp",
+    "unix_timestamp",
+    "random",
+    "rand",
+    "randomblob",
+    "uuid",
+    "uuid_short",
+    "gen_random_uuid",
+    "uuid_generate_v4",
+    "currval",
+    "lastval",
+    "last_insert_id",
+    "last_insert_rowid",
+    "changes",
+    "connection_id",
+    "pg_backend_pid",
+    "txid_current",
+];
+
+/// Whether `sql` names any of `functions` or contains one of the string
+/// literals in `literals`. True when it doesn't tokenize.
+fn mentions_function(sql: &str, functions: &[&str], literals: &[&str]) -> bool {
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, sql).tokenize() else {
+        return true;
+    };
+    tokens.iter().any(|token| match token {
+        Token::Word(word) if word.quote_style.is_none() => functions
+            .iter()
+            .any(|name| word.value.eq_ignore_ascii_case(name)),
+        Token::SingleQuotedString(s) => literals.iter().any(|l| s.eq_ignore_ascii_case(l)),
+        _ => false,
+    })
 }
 
+/// Whether `sql` only reads, so it can run on a read replica: every
+/// statement is a query without locking clauses, SELECT INTO,
+/// data-modifying CTEs or functions with side effects like `nextval`.
+pub fn is_read_only(sql: &str) -> bool {
+    let Ok(ast) = SqlParser::new().with_recovery(false).parse(sql) else {
+        return false;
+    };
+    !ast.statements.is_empty()
+        && ast.statements.iter().all(|statement| match statement {
+            Statement::Query(query) => read_only_query(query),
+            _ => false,
+        })
+        && !mentions_function(sql, WRITING_FUNCTIONS, &[])
+}
+
+/// Whether a result of `sql` can be served again later: it only reads and
+/// calls nothing like `now()` or `random()` that changes between runs.
+pub fn is_cacheable(sql: &str) -> bool {
+    // SQLite 用 date('now') 之类的写法取当前时间
+    is_read_only(sql) && !mentions_function(sql, VOLATILE_FUNCTIONS, &["now"])
+}
+
+/// A statement of a script split by [`split_statements`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptChunk {
+    pub sql: String,
+    /// Location of the chunk's first character in the script
+    pub start: Location,
+}
+
+/// Client-side directive line of a script.
+enum ScriptDirective {
+    /// `DELIMITER $$`, MySQL client syntax for statements containing `;`
+    Delimiter(String),
+    /// `GO`, the SQL Server batch separator
+    Go,
+}
+
+fn script_directive(line: &str) -> Option<ScriptDirective> {
+    let mut words = line.split_whitespace();
+    let first = words.next()?;
+    let second = words.next();
+    if words.next().is_some() {
+        return None;
+    }
+    if first.eq_ignore_ascii_case("DELIMITER") {
+        return second.map(|delimiter| ScriptDirective::Delimiter(delimiter.to_string()));
+    }
+    // GO 后面可以跟重复次数
+    (first.eq_ignore_ascii_case("GO") && second.is_none_or(|count| count.parse::<u32>().is_ok()))
+        .then_some(ScriptDirective::Go)
+}
+
+/// The `$tag$` opening a PostgreSQL dollar-quoted string at the start of
+/// `rest`. `$1` placeholders don't match.
+fn dollar_quote_tag(rest: &str) -> Option<&str> {
+    let body = rest.strip_prefix('$')?;
+    let tag = &body[..body.find('$')?];
+    (tag.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !tag.starts_with(|c: char| c.is_ascii_digit()))
+    .then(|| &rest[..tag.len() + 2])
+}
+
+/// The identifier-like word at the start of `text`.
+fn leading_word(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Tracks the `BEGIN ... END` blocks of a trigger, function or procedure
+/// body, whose statements end with `;` without ending the definition.
+#[derive(Default)]
+struct BlockDepth {
+    words: usize,
+    create: bool,
+    routine: bool,
+    depth: usize,
+}
+
+impl BlockDepth {
+    /// Look at the next word of the statement, `after` being the text that
+    /// follows it.
+    fn word(&mut self, word: &str, after: &str) {
+        let word = word.to_uppercase();
+        self.words += 1;
+        if self.words == 1 {
+            self.create = word == "CREATE";
+        } else if self.create && !self.routine {
+            self.routine = matches!(
+                word.as_str(),
+                "TRIGGER" | "FUNCTION" | "PROCEDURE" | "EVENT"
+            );
+        } else if self.routine {
+            match word.as_str() {
+                "BEGIN" => self.depth += 1,
+                "CASE" if self.depth > 0 => self.depth += 1,
+                // END IF 之类结束的块没有计数
+                "END" => {
+                    let next = leading_word(after.trim_start()).to_uppercase();
+                    if !matches!(next.as_str(), "IF" | "LOOP" | "WHILE" | "REPEAT") {
+                        self.depth = self.depth.saturating_sub(1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Split a script into its statements, keeping each one's text as written.
+/// The current delimiter ends a statement outside of quotes, comments,
+/// dollar-quoted bodies and the `BEGIN ... END` body of a trigger or
+/// routine. `DELIMITER` changes and `GO` batch separators are recognized on
+/// a line of their own between two statements and dropped, as are comments
+/// before a statement.
+pub fn split_statements(sql: &str) -> Vec<ScriptChunk> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut start = Location::empty();
+    let mut blocks = BlockDepth::default();
+    let mut flush = |current: &mut String, start: Location, blocks: &mut BlockDepth| {
+        let sql = current.trim_end();
+        if !sql.is_empty() {
+            chunks.push(ScriptChunk {
+                sql: sql.to_string(),
+                start,
+            });
+        }
+        current.clear();
+        *blocks = BlockDepth::default();
+    };
+
+    let mut delimiter = ";".to_string();
+    // 只有使用 DELIMITER 的 MySQL 脚本里反斜杠才是转义符
+    let mut backslash_escapes = false;
+    let mut quote: Option<char> = None;
+    let mut dollar_tag: Option<&str> = None;
+    let mut escaped = false;
+    let mut block_comment = false;
+    let mut line_start = 0;
+    for (line_index, line) in sql.split_inclusive('\n').enumerate() {
+        let line_offset = line_start;
+        line_start += line.len();
+        if quote.is_none() && dollar_tag.is_none() && !block_comment && current.is_empty() {
+            match script_directive(line) {
+                Some(ScriptDirective::Delimiter(next)) => {
+                    delimiter = next;
+                    backslash_escapes = true;
+                    continue;
+                }
+                Some(ScriptDirective::Go) => continue,
+                None => {}
+            }
+        }
+
+        let mut skip = 0;
+        let mut line_comment = false;
+        for (column, (offset, c)) in line.char_indices().enumerate() {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            let rest = &sql[line_offset + offset..];
+            let after_word = line[..offset]
+                .chars()
+                .next_back()
+                .is_some_and(|p| p.is_alphanumeric() || p == '_');
+            if line_comment {
+                // 行注释一直到行尾
+            } else if block_comment {
+                if rest.starts_with("*/") {
+                    block_comment = false;
+                    // 语句前的注释不保留
+                    if !current.is_empty() {
+                        current.push_str("*/");
+                    }
+                    skip = 1;
+                    continue;
+                }
+            } else if let Some(tag) = dollar_tag {
+                if rest.starts_with(tag) {
+                    current.push_str(tag);
+                    skip = tag.chars().count() - 1;
+                    dollar_tag = None;
+                    continue;
+                }
+            } else if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && backslash_escapes && q != '`' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            } else if rest.starts_with(delimiter.as_str())
+                && (blocks.depth == 0 || delimiter != ";")
+            {
+                flush(&mut current, start, &mut blocks);
+                skip = delimiter.chars().count() - 1;
+                continue;
+            } else if rest.starts_with("--") {
+                line_comment = true;
+            } else if rest.starts_with("/*") {
+                block_comment = true;
+            } else if matches!(c, '\'' | '"' | '`') {
+                quote = Some(c);
+            } else if let Some(tag) = dollar_quote_tag(rest).filter(|_| !after_word) {
+                if current.is_empty() {
+                    start = Location::new(line_index as u64 + 1, column as u64 + 1);
+                }
+                current.push_str(tag);
+                skip = tag.chars().count() - 1;
+                dollar_tag = Some(tag);
+                continue;
+            } else if (c.is_alphabetic() || c == '_') && !after_word {
+                let word = leading_word(rest);
+                blocks.word(word, &rest[word.len()..]);
+            }
+            if current.is_empty() {
+                if c.is_whitespace() || line_comment || block_comment {
+                    continue;
+                }
+                start = Location::new(line_index as u64 + 1, column as u64 + 1);
+            }
+            current.push(c);
+        }
+    }
+    flush(&mut current, start, &mut blocks);
+    chunks
+}
+
+fn read_only_query(query: &Query) -> bool {
+    query.locks.is_empty()
+        && query
+            .with
+            .iter()
+            .flat_map(|with| &with.cte_tables)
+            .all(|cte| read_only_query(&cte.query))
+        && read_only_set_expr(&query.body)
+}
+
+fn read_only_set_expr(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.into.is_none(),
+        SetExpr::Query(query) => read_only_query(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            read_only_set_expr(left) && read_only_set_expr(right)
+        }
+        SetExpr::Values(_) | SetExpr::Table(_) => true,
+        _ => false,
+    }
+}
+
+/// A statement's canonical text with every literal and placeholder
+/// replaced by `?`, so queries differing only in their values match.
+pub fn fingerprint(statement: &Statement) -> String {
+    let sql = statement.to_string();
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, &sql).tokenize() else {
+        return sql;
+    };
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Number(..)
+            | Token::SingleQuotedString(_)
+            | Token::TripleSingleQuotedString(_)
+            | Token::DollarQuotedString(_)
+            | Token::SingleQuotedByteStringLiteral(_)
+            | Token::DoubleQuotedByteStringLiteral(_)
+            | Token::NationalStringLiteral(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::UnicodeStringLiteral(_)
+            | Token::HexStringLiteral(_)
+            | Token::Placeholder(_) => "?".to_string(),
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+fn has_placeholder(sql: &str) -> bool {
+    Tokenizer::new(&GenericDialect {}, sql)
+        .tokenize()
+        .is_ok_and(|tokens| {
+            tokens
+                .iter()
+                .any(|token| matches!(token, Token::Placeholder(_)))
+        })
+}
+
+#[derive(Debug, PartialEq)]
 pub enum CompletionContext {
     None,
     TableName,
     ColumnName(String), // 包含表名
+    /// After `<schema>.` (or `<database>.` on MySQL) where a table name goes
+    SchemaQualified(String),
+    /// Right after a table in a FROM/JOIN clause, where a JOIN can follow
+    AfterTable {
+        table: String,
+        alias: Option<String>,
+    },
+    /// Inside the column list of `INSERT INTO <table> (`, with the columns
+    /// already listed before the cursor
+    InsertColumnList {
+        table: String,
+        listed: Vec<String>,
+    },
 }
 
 impl SqlAst {
-    pub fn code_lens(&self) -> anyhow::Result<Option<Vec<CodeLens>>> {
+    /// Run lenses for every statement. `connection_id` is the document's
+    /// associated connection, passed as the second command argument.
+    pub fn code_lens(&self, connection_id: Option<&str>) -> anyhow::Result<Option<Vec<CodeLens>>> {
         let mut code_lens = vec![];
         for statement in &self.statements {
+            // 将SQL语句作为参数传递给命令
+            let mut arguments = vec![serde_json::to_value(statement.to_string()).unwrap()];
+            if let Some(connection_id) = connection_id {
+                arguments.push(serde_json::Value::String(connection_id.to_string()));
+            }
             let command = Command {
                 title: "😼 Run SQL".to_string(),
                 command: CLIENT_EXECUTE_COMMAND.to_string(),
-                // 将SQL语句作为参数传递给命令
-                arguments: Some(vec![serde_json::to_value(statement.to_string()).unwrap()]),
+                arguments: Some(arguments),
             };
             code_lens.push(CodeLens {
                 range: Range {
@@ -48,90 +641,586 @@ impl SqlAst {
         Ok(Some(code_lens))
     }
 
+    /// Parse failures as LSP diagnostics.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|err| Diagnostic {
+                range: err.range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("db-viewer".to_string()),
+                message: err.message.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     pub fn get_completion_context(&self, position: Position) -> CompletionContext {
-        // 根据光标位置和SQL AST分析当前上下文
-        // 这需要深入解析SQL语法，但可以简化为一些基本模式匹配
-
-        // 例如：如果光标在FROM或JOIN后面，则为TableName上下文
-        // 如果光标在表名后面跟着点(.)，则为ColumnName上下文
-
-        // 实现细节依赖于您的SQL解析器
-
-        // 示例简化实现：
-        let line = position.line as usize;
-        let character = position.character as usize;
-
-        // 获取当前行的文本
-        // if let Some(stmt) = self.get_statement_at(line, character) {
-        //     let line_text = stmt.text.lines().nth(position.line as usize).unwrap_or("");
-        //     let prefix = &line_text[0..character as usize];
-
-        //     // 简单匹配：在FROM或JOIN后面提示表名
-        //     if prefix.to_uppercase().trim().ends_with("FROM")
-        //         || prefix.to_uppercase().contains("JOIN")
-        //     {
-        //         return CompletionContext::TableName;
-        //     }
-
-        //     // 简单匹配：在表名后面的点后提示列名
-        //     if let Some(table_name) = Self::extract_table_name_before_dot(prefix) {
-        //         return CompletionContext::ColumnName(table_name);
-        //     }
-        // }
-
-        CompletionContext::None
-    }
-
-    // 辅助函数：提取点号前的表名
-    fn extract_table_name_before_dot(text: &str) -> Option<String> {
-        // 这是一个简化实现，实际应用中需要更复杂的解析
-        let parts: Vec<&str> = text.trim().split('.').collect();
-        if parts.len() >= 2 {
-            let potential_table = parts[parts.len() - 2].trim();
-            // 从后往前查找第一个空格或特殊字符
-            if let Some(pos) =
-                potential_table.rfind(|c: char| c.is_whitespace() || "(),;".contains(c))
+        // 只分析光标所在语句中光标之前的部分
+        let prefix = &self.document[..self.offset_at(position)];
+        let statement = prefix.rsplit(';').next().unwrap_or_default();
+        let tokens = match Tokenizer::new(&GenericDialect {}, statement).tokenize() {
+            Ok(tokens) => tokens,
+            Err(_) => return CompletionContext::None,
+        };
+
+        let trailing_space = matches!(tokens.last(), Some(Token::Whitespace(_)));
+        let tokens: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| !matches!(t, Token::Whitespace(_)))
+            .collect();
+        let is_table_keyword = |word: &Word| {
+            matches!(
+                word.keyword,
+                Keyword::FROM | Keyword::JOIN | Keyword::INTO | Keyword::UPDATE
+            )
+        };
+        let is_join_source = |word: &Word| matches!(word.keyword, Keyword::FROM | Keyword::JOIN);
+
+        if let Some(context) = insert_column_list(&tokens, trailing_space) {
+            return context;
+        }
+
+        match tokens.as_slice() {
+            // FROM public. | FROM public.us
+            [.., Token::Word(keyword), Token::Word(schema), Token::Period]
+                if is_table_keyword(keyword) =>
+            {
+                CompletionContext::SchemaQualified(schema.value.clone())
+            }
+            [
+                ..,
+                Token::Word(keyword),
+                Token::Word(schema),
+                Token::Period,
+                Token::Word(_),
+            ] if !trailing_space && is_table_keyword(keyword) => {
+                CompletionContext::SchemaQualified(schema.value.clone())
+            }
+            // 在表名或别名后面的点后提示列名
+            [.., Token::Word(qualifier), Token::Period] => {
+                CompletionContext::ColumnName(self.resolve_table(&qualifier.value, position))
+            }
+            [.., Token::Word(qualifier), Token::Period, Token::Word(_)] if !trailing_space => {
+                CompletionContext::ColumnName(self.resolve_table(&qualifier.value, position))
+            }
+            // 在FROM或JOIN后面提示表名
+            [.., Token::Word(keyword)] if trailing_space && is_table_keyword(keyword) => {
+                CompletionContext::TableName
+            }
+            [.., Token::Word(keyword), Token::Word(_)]
+                if !trailing_space && is_table_keyword(keyword) =>
+            {
+                CompletionContext::TableName
+            }
+            // FROM users | FROM users u | FROM users AS u
+            [.., Token::Word(keyword), Token::Word(table)]
+                if trailing_space && is_join_source(keyword) =>
             {
-                return Some(potential_table[pos + 1..].trim().to_string());
-            } else {
-                return Some(potential_table.to_string());
+                CompletionContext::AfterTable {
+                    table: table.value.clone(),
+                    alias: None,
+                }
+            }
+            [
+                ..,
+                Token::Word(keyword),
+                Token::Word(table),
+                Token::Word(alias),
+            ]
+            | [
+                ..,
+                Token::Word(keyword),
+                Token::Word(table),
+                Token::Word(Word {
+                    keyword: Keyword::AS,
+                    ..
+                }),
+                Token::Word(alias),
+            ] if trailing_space
+                && is_join_source(keyword)
+                && alias.keyword == Keyword::NoKeyword =>
+            {
+                CompletionContext::AfterTable {
+                    table: table.value.clone(),
+                    alias: Some(alias.value.clone()),
+                }
             }
+            _ => CompletionContext::None,
+        }
+    }
+
+    /// Resolve a table alias used in the statement at `position`, names that
+    /// aren't aliases are returned unchanged.
+    fn resolve_table(&self, qualifier: &str, position: Position) -> String {
+        self.table_aliases(position)
+            .remove(qualifier)
+            .unwrap_or_else(|| qualifier.to_string())
+    }
+
+    /// Name of the function call enclosing `position` and the index of the
+    /// argument the cursor is in.
+    pub fn call_at(&self, position: Position) -> Option<(String, u32)> {
+        let prefix = &self.document[..self.offset_at(position)];
+        let statement = prefix.rsplit(';').next().unwrap_or_default();
+        let tokens = Tokenizer::new(&GenericDialect {}, statement)
+            .tokenize()
+            .ok()?;
+
+        // 每层括号记录函数名（如果有）和已经过的逗号数
+        let mut calls: Vec<(Option<String>, u32)> = Vec::new();
+        let mut previous: Option<&Token> = None;
+        for token in &tokens {
+            match token {
+                Token::LParen => {
+                    let name = match previous {
+                        Some(Token::Word(word)) => Some(word.value.to_uppercase()),
+                        _ => None,
+                    };
+                    calls.push((name, 0));
+                }
+                Token::RParen => {
+                    calls.pop();
+                }
+                Token::Comma => {
+                    if let Some((_, argument)) = calls.last_mut() {
+                        *argument += 1;
+                    }
+                }
+                _ => {}
+            }
+            if !matches!(token, Token::Whitespace(_)) {
+                previous = Some(token);
+            }
+        }
+
+        let (name, argument) = calls.pop()?;
+        name.map(|name| (name, argument))
+    }
+
+    /// The statement whose span contains `position`.
+    pub fn statement_at(&self, position: Position) -> Option<&Statement> {
+        let location = Location::new(position.line as u64 + 1, position.character as u64 + 1);
+        self.statements.iter().find(|statement| {
+            let span = statement.span();
+            span.start <= location && location <= span.end
+        })
+    }
+
+    /// Statements whose span touches `range`, in document order.
+    pub fn statements_in(&self, range: Range) -> Vec<&Statement> {
+        let start = Location::new(
+            range.start.line as u64 + 1,
+            range.start.character as u64 + 1,
+        );
+        let end = Location::new(range.end.line as u64 + 1, range.end.character as u64 + 1);
+        self.statements
+            .iter()
+            .filter(|statement| {
+                let span = statement.span();
+                span.start <= end && start <= span.end
+            })
+            .collect()
+    }
+
+    /// Map of aliases (and plain table names) to tables for the statement
+    /// containing `position`.
+    fn table_aliases(&self, position: Position) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        if let Some(statement) = self.statement_at(position) {
+            collect_statement_aliases(statement, &mut aliases);
+        }
+
+        if aliases.is_empty() {
+            // 正在编辑的语句通常无法解析，退回到扫描它的词法单元
+            let offset = self.offset_at(position);
+            let start = self.document[..offset]
+                .rfind(';')
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            let end = self.document[offset..]
+                .find(';')
+                .map(|i| offset + i)
+                .unwrap_or(self.document.len());
+            collect_token_aliases(&self.document[start..end], &mut aliases);
+        }
+        aliases
+    }
+
+    /// A folding range for every multi-line statement, CTE and subquery in
+    /// a FROM/JOIN.
+    pub fn folding_ranges(&self) -> Vec<FoldingRange> {
+        let mut spans = Vec::new();
+        for statement in &self.statements {
+            spans.push(statement.span());
+            match statement {
+                Statement::Query(query) => collect_query_spans(query, &mut spans),
+                Statement::Insert(insert) => {
+                    if let Some(source) = &insert.source {
+                        collect_query_spans(source, &mut spans);
+                    }
+                }
+                Statement::CreateView { query, .. } => collect_query_spans(query, &mut spans),
+                _ => {}
+            }
+        }
+        spans
+            .into_iter()
+            // 空 span 表示解析器没有记录位置
+            .filter(|span| span.start.line > 0 && span.end.line > span.start.line)
+            .map(|span| FoldingRange {
+                start_line: span.start.line as u32 - 1,
+                end_line: span.end.line as u32 - 1,
+                kind: Some(FoldingRangeKind::Region),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// The identifier being typed at a position, empty after a space or
+    /// punctuation.
+    pub fn word_before(&self, position: Position) -> String {
+        let prefix = &self.document[..self.offset_at(position)];
+        let start = prefix
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(prefix.len(), |(i, _)| i);
+        prefix[start..].to_string()
+    }
+
+    /// Byte offset of an LSP position in the document, clamped to its end.
+    fn offset_at(&self, position: Position) -> usize {
+        let mut offset = 0;
+        for (index, line) in self.document.split_inclusive('\n').enumerate() {
+            if index == position.line as usize {
+                let column = line
+                    .char_indices()
+                    .nth(position.character as usize)
+                    .map(|(i, _)| i)
+                    .unwrap_or_else(|| line.trim_end_matches(['\r', '\n']).len());
+                return offset + column;
+            }
+            offset += line.len();
+        }
+        self.document.len()
+    }
+}
+
+/// `INSERT [INTO] [schema.]table (col, ...` with the paren still open at the
+/// end of `tokens`. A word being typed right at the cursor isn't listed.
+fn insert_column_list(tokens: &[&Token], trailing_space: bool) -> Option<CompletionContext> {
+    let insert = tokens.iter().rposition(|t| {
+        matches!(
+            t,
+            Token::Word(Word {
+                keyword: Keyword::INSERT,
+                ..
+            })
+        )
+    })?;
+    let mut rest = &tokens[insert + 1..];
+    if let [
+        Token::Word(Word {
+            keyword: Keyword::INTO,
+            ..
+        }),
+        tail @ ..,
+    ] = rest
+    {
+        rest = tail;
+    }
+    let (table, rest) = match rest {
+        [
+            Token::Word(_),
+            Token::Period,
+            Token::Word(table),
+            Token::LParen,
+            tail @ ..,
+        ]
+        | [Token::Word(table), Token::LParen, tail @ ..] => (table, tail),
+        _ => return None,
+    };
+    if rest
+        .iter()
+        .any(|t| matches!(t, Token::LParen | Token::RParen))
+    {
+        return None;
+    }
+
+    let mut listed = rest;
+    if !trailing_space && let [head @ .., Token::Word(_)] = listed {
+        listed = head;
+    }
+    let listed = listed
+        .iter()
+        .filter_map(|t| match t {
+            Token::Word(word) => Some(word.value.clone()),
+            _ => None,
+        })
+        .collect();
+    Some(CompletionContext::InsertColumnList {
+        table: table.value.clone(),
+        listed,
+    })
+}
+
+/// Spans of the CTEs and derived tables nested in a query.
+fn collect_query_spans(query: &Query, spans: &mut Vec<Span>) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            spans.push(cte.query.span());
+            collect_query_spans(&cte.query, spans);
+        }
+    }
+    collect_set_expr_spans(&query.body, spans);
+}
+
+fn collect_set_expr_spans(body: &SetExpr, spans: &mut Vec<Span>) {
+    match body {
+        SetExpr::Select(select) => {
+            for table in &select.from {
+                for factor in std::iter::once(&table.relation)
+                    .chain(table.joins.iter().map(|join| &join.relation))
+                {
+                    if let TableFactor::Derived { subquery, .. } = factor {
+                        spans.push(subquery.span());
+                        collect_query_spans(subquery, spans);
+                    }
+                }
+            }
+        }
+        SetExpr::Query(query) => collect_query_spans(query, spans),
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_set_expr_spans(left, spans);
+            collect_set_expr_spans(right, spans);
+        }
+        _ => {}
+    }
+}
+
+fn collect_statement_aliases(statement: &Statement, aliases: &mut HashMap<String, String>) {
+    match statement {
+        Statement::Query(query) => collect_query_aliases(query, aliases),
+        Statement::Update { table, from, .. } => {
+            collect_table_aliases(table, aliases);
+            if let Some(
+                UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from),
+            ) = from
+            {
+                from.iter()
+                    .for_each(|table| collect_table_aliases(table, aliases));
+            }
+        }
+        Statement::Delete(delete) => {
+            let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+            from.iter()
+                .for_each(|table| collect_table_aliases(table, aliases));
+        }
+        _ => {}
+    }
+}
+
+fn collect_query_aliases(query: &Query, aliases: &mut HashMap<String, String>) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            collect_query_aliases(&cte.query, aliases);
+        }
+    }
+    collect_set_expr_aliases(&query.body, aliases);
+}
+
+fn collect_set_expr_aliases(body: &SetExpr, aliases: &mut HashMap<String, String>) {
+    match body {
+        SetExpr::Select(select) => select
+            .from
+            .iter()
+            .for_each(|table| collect_table_aliases(table, aliases)),
+        SetExpr::Query(query) => collect_query_aliases(query, aliases),
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_set_expr_aliases(left, aliases);
+            collect_set_expr_aliases(right, aliases);
+        }
+        _ => {}
+    }
+}
+
+fn collect_table_aliases(table: &TableWithJoins, aliases: &mut HashMap<String, String>) {
+    collect_factor_aliases(&table.relation, aliases);
+    for join in &table.joins {
+        collect_factor_aliases(&join.relation, aliases);
+    }
+}
+
+fn collect_factor_aliases(factor: &TableFactor, aliases: &mut HashMap<String, String>) {
+    match factor {
+        TableFactor::Table { name, alias, .. } => {
+            // 忽略 schema 前缀，模式缓存以表名为键
+            let Some(table) = name.0.last().and_then(|part| part.as_ident()) else {
+                return;
+            };
+            aliases.insert(table.value.clone(), table.value.clone());
+            if let Some(alias) = alias {
+                aliases.insert(alias.name.value.clone(), table.value.clone());
+            }
+        }
+        TableFactor::Derived { subquery, .. } => collect_query_aliases(subquery, aliases),
+        TableFactor::NestedJoin {
+            table_with_joins, ..
+        } => collect_table_aliases(table_with_joins, aliases),
+        _ => {}
+    }
+}
+
+/// Token based fallback of [`collect_statement_aliases`] for statements that
+/// don't parse: picks up `FROM|JOIN|UPDATE table [AS] alias[, ...]`.
+fn collect_token_aliases(sql: &str, aliases: &mut HashMap<String, String>) {
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, sql).tokenize() else {
+        return;
+    };
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let is_source = matches!(
+            &tokens[i],
+            Token::Word(word) if matches!(word.keyword, Keyword::FROM | Keyword::JOIN | Keyword::UPDATE)
+        );
+        i += 1;
+        if !is_source {
+            continue;
+        }
+
+        while let Some(Token::Word(word)) = tokens.get(i) {
+            let mut table = word.value.clone();
+            i += 1;
+            // schema.table
+            while let (Some(Token::Period), Some(Token::Word(part))) =
+                (tokens.get(i), tokens.get(i + 1))
+            {
+                table = part.value.clone();
+                i += 2;
+            }
+            aliases.insert(table.clone(), table.clone());
+
+            if matches!(tokens.get(i), Some(Token::Word(word)) if word.keyword == Keyword::AS) {
+                i += 1;
+            }
+            if let Some(Token::Word(alias)) = tokens.get(i)
+                && alias.keyword == Keyword::NoKeyword
+            {
+                aliases.insert(alias.value.clone(), table);
+                i += 1;
+            }
+            if tokens.get(i) != Some(&Token::Comma) {
+                break;
+            }
+            i += 1;
         }
-        None
     }
 }
 
 #[derive(Debug)]
 pub struct SqlParser {
     dialect: GenericDialect,
+    /// Parse each `;`-separated statement on its own so one bad statement
+    /// doesn't discard the rest of the document.
+    recover: bool,
 }
 
 impl SqlParser {
     pub(crate) fn new() -> Self {
         SqlParser {
             dialect: GenericDialect {},
+            recover: true,
         }
     }
 
+    /// Toggle best-effort parsing; when disabled any error fails the whole document.
+    pub(crate) fn with_recovery(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Parse each statement of [`split_statements`] on its own. Token
+    /// locations are moved to where the statement sits in the document.
     pub(crate) fn parse(&self, sql: &str) -> anyhow::Result<SqlAst> {
-        let mut tokens =
-            sqlparser::tokenizer::Tokenizer::new(&self.dialect, sql).with_unescape(true);
-        let mut vals: Vec<sqlparser::tokenizer::TokenWithSpan> = vec![];
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        for chunk in split_statements(sql) {
+            let mut tokens = Vec::new();
+            let _ = Tokenizer::new(&self.dialect, &chunk.sql)
+                .with_unescape(true)
+                .tokenize_with_location_into_buf(&mut tokens);
+            for token in &mut tokens {
+                token.span = Span::new(
+                    Self::shift(token.span.start, chunk.start),
+                    Self::shift(token.span.end, chunk.start),
+                );
+            }
+            let range = Self::chunk_range(&tokens);
+            match self.parse_tokens(tokens) {
+                Ok(mut stmts) => statements.append(&mut stmts),
+                Err(err) if !self.recover => {
+                    log(
+                        MessageType::ERROR,
+                        format!("Failed to parse SQL statement: {}", err),
+                    );
+                    return Err(anyhow::anyhow!(err));
+                }
+                Err(err) => {
+                    // 解析错误，跳过
+                    log(
+                        MessageType::ERROR,
+                        format!("Failed to parse SQL statement: {}", err),
+                    );
+                    errors.push(ParseFailure {
+                        range,
+                        message: err.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(SqlAst {
+            statements,
+            document: sql.to_string(),
+            errors,
+        })
+    }
 
-        // skip errors
-        let _ = tokens.tokenize_with_location_into_buf(&mut vals);
+    /// A location inside a chunk as a location in the whole script.
+    fn shift(location: Location, chunk_start: Location) -> Location {
+        if location.line == 0 {
+            return location;
+        }
+        let column = if location.line == 1 {
+            location.column + chunk_start.column - 1
+        } else {
+            location.column
+        };
+        Location::new(location.line + chunk_start.line - 1, column)
+    }
+
+    fn parse_tokens(
+        &self,
+        tokens: Vec<TokenWithSpan>,
+    ) -> Result<Vec<Statement>, sqlparser::parser::ParserError> {
         let mut ast =
-            sqlparser::parser::Parser::new(&self.dialect).with_tokens_with_locations(vals);
+            sqlparser::parser::Parser::new(&self.dialect).with_tokens_with_locations(tokens);
         let mut stmts = Vec::new();
         let mut expecting_statement_delimiter = false;
         loop {
-            while ast.consume_token(&sqlparser::tokenizer::Token::SemiColon) {
+            while ast.consume_token(&Token::SemiColon) {
                 expecting_statement_delimiter = false;
             }
             match ast.peek_token().token {
-                sqlparser::tokenizer::Token::EOF => break,
+                Token::EOF => break,
                 // end of statement
-                sqlparser::tokenizer::Token::Word(word) => {
+                Token::Word(word) => {
                     if expecting_statement_delimiter
                         && word.keyword == sqlparser::keywords::Keyword::END
                     {
@@ -141,26 +1230,45 @@ impl SqlParser {
                 _ => {}
             }
 
-            match ast.parse_statement() {
-                // 解析成功，继续
-                Ok(statement) => {
-                    stmts.push(statement);
-                    expecting_statement_delimiter = true;
-                }
-                Err(err) => {
-                    // 解析错误，跳过
-                    log(
-                        MessageType::ERROR,
-                        format!("Failed to parse SQL statement: {}", err),
-                    );
-                    break;
-                }
-            }
+            // 解析成功，继续
+            stmts.push(ast.parse_statement()?);
+            expecting_statement_delimiter = true;
+        }
+        Ok(stmts)
+    }
+
+    /// The range covered by the non-whitespace tokens of a chunk.
+    fn chunk_range(chunk: &[TokenWithSpan]) -> Range {
+        let mut significant = chunk
+            .iter()
+            .filter(|t| !matches!(t.token, Token::Whitespace(_)));
+        let first = significant
+            .next()
+            .map(|t| t.span.start)
+            .unwrap_or(Location::empty());
+        let last = significant.last().map(|t| t.span.end).unwrap_or(first);
+        Range {
+            start: to_position(first),
+            end: to_position(last),
+        }
+    }
+
+    /// Classify a query by its first statement, falling back to a keyword
+    /// check when it can't be parsed.
+    pub(crate) fn result_kind(&self, sql: &str) -> ResultKind {
+        let mut tokens =
+            sqlparser::tokenizer::Tokenizer::new(&self.dialect, sql).with_unescape(true);
+        let mut vals: Vec<TokenWithSpan> = vec![];
+        let _ = tokens.tokenize_with_location_into_buf(&mut vals);
+        let kind = self
+            .parse_tokens(vals)
+            .ok()
+            .and_then(|statements| statements.first().map(ResultKind::of));
+        match kind {
+            Some(kind) => kind,
+            None if sql.trim().to_lowercase().starts_with("select") => ResultKind::Rows,
+            None => ResultKind::Affected,
         }
-        Ok(SqlAst {
-            statements: stmts,
-            document: sql.to_string(),
-        })
     }
 }
 
@@ -182,7 +1290,7 @@ mod tests {
         CREATE TABLE orders (id INT, user_id INT, amount DECIMAL);
         ";
         let result = parser.parse(sql).unwrap();
-        let code_lens = result.code_lens().unwrap().unwrap();
+        let code_lens = result.code_lens(None).unwrap().unwrap();
         assert_eq!(code_lens.len(), 5);
 
         for code_len in code_lens {
@@ -209,4 +1317,352 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_result_kind() {
+        let parser = SqlParser::new();
+        assert_eq!(parser.result_kind("SELECT * FROM users"), ResultKind::Rows);
+        assert_eq!(
+            parser.result_kind("WITH t AS (SELECT 1) SELECT * FROM t"),
+            ResultKind::Rows
+        );
+        assert_eq!(
+            parser.result_kind("DELETE FROM users WHERE id = 1"),
+            ResultKind::Affected
+        );
+        assert_eq!(
+            parser.result_kind("DELETE FROM users WHERE id = 1 RETURNING id"),
+            ResultKind::Rows
+        );
+        assert_eq!(
+            parser.result_kind("UPDATE users SET age = 1"),
+            ResultKind::Affected
+        );
+    }
+
+    #[test]
+    fn test_affected_objects() {
+        let parse = |sql: &str| SqlParser::new().parse(sql).unwrap().statements.remove(0);
+        let statement = parse("DELETE FROM orders WHERE id = 1");
+        assert_eq!(statement_kind(&statement), "DELETE");
+        assert_eq!(affected_objects(&statement), vec!["orders"]);
+
+        let statement = parse("DROP TABLE a, b");
+        assert_eq!(statement_kind(&statement), "DROP TABLE");
+        assert_eq!(affected_objects(&statement), vec!["a", "b"]);
+
+        let statement = parse("UPDATE public.users SET age = 1");
+        assert_eq!(affected_objects(&statement), vec!["public.users"]);
+        assert!(!is_ddl(&statement));
+        assert!(is_ddl(&parse("ALTER TABLE users ADD COLUMN age INT")));
+        assert!(commits_implicitly("-- add\ncreate index i on t (a)"));
+        assert!(!commits_implicitly("CREATE TEMPORARY TABLE t (a INT)"));
+        assert!(!commits_implicitly("UPDATE t SET a = 1"));
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("SELECT * FROM users"));
+        assert!(is_read_only(
+            "WITH recent AS (SELECT * FROM orders) SELECT * FROM recent UNION SELECT * FROM old"
+        ));
+        assert!(!is_read_only("SELECT * FROM users FOR UPDATE"));
+        assert!(!is_read_only("SELECT * INTO backup FROM users"));
+        assert!(!is_read_only("SELECT 1; DELETE FROM users"));
+        assert!(!is_read_only("UPDATE users SET name = 'x'"));
+        assert!(!is_read_only("SELEC broken"));
+        assert!(!is_read_only("SELECT nextval('orders_id_seq')"));
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        assert!(is_cacheable("SELECT id, \"now\" FROM events"));
+        assert!(!is_cacheable(
+            "SELECT * FROM events WHERE at > now() - interval '1 day'"
+        ));
+        assert!(!is_cacheable("SELECT CURRENT_TIMESTAMP"));
+        assert!(!is_cacheable("SELECT datetime('now')"));
+        assert!(!is_cacheable("SELECT * FROM users FOR UPDATE"));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let parse = |sql: &str| SqlParser::new().parse(sql).unwrap().statements.remove(0);
+        let statement = parse("select *  from users where id = 42 and name = 'it''s' limit $1");
+        assert_eq!(
+            fingerprint(&statement),
+            "SELECT * FROM users WHERE id = ? AND name = ? LIMIT ?"
+        );
+        assert_eq!(
+            fingerprint(&parse("SELECT a FROM t WHERE b IN (1, 2)")),
+            "SELECT a FROM t WHERE b IN (?, ?)"
+        );
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let sql = |chunks: Vec<ScriptChunk>| -> Vec<String> {
+            chunks.into_iter().map(|c| c.sql).collect()
+        };
+        assert_eq!(
+            sql(split_statements("SELECT 1; SELECT ';'\n-- done\n;")),
+            vec!["SELECT 1", "SELECT ';'\n-- done"]
+        );
+
+        let script = "DELIMITER $$\n\
+            CREATE PROCEDURE p() BEGIN SELECT ';'; SELECT 2; END$$\n\
+            DELIMITER ;\n\
+            -- done\n\
+            CALL p();\n";
+        let chunks = split_statements(script);
+        assert_eq!(chunks[1].start, Location::new(5, 1));
+        assert_eq!(
+            sql(chunks),
+            vec![
+                "CREATE PROCEDURE p() BEGIN SELECT ';'; SELECT 2; END",
+                "CALL p()"
+            ]
+        );
+
+        let chunks = split_statements("SELECT 1;\ngo\n  SELECT 2; SELECT 3;\nGO 2\n");
+        assert_eq!(chunks[1].start, Location::new(3, 3));
+        assert_eq!(sql(chunks), vec!["SELECT 1", "SELECT 2", "SELECT 3"]);
+        assert_eq!(
+            sql(split_statements(
+                "CREATE TRIGGER t AFTER INSERT ON a BEGIN\n\
+                 UPDATE b SET n = CASE WHEN n > 0 THEN n + 1 ELSE 1 END;\n\
+                 IF n > 1 THEN SELECT 1; END IF;\n\
+                 END;\nBEGIN; COMMIT"
+            ))
+            .len(),
+            3
+        );
+        assert_eq!(
+            sql(split_statements(
+                "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ \
+                 LANGUAGE plpgsql; SELECT $1"
+            )),
+            vec![
+                "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql",
+                "SELECT $1"
+            ]
+        );
+        // 语句中间单独一行的 go 是列名
+        assert_eq!(
+            sql(split_statements("SELECT id,\ngo\nFROM t")),
+            vec!["SELECT id,\ngo\nFROM t"]
+        );
+
+        // 按语句解析时位置对应原文
+        let ast = SqlParser::new()
+            .parse("SELECT 1;\nGO\nSELEC 2;\nGO\nSELECT 3")
+            .unwrap();
+        assert_eq!(ast.statements.len(), 2);
+        assert_eq!(ast.errors[0].range.start, Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_count_query() {
+        assert_eq!(
+            count_query(
+                "SELECT id, name FROM users WHERE age > ? ORDER BY name LIMIT 50 OFFSET 100",
+                &DatabaseType::SQLite
+            )
+            .as_deref(),
+            Some("SELECT CAST(COUNT(*) AS TEXT) FROM users WHERE age > ?")
+        );
+        assert_eq!(
+            count_query(
+                "SELECT city, COUNT(*) FROM users GROUP BY city ORDER BY 2 DESC",
+                &DatabaseType::SQLite
+            )
+            .as_deref(),
+            Some(
+                "SELECT CAST(COUNT(*) AS TEXT) FROM (SELECT city, COUNT(*) FROM users GROUP BY city) AS counted"
+            )
+        );
+        assert_eq!(
+            count_query("SELECT MAX(age) FROM users", &DatabaseType::SQLite).as_deref(),
+            Some("SELECT CAST(COUNT(*) AS TEXT) FROM (SELECT MAX(age) FROM users) AS counted")
+        );
+        // LIMIT 绑定了参数，去掉后参数个数就对不上了
+        assert_eq!(
+            count_query("SELECT * FROM users LIMIT ?", &DatabaseType::SQLite),
+            None
+        );
+        assert_eq!(
+            count_query(
+                "SELECT ?, name FROM t WHERE id > ? ORDER BY name",
+                &DatabaseType::SQLite
+            )
+            .as_deref(),
+            Some(
+                "SELECT CAST(COUNT(*) AS TEXT) FROM (SELECT ?, name FROM t WHERE id > ?) AS counted"
+            )
+        );
+        assert_eq!(
+            count_query("SELECT * FROM users", &DatabaseType::MySQL).as_deref(),
+            Some("SELECT CAST(COUNT(*) AS CHAR) FROM users")
+        );
+        assert_eq!(
+            count_query("DELETE FROM users", &DatabaseType::SQLite),
+            None
+        );
+        assert_eq!(
+            count_query("SELECT 1; SELECT 2", &DatabaseType::SQLite),
+            None
+        );
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let sql = "SELECT 1;\nWITH recent AS (\n  SELECT *\n  FROM orders\n)\nSELECT *\nFROM recent r\nJOIN (\n  SELECT id\n  FROM users\n) u ON u.id = r.user_id;";
+        let ast = SqlParser::new().parse(sql).unwrap();
+        let ranges: Vec<(u32, u32)> = ast
+            .folding_ranges()
+            .iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect();
+        assert!(ranges.contains(&(1, 10)));
+        assert!(ranges.contains(&(2, 3)));
+        assert!(ranges.contains(&(8, 9)));
+        assert!(!ranges.iter().any(|(start, _)| *start == 0));
+    }
+
+    #[test]
+    fn test_parse_recovery() {
+        let sql = "
+        SELECT * FROM users;
+        SELEC broken FROM;
+        DELETE FROM users WHERE id = 1;
+        ";
+        let ast = SqlParser::new().parse(sql).unwrap();
+        assert_eq!(ast.statements.len(), 2);
+        assert_eq!(ast.errors.len(), 1);
+        assert_eq!(ast.errors[0].range.start.line, 2);
+
+        assert!(SqlParser::new().with_recovery(false).parse(sql).is_err());
+    }
+
+    #[test]
+    fn test_completion_context() {
+        let context = |sql: &str| {
+            let ast = SqlParser::new().parse(sql).unwrap();
+            let line = sql.lines().count() as u32 - 1;
+            let character = sql.lines().last().unwrap().len() as u32;
+            ast.get_completion_context(Position { line, character })
+        };
+
+        assert_eq!(context("SELECT * FROM "), CompletionContext::TableName);
+        assert_eq!(context("SELECT * FROM us"), CompletionContext::TableName);
+        assert_eq!(
+            context("SELECT users."),
+            CompletionContext::ColumnName("users".to_string())
+        );
+        assert_eq!(
+            context("SELECT * FROM users u "),
+            CompletionContext::AfterTable {
+                table: "users".to_string(),
+                alias: Some("u".to_string()),
+            }
+        );
+        assert_eq!(context("SELECT 1; SELECT "), CompletionContext::None);
+        assert_eq!(
+            context("SELECT * FROM public."),
+            CompletionContext::SchemaQualified("public".to_string())
+        );
+        assert_eq!(
+            context("SELECT * FROM sales.ord"),
+            CompletionContext::SchemaQualified("sales".to_string())
+        );
+        assert_eq!(
+            context("INSERT INTO users ("),
+            CompletionContext::InsertColumnList {
+                table: "users".to_string(),
+                listed: vec![],
+            }
+        );
+        assert_eq!(
+            context("INSERT INTO public.users (id, name, em"),
+            CompletionContext::InsertColumnList {
+                table: "users".to_string(),
+                listed: vec!["id".to_string(), "name".to_string()],
+            }
+        );
+        assert_eq!(
+            context("INSERT INTO users (id) VALUES ("),
+            CompletionContext::None
+        );
+    }
+
+    #[test]
+    fn test_alias_resolution() {
+        // 语句无法解析时扫描词法单元
+        let sql = "SELECT u. FROM users u";
+        let ast = SqlParser::new().parse(sql).unwrap();
+        assert_eq!(
+            ast.get_completion_context(Position {
+                line: 0,
+                character: 9
+            }),
+            CompletionContext::ColumnName("users".to_string())
+        );
+
+        // 语句可以解析时遍历 FROM/JOIN
+        let sql = "SELECT o.id FROM users AS u JOIN orders o ON o.user_id = u.id";
+        let ast = SqlParser::new().parse(sql).unwrap();
+        assert_eq!(
+            ast.get_completion_context(Position {
+                line: 0,
+                character: 10
+            }),
+            CompletionContext::ColumnName("orders".to_string())
+        );
+    }
+
+    #[test]
+    fn test_call_at() {
+        let call_at = |sql: &str| {
+            let ast = SqlParser::new().parse(sql).unwrap();
+            let character = sql.len() as u32;
+            ast.call_at(Position { line: 0, character })
+        };
+        assert_eq!(call_at("SELECT count("), Some(("COUNT".to_string(), 0)));
+        assert_eq!(
+            call_at("SELECT coalesce(a, substring(b, 1"),
+            Some(("SUBSTRING".to_string(), 1))
+        );
+        assert_eq!(
+            call_at("SELECT coalesce(a, (1 + 2), "),
+            Some(("COALESCE".to_string(), 2))
+        );
+        assert_eq!(call_at("SELECT count(*) "), None);
+    }
+
+    #[test]
+    fn test_statement_at() {
+        let sql = "SELECT 1;\nSELECT 2\nFROM t;\n";
+        let ast = SqlParser::new().parse(sql).unwrap();
+        let at = |line, character| {
+            ast.statement_at(Position { line, character })
+                .map(|s| s.to_string())
+        };
+        assert_eq!(at(0, 3).as_deref(), Some("SELECT 1"));
+        assert_eq!(at(2, 2).as_deref(), Some("SELECT 2 FROM t"));
+        assert_eq!(at(3, 0), None);
+
+        let range = |start, end| Range {
+            start: Position {
+                line: start,
+                character: 0,
+            },
+            end: Position {
+                line: end,
+                character: 1,
+            },
+        };
+        assert_eq!(ast.statements_in(range(0, 1)).len(), 2);
+        assert_eq!(ast.statements_in(range(2, 2)).len(), 1);
+        assert!(ast.statements_in(range(3, 3)).is_empty());
+    }
 }
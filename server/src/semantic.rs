@@ -0,0 +1,150 @@
+use sqlparser::{
+    dialect::GenericDialect,
+    keywords::Keyword,
+    tokenizer::{Token, TokenWithSpan, Tokenizer, Whitespace},
+};
+use tower_lsp::lsp_types::{SemanticToken, SemanticTokenType, SemanticTokensLegend};
+
+/// Token types in legend order, a token's `token_type` indexes this list.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+];
+
+const KEYWORD: u32 = 0;
+const IDENTIFIER: u32 = 1;
+const STRING: u32 = 2;
+const NUMBER: u32 = 3;
+const COMMENT: u32 = 4;
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    }
+}
+
+fn token_type(token: &Token) -> Option<u32> {
+    match token {
+        Token::Word(word) if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword => {
+            Some(KEYWORD)
+        }
+        Token::Word(_) => Some(IDENTIFIER),
+        Token::Number(..) => Some(NUMBER),
+        Token::Whitespace(Whitespace::SingleLineComment { .. })
+        | Token::Whitespace(Whitespace::MultiLineComment(_)) => Some(COMMENT),
+        Token::SingleQuotedString(_)
+        | Token::DoubleQuotedString(_)
+        | Token::TripleSingleQuotedString(_)
+        | Token::TripleDoubleQuotedString(_)
+        | Token::DollarQuotedString(_)
+        | Token::SingleQuotedByteStringLiteral(_)
+        | Token::DoubleQuotedByteStringLiteral(_)
+        | Token::TripleSingleQuotedByteStringLiteral(_)
+        | Token::TripleDoubleQuotedByteStringLiteral(_)
+        | Token::SingleQuotedRawStringLiteral(_)
+        | Token::DoubleQuotedRawStringLiteral(_)
+        | Token::TripleSingleQuotedRawStringLiteral(_)
+        | Token::TripleDoubleQuotedRawStringLiteral(_)
+        | Token::NationalStringLiteral(_)
+        | Token::EscapedStringLiteral(_)
+        | Token::UnicodeStringLiteral(_)
+        | Token::HexStringLiteral(_) => Some(STRING),
+        _ => None,
+    }
+}
+
+/// Semantic tokens of a document, relative encoded as the protocol expects.
+/// When the tokenizer fails, e.g. on an unterminated string, the tokens up
+/// to that point are returned.
+pub fn semantic_tokens(text: &str) -> Vec<SemanticToken> {
+    let mut tokens: Vec<TokenWithSpan> = Vec::new();
+    // 出错时保留已识别的 token
+    let _ = Tokenizer::new(&GenericDialect {}, text).tokenize_with_location_into_buf(&mut tokens);
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut result = Vec::new();
+    let (mut prev_line, mut prev_start) = (0u32, 0u32);
+    for token in tokens {
+        let Some(token_type) = token_type(&token.token) else {
+            continue;
+        };
+        let (start, end) = (token.span.start, token.span.end);
+        if start.line == 0 {
+            continue;
+        }
+        // 跨行的 token 按行拆开，客户端不一定支持多行 token
+        for line in start.line..=end.line {
+            let from = if line == start.line { start.column } else { 1 };
+            let to = if line == end.line {
+                end.column
+            } else {
+                lines
+                    .get(line as usize - 1)
+                    .map(|l| l.chars().count() as u64 + 1)
+                    .unwrap_or(from)
+            };
+            if to <= from {
+                continue;
+            }
+            let (line, column) = ((line - 1) as u32, (from - 1) as u32);
+            result.push(SemanticToken {
+                delta_line: line - prev_line,
+                delta_start: if line == prev_line {
+                    column - prev_start
+                } else {
+                    column
+                },
+                length: (to - from) as u32,
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            (prev_line, prev_start) = (line, column);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(delta_line: u32, delta_start: u32, length: u32, token_type: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let tokens = semantic_tokens("SELECT qty, 'a'\n-- note\nFROM t WHERE n = 10");
+        assert_eq!(
+            tokens,
+            vec![
+                token(0, 0, 6, KEYWORD),
+                token(0, 7, 3, IDENTIFIER),
+                token(0, 5, 3, STRING),
+                // 单行注释包含换行符，只标记注释文本
+                token(1, 0, 7, COMMENT),
+                token(1, 0, 4, KEYWORD),
+                token(0, 5, 1, IDENTIFIER),
+                token(0, 2, 5, KEYWORD),
+                token(0, 6, 1, IDENTIFIER),
+                token(0, 4, 2, NUMBER),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_tokens_unterminated() {
+        let tokens = semantic_tokens("SELECT 'abc");
+        assert_eq!(tokens, vec![token(0, 0, 6, KEYWORD)]);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use serde::Deserialize;
+use tower_lsp::lsp_types::MessageType;
+
+use crate::{
+    db::{cache::ResultCacheSettings, dialect::Dialect, value::FormatOptions},
+    lint::LintSettings,
+};
+
+static SETTINGS: once_cell::sync::Lazy<RwLock<Settings>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(Settings::default()));
+/// The JSON the current settings were read from, which changes are merged
+/// onto.
+static OPTIONS: once_cell::sync::Lazy<RwLock<serde_json::Value>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(serde_json::Value::Object(Default::default())));
+
+/// Server settings, sent by the client as initialization options.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Driver specific settings applied when a pool connects, e.g.
+    /// `application_name` for PostgreSQL or `sql_mode` for MySQL.
+    /// Keys a driver doesn't know are skipped with a warning.
+    pub driver_options: HashMap<String, serde_json::Value>,
+    /// Render 16 byte MySQL `BINARY` values as UUIDs instead of base64.
+    pub binary_uuid: bool,
+    /// Force the quoting style of SQL returned to the client, e.g. `"ansi"`
+    /// to always double-quote identifiers. SQL the server runs itself keeps
+    /// the connection's own style.
+    pub dialect: Option<Dialect>,
+    /// Maximum number of cached connection pools, the least recently used
+    /// idle pool is closed when exceeded. Zero means no limit.
+    pub max_pools: usize,
+    /// Maximum number of pools establishing their connections at the same
+    /// time, so opening many connections doesn't overwhelm a server. Zero
+    /// means no limit.
+    pub max_concurrent_connects: usize,
+    pub lint: LintSettings,
+    /// Most completion items returned at once, the best matches are kept.
+    /// Defaults to [`DEFAULT_MAX_COMPLETION_ITEMS`], zero means no limit.
+    pub max_completion_items: Option<usize>,
+    /// Rendering of dates and NULLs in results, re-read on every query.
+    pub format: FormatOptions,
+    /// Maximum connections of each pool, only used for pools created
+    /// afterwards. One of them is kept for metadata lookups. Defaults to
+    /// [`DEFAULT_POOL_SIZE`].
+    pub pool_size: Option<u32>,
+    /// Times a query waits again for a free connection when the pool is
+    /// exhausted. Defaults to [`DEFAULT_ACQUIRE_RETRIES`].
+    pub acquire_retries: Option<u32>,
+    /// Least severe server message forwarded to the client's log.
+    pub log_level: LogLevel,
+    /// Messages buffered for the client's log before the oldest are
+    /// dropped. Defaults to [`DEFAULT_LOG_CAPACITY`], only read at
+    /// initialization.
+    pub log_capacity: Option<usize>,
+    /// Ping each pool at this interval in seconds so idle connections
+    /// aren't dropped by the server. Unset or zero disables it.
+    pub keep_alive_secs: Option<u64>,
+    /// Roll back sessions left unused for this many seconds, so a forgotten
+    /// session doesn't keep its connection and locks. Defaults to
+    /// [`DEFAULT_SESSION_IDLE_SECS`], zero disables it.
+    pub session_idle_secs: Option<u64>,
+    pub result_cache: ResultCacheSettings,
+    /// Named query templates run with `RunMacroCommand`, e.g.
+    /// `"SELECT * FROM ${table:ident} WHERE id = ${id}"`.
+    pub macros: HashMap<String, String>,
+    /// `application_name` of PostgreSQL connections, shown in
+    /// `pg_stat_activity`. Defaults to [`DEFAULT_APPLICATION_NAME`], an
+    /// `application_name` in the connection string or `driver_options`
+    /// takes precedence.
+    pub application_name: Option<String>,
+    /// Prefix PostgreSQL queries run from a document with a comment naming
+    /// its URI, so they can be traced back to the editor.
+    pub tag_queries: bool,
+    /// Gzip and base64 encode command results whose JSON is at least this
+    /// many bytes, flagging them `compressed`. Unset disables it, needs the
+    /// `compress` feature.
+    pub compress_threshold: Option<usize>,
+}
+
+pub const DEFAULT_POOL_SIZE: u32 = 5;
+pub const DEFAULT_ACQUIRE_RETRIES: u32 = 2;
+pub const DEFAULT_MAX_COMPLETION_ITEMS: usize = 200;
+pub const DEFAULT_LOG_CAPACITY: usize = 100;
+pub const DEFAULT_APPLICATION_NAME: &str = "vscode-db-viewer";
+pub const DEFAULT_SESSION_IDLE_SECS: u64 = 900;
+
+impl Settings {
+    pub fn pool_size(&self) -> u32 {
+        self.pool_size.unwrap_or(DEFAULT_POOL_SIZE)
+    }
+
+    pub fn acquire_retries(&self) -> u32 {
+        self.acquire_retries.unwrap_or(DEFAULT_ACQUIRE_RETRIES)
+    }
+
+    pub fn max_completion_items(&self) -> usize {
+        self.max_completion_items
+            .unwrap_or(DEFAULT_MAX_COMPLETION_ITEMS)
+    }
+
+    pub fn log_capacity(&self) -> usize {
+        self.log_capacity.unwrap_or(DEFAULT_LOG_CAPACITY)
+    }
+
+    pub fn session_idle_timeout(&self) -> Option<Duration> {
+        let secs = self.session_idle_secs.unwrap_or(DEFAULT_SESSION_IDLE_SECS);
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    pub fn application_name(&self) -> &str {
+        self.application_name
+            .as_deref()
+            .unwrap_or(DEFAULT_APPLICATION_NAME)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warning,
+    Info,
+    #[default]
+    Log,
+}
+
+impl LogLevel {
+    /// Whether a message of type `message_type` passes this threshold.
+    pub fn allows(self, message_type: MessageType) -> bool {
+        let level = match message_type {
+            MessageType::ERROR => LogLevel::Error,
+            MessageType::WARNING => LogLevel::Warning,
+            MessageType::INFO => LogLevel::Info,
+            _ => LogLevel::Log,
+        };
+        level <= self
+    }
+}
+
+pub fn get() -> Settings {
+    SETTINGS.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+pub fn set(settings: Settings) {
+    if let Ok(mut current) = SETTINGS.write() {
+        *current = settings;
+    }
+}
+
+/// Apply settings sent by the client. Objects are merged onto the options
+/// received so far, so a change only needs to carry the fields it changes.
+/// Nothing is applied when the merged options are invalid.
+pub fn update(changes: serde_json::Value) -> Result<(), serde_json::Error> {
+    let mut options = OPTIONS.read().map(|o| o.clone()).unwrap_or_default();
+    merge(&mut options, changes);
+    set(serde_json::from_value(options.clone())?);
+    if let Ok(mut current) = OPTIONS.write() {
+        *current = options;
+    }
+    Ok(())
+}
+
+fn merge(target: &mut serde_json::Value, changes: serde_json::Value) {
+    match (target, changes) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(changes)) => {
+            for (key, value) in changes {
+                merge(target.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (target, changes) => *target = changes,
+    }
+}
+
+/// Render a driver option value without JSON string quotes.
+pub fn option_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut options = serde_json::json!({ "pool_size": 5, "format": { "null_display": "-" } });
+        merge(
+            &mut options,
+            serde_json::json!({ "format": { "blob_threshold": 10 } }),
+        );
+        assert_eq!(
+            options,
+            serde_json::json!({
+                "pool_size": 5,
+                "format": { "null_display": "-", "blob_threshold": 10 },
+            })
+        );
+    }
+}
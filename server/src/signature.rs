@@ -0,0 +1,188 @@
+use tower_lsp::lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, SignatureHelp, SignatureInformation,
+};
+
+use crate::db::DatabaseType;
+
+/// A known SQL function. `backends` is empty for standard functions.
+pub struct FunctionSignature {
+    pub name: &'static str,
+    pub parameters: &'static [&'static str],
+    pub description: &'static str,
+    pub backends: &'static [DatabaseType],
+}
+
+/// Whether the last parameter can repeat.
+fn is_variadic(signature: &FunctionSignature) -> bool {
+    signature
+        .parameters
+        .last()
+        .is_some_and(|p| p.ends_with("..."))
+}
+
+macro_rules! function {
+    ($name:literal, [$($param:literal),*], $description:literal) => {
+        function!($name, [$($param),*], $description, [])
+    };
+    ($name:literal, [$($param:literal),*], $description:literal, [$($backend:ident),*]) => {
+        FunctionSignature {
+            name: $name,
+            parameters: &[$($param),*],
+            description: $description,
+            backends: &[$(DatabaseType::$backend),*],
+        }
+    };
+}
+
+pub const FUNCTIONS: &[FunctionSignature] = &[
+    function!(
+        "COUNT",
+        ["expression"],
+        "Number of non-null values, or rows for `*`"
+    ),
+    function!("SUM", ["expression"], "Sum of non-null values"),
+    function!("AVG", ["expression"], "Average of non-null values"),
+    function!("MIN", ["expression"], "Smallest value"),
+    function!("MAX", ["expression"], "Largest value"),
+    function!("COALESCE", ["value", "value..."], "First non-null argument"),
+    function!(
+        "NULLIF",
+        ["value1", "value2"],
+        "NULL if both arguments are equal, else the first"
+    ),
+    function!(
+        "SUBSTRING",
+        ["string", "start", "length"],
+        "Part of a string, `start` is 1-based"
+    ),
+    function!("UPPER", ["string"], "String in upper case"),
+    function!("LOWER", ["string"], "String in lower case"),
+    function!("LENGTH", ["string"], "Length of a string"),
+    function!(
+        "TRIM",
+        ["string"],
+        "String without leading and trailing spaces"
+    ),
+    function!(
+        "REPLACE",
+        ["string", "from", "to"],
+        "Replace every occurrence of `from`"
+    ),
+    function!(
+        "ROUND",
+        ["number", "decimals"],
+        "Number rounded to `decimals` places"
+    ),
+    function!("ABS", ["number"], "Absolute value"),
+    function!(
+        "CAST",
+        ["expression AS type"],
+        "Convert a value to another type"
+    ),
+    function!(
+        "CONCAT",
+        ["string", "string..."],
+        "Concatenation of the arguments",
+        [MySQL, PostgreSQL]
+    ),
+    function!(
+        "IFNULL",
+        ["expression", "fallback"],
+        "`fallback` when `expression` is NULL",
+        [MySQL, SQLite]
+    ),
+    function!(
+        "GROUP_CONCAT",
+        ["expression"],
+        "Values of a group joined with commas",
+        [MySQL]
+    ),
+    function!(
+        "GROUP_CONCAT",
+        ["expression", "separator"],
+        "Values of a group joined by `separator`",
+        [SQLite]
+    ),
+    function!(
+        "DATE_FORMAT",
+        ["date", "format"],
+        "Format a date, e.g. `'%Y-%m-%d'`",
+        [MySQL]
+    ),
+    function!("NOW", [], "Current date and time", [MySQL, PostgreSQL]),
+    function!(
+        "STRING_AGG",
+        ["expression", "delimiter"],
+        "Values of a group joined by `delimiter`",
+        [PostgreSQL]
+    ),
+    function!(
+        "TO_CHAR",
+        ["value", "format"],
+        "Format a date or number, e.g. `'YYYY-MM-DD'`",
+        [PostgreSQL]
+    ),
+    function!(
+        "DATE_TRUNC",
+        ["field", "source"],
+        "Timestamp truncated to `field`, e.g. `'day'`",
+        [PostgreSQL]
+    ),
+    function!(
+        "STRFTIME",
+        ["format", "time", "modifier..."],
+        "Format a date, e.g. `'%Y-%m-%d'`",
+        [SQLite]
+    ),
+    function!(
+        "DATETIME",
+        ["time", "modifier..."],
+        "Date and time as `YYYY-MM-DD HH:MM:SS`",
+        [SQLite]
+    ),
+];
+
+/// Signature help for a call to `name` with the cursor in argument
+/// `argument`. Without a known backend every variant is offered.
+pub fn signature_help(
+    name: &str,
+    argument: u32,
+    db_type: Option<&DatabaseType>,
+) -> Option<SignatureHelp> {
+    let signatures: Vec<SignatureInformation> = FUNCTIONS
+        .iter()
+        .filter(|f| f.name.eq_ignore_ascii_case(name))
+        .filter(|f| f.backends.is_empty() || db_type.is_none_or(|t| f.backends.contains(t)))
+        .map(|f| {
+            let last = f.parameters.len().saturating_sub(1) as u32;
+            let active = if is_variadic(f) {
+                argument.min(last)
+            } else {
+                argument
+            };
+            SignatureInformation {
+                label: format!("{}({})", f.name, f.parameters.join(", ")),
+                documentation: Some(Documentation::String(f.description.to_string())),
+                parameters: Some(
+                    f.parameters
+                        .iter()
+                        .map(|p| ParameterInformation {
+                            label: ParameterLabel::Simple(p.to_string()),
+                            documentation: None,
+                        })
+                        .collect(),
+                ),
+                active_parameter: Some(active),
+            }
+        })
+        .collect();
+    if signatures.is_empty() {
+        return None;
+    }
+
+    Some(SignatureHelp {
+        signatures,
+        active_signature: Some(0),
+        active_parameter: Some(argument),
+    })
+}